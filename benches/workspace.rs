@@ -0,0 +1,100 @@
+// Compares parsing a workspace on one thread with parsing it on the rayon
+// thread pool. Run with `cargo bench --features parallel`, optionally
+// followed by `-- <directory>` to parse the .pwn and .inc files under a
+// directory instead of generated ones.
+
+use rook::workspace::Session;
+use rook::workspace::Workspace;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use std::time::Instant;
+
+const GENERATED_FILES: usize = 400;
+const RUNS: u32 = 5;
+
+fn main() -> io::Result<()> {
+    let mut workspace = Workspace::new();
+    match env::args().skip(1).find(|a| !a.starts_with('-')) {
+        Some(dir) => load_dir(&mut workspace, Path::new(&dir))?,
+        None => generate(&mut workspace),
+    }
+    let bytes: usize = workspace.files().iter().map(|f| f.source.len()).sum();
+    println!("{} files, {} KiB", workspace.files().len(), bytes / 1024);
+
+    let (single, single_time) = time(|| workspace.parse());
+    let (parallel, parallel_time) = time(|| workspace.parse_parallel());
+    assert_same(&single, &parallel);
+
+    println!("{} diagnostics", single.diagnostics().count());
+    println!("single-threaded: {:?}", single_time);
+    println!("parallel:        {:?}", parallel_time);
+    println!(
+        "speedup:         {:.2}x",
+        single_time.as_secs_f64() / parallel_time.as_secs_f64()
+    );
+    Ok(())
+}
+
+// time returns the last result of `f` and the fastest of several runs.
+fn time(f: impl Fn() -> Session) -> (Session, Duration) {
+    let mut best = Duration::MAX;
+    let mut session = None;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        session = Some(f());
+        best = best.min(start.elapsed());
+    }
+    (session.unwrap(), best)
+}
+
+fn assert_same(a: &Session, b: &Session) {
+    assert_eq!(a.files.len(), b.files.len());
+    for (a, b) in a.files.iter().zip(&b.files) {
+        assert_eq!(a.name, b.name);
+        assert!(a.root == b.root, "{} parsed differently", a.name);
+        assert_eq!(a.diagnostics, b.diagnostics);
+        assert!(a.ast == b.ast, "{} interned differently", a.name);
+    }
+}
+
+fn load_dir(workspace: &mut Workspace, dir: &Path) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    entries.sort_by_key(|e| e.path());
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            load_dir(workspace, &path)?;
+        } else if matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("pwn") | Some("inc")
+        ) {
+            workspace.load(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn generate(workspace: &mut Workspace) {
+    for i in 0..GENERATED_FILES {
+        let mut source = format!("#include <a_samp>\n\nnew gFile{}[MAX_PLAYERS];\n\n", i);
+        for j in 0..50 {
+            source.push_str(&format!(
+                "stock Float:Function{}_{}(playerid, const name[], Float:amount = 1.0) {{\n\
+                 \tnew total = 0, buffer[128];\n\
+                 \tfor (new k = 0; k < 128; k++) {{\n\
+                 \t\tif (name[k] == '\\0') break;\n\
+                 \t\ttotal += k * {} + gFile{}[playerid];\n\
+                 \t}}\n\
+                 \tformat(buffer, 128, \"%s %d\" \" %f\", name, total, amount);\n\
+                 \treturn amount * float(total);\n\
+                 }}\n\n",
+                i, j, j, i
+            ));
+        }
+        workspace.add(&format!("file{}.pwn", i), &source);
+    }
+}
@@ -0,0 +1,811 @@
+use crate::ast::Argument;
+use crate::ast::CaseLabel;
+use crate::ast::Enum;
+use crate::ast::Expression;
+use crate::ast::For;
+use crate::ast::Function;
+use crate::ast::FunctionKind;
+use crate::ast::If;
+use crate::ast::Literal;
+use crate::ast::NativeAlias;
+use crate::ast::Node;
+use crate::ast::Spanned;
+use crate::ast::StateList;
+use crate::ast::StateTransition;
+use crate::ast::Statement;
+use crate::ast::StorageClass;
+use crate::ast::StringLiteral;
+use crate::ast::Switch;
+use crate::ast::Variable;
+use crate::intern::Interner;
+use crate::intern::Symbol;
+use crate::token::Span;
+use crate::token::Token;
+
+use std::ops;
+
+/// NodeId identifies a node of an `Ast`. Ids are handed out in source order,
+/// so a parent always has a lower id than its children, and they stay the
+/// same for as long as the tree exists. They only mean something to the
+/// `Ast` they came from, which is why `resolve` records the file along with
+/// the node of each name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Ast is a syntax tree stored as a flat list of nodes which refer to each
+/// other by id, for walking a tree up through parent links as well as down.
+/// It is built from the tree returned by the parser, and it is what the
+/// passes such as `resolve` walk. Since no node owns another, walking or
+/// dropping a deep tree takes no more stack than a shallow one.
+#[derive(Debug, PartialEq)]
+pub struct Ast {
+    nodes: Vec<AstNode>,
+}
+
+/// AstNode is a node of an `Ast`. The kind holds the data of the node
+/// itself, anything that is a node in its own right is one of the children.
+#[derive(Debug, PartialEq)]
+pub struct AstNode {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+}
+
+/// NodeKind is the kind of a node and the data it holds. The comment on each
+/// kind lists its children in order, `?` marks one that may be missing. Where
+/// two optional children could not be told apart by their kind, the kind
+/// records which are there. Names are symbols of the interner the arena was
+/// built with, while tags and states keep the text they are written as.
+#[derive(Debug, PartialEq)]
+pub enum NodeKind {
+    /// declarations...
+    Root,
+    /// a declaration, statement or expression that failed to parse.
+    Error,
+
+    // -
+    // Declarations
+    // -
+    /// dimensions..., initialiser?
+    Variable {
+        name: Symbol,
+        tag: String,
+        storage: StorageClass,
+        is_stock: bool,
+        is_const: bool,
+    },
+    /// size?, the size is missing for an empty `[]`.
+    Dimension,
+    /// parameters..., body?, the body is a `Block`.
+    Function {
+        kind: FunctionKind,
+        is_public: bool,
+        is_stock: bool,
+        is_static: bool,
+        name: Symbol,
+        tag: String,
+        native_alias: Option<NativeAlias>,
+        states: Option<StateList>,
+        operator: Option<Token>,
+    },
+    /// dimensions..., default?
+    Parameter {
+        name: Option<Symbol>,
+        tags: Vec<String>,
+        is_const: bool,
+        is_reference: bool,
+        is_variadic: bool,
+    },
+    /// increment amount?, members..., the amount is there when `increment`
+    /// is.
+    Enum {
+        name: Option<Symbol>,
+        tag: Option<String>,
+        increment: Option<Token>,
+    },
+    /// size?, value?, the size is a `Dimension`.
+    EnumMember {
+        name: Symbol,
+        tag: String,
+    },
+
+    // -
+    // Statements
+    // -
+    /// statements...
+    Block,
+    /// variables...
+    Declaration,
+    /// expression
+    ExpressionStatement,
+    /// condition, body, otherwise?
+    If {
+        has_otherwise: bool,
+    },
+    /// condition, body
+    While,
+    /// body, condition
+    DoWhile,
+    /// initialiser?, condition?, increment?, body
+    For {
+        has_initialiser: bool,
+        has_condition: bool,
+        has_increment: bool,
+    },
+    /// subject, cases..., default?
+    Switch,
+    /// labels..., body, a label is an expression or a `Range`.
+    Case,
+    /// first, last
+    Range,
+    /// condition?
+    StateTransition {
+        automaton: Option<String>,
+        state: String,
+    },
+    Label(Symbol),
+    Goto(Symbol),
+    /// value?
+    Return,
+    Break,
+    Continue,
+    Empty,
+
+    // -
+    // Expressions
+    // -
+    /// left, right
+    BinaryOperation(Token),
+    /// operand
+    UnaryOperation {
+        operator: Token,
+        postfix: bool,
+    },
+    /// condition, left, right
+    TernaryOperation,
+    /// operand
+    TagOverride(String),
+    /// function, arguments...
+    Call,
+    /// value?, the value is missing for a skipped argument such as `_`.
+    Argument {
+        name: Option<Symbol>,
+    },
+    /// array, index
+    Index,
+    /// array, the `a[]` of `sizeof a[]`.
+    Selector,
+    Symbol(Symbol),
+    /// a tag on its own, the operand of `tagof(Float:)`.
+    Tag(String),
+    Integer(i32),
+    Float(f32),
+    Bool(bool),
+    Char(char),
+    String(StringLiteral),
+    /// elements...
+    Array {
+        ellipsis: bool,
+    },
+}
+
+// Link is what is left to add of a link of a chain once its first operand
+// has been added.
+enum Link<'a> {
+    Operand(&'a Spanned<Expression>),
+    Arguments(&'a [Spanned<Argument>]),
+    Done,
+}
+
+impl Ast {
+    /// new builds an arena from the root returned by the parser, interning
+    /// the names in it.
+    pub fn new(root: &Node, interner: &mut Interner) -> Ast {
+        let mut builder = Builder {
+            nodes: vec![],
+            interner,
+        };
+        builder.node(root, None);
+        Ast {
+            nodes: builder.nodes,
+        }
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn get(&self, id: NodeId) -> &AstNode {
+        &self.nodes[id.index()]
+    }
+
+    pub fn kind(&self, id: NodeId) -> &NodeKind {
+        &self.get(id).kind
+    }
+
+    pub fn span(&self, id: NodeId) -> Span {
+        self.get(id).span
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.get(id).parent
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.get(id).children
+    }
+
+    /// ancestors returns the parent of a node, its parent and so on up to
+    /// the root.
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), move |&id| self.parent(id))
+    }
+
+    /// descendants returns a node and everything below it in source order.
+    pub fn descendants(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        // nodes are numbered in preorder, so a subtree is the run of ids from
+        // its root to its last descendant, found by following last children.
+        let mut last = id;
+        while let Some(&child) = self.children(last).last() {
+            last = child;
+        }
+        (id.index()..=last.index()).map(|i| NodeId(i as u32))
+    }
+
+    /// walk returns the events of entering and leaving a node and everything
+    /// below it in source order.
+    pub fn walk(&self, id: NodeId) -> Walk<'_> {
+        Walk {
+            ast: self,
+            start: Some(id),
+            stack: vec![],
+        }
+    }
+
+    /// ids returns every node in source order.
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.nodes.len()).map(|i| NodeId(i as u32))
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // remap replaces every symbol with the one at its index in `symbols`, to
+    // move a tree over to the interner the one it was built with was merged
+    // into.
+    pub(crate) fn remap(&mut self, symbols: &[Symbol]) {
+        for node in &mut self.nodes {
+            let name = match &mut node.kind {
+                NodeKind::Variable { name, .. }
+                | NodeKind::Function { name, .. }
+                | NodeKind::EnumMember { name, .. }
+                | NodeKind::Label(name)
+                | NodeKind::Goto(name)
+                | NodeKind::Symbol(name) => Some(name),
+                NodeKind::Parameter { name, .. }
+                | NodeKind::Enum { name, .. }
+                | NodeKind::Argument { name } => name.as_mut(),
+                _ => None,
+            };
+            if let Some(name) = name {
+                *name = symbols[name.index()];
+            }
+        }
+    }
+}
+
+// Builder adds the nodes of a tree to an arena, interning names as it goes.
+struct Builder<'a> {
+    nodes: Vec<AstNode>,
+    interner: &'a mut Interner,
+}
+
+impl Builder<'_> {
+    fn intern(&mut self, name: &str) -> Symbol {
+        self.interner.intern(name)
+    }
+
+    // add appends a node without children, the children are added after it
+    // so that ids stay in preorder.
+    fn add(&mut self, kind: NodeKind, span: Span, parent: Option<NodeId>) -> NodeId {
+        let id = NodeId(self.nodes.len() as u32);
+        self.nodes.push(AstNode {
+            kind,
+            span,
+            parent,
+            children: vec![],
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent.index()].children.push(id);
+        }
+        id
+    }
+
+    fn node(&mut self, node: &Node, parent: Option<NodeId>) -> NodeId {
+        let id = self.expression(&node.expr, node.span, parent);
+        for child in &node.children {
+            self.node(child, Some(id));
+        }
+        id
+    }
+
+    fn spanned_expression(&mut self, expr: &Spanned<Expression>, parent: NodeId) -> NodeId {
+        self.expression(&expr.node, expr.span, Some(parent))
+    }
+
+    fn expression(&mut self, expr: &Expression, span: Span, parent: Option<NodeId>) -> NodeId {
+        match expr {
+            Expression::GlobalScope => self.add(NodeKind::Root, span, parent),
+            Expression::Error => self.add(NodeKind::Error, span, parent),
+            Expression::Variable(variable) => self.variable(variable, span, parent),
+            Expression::Function(function) => self.function(function, span, parent),
+            Expression::Enum(e) => self.enumeration(e, span, parent),
+            Expression::BinaryOperation(_)
+            | Expression::UnaryOperation(_)
+            | Expression::Call(_)
+            | Expression::Index(_)
+            | Expression::Selector(_) => self.chain(expr, span, parent),
+            Expression::TernaryOperation(op) => {
+                let id = self.add(NodeKind::TernaryOperation, span, parent);
+                self.spanned_expression(&op.condition, id);
+                self.spanned_expression(&op.left, id);
+                self.spanned_expression(&op.right, id);
+                id
+            }
+            Expression::TagOverride(tag) => {
+                let id = self.add(NodeKind::TagOverride(tag.tag.clone()), span, parent);
+                self.spanned_expression(&tag.operand, id);
+                id
+            }
+            Expression::Symbol(name) => {
+                let name = self.intern(name);
+                self.add(NodeKind::Symbol(name), span, parent)
+            }
+            Expression::Tag(tag) => self.add(NodeKind::Tag(tag.clone()), span, parent),
+            Expression::Literal(literal) => self.literal(literal, span, parent),
+        }
+    }
+
+    // chain adds an expression whose first operand can itself be a long
+    // chain, as in `a + b + c`, `a[0][0]` or `f()()`. It goes down the first
+    // operands in a loop and adds the rest of each link on the way back up,
+    // so the nodes are numbered as they would be by recursing.
+    fn chain(
+        &mut self,
+        mut expr: &Expression,
+        mut span: Span,
+        mut parent: Option<NodeId>,
+    ) -> NodeId {
+        let mut links = vec![];
+        let top = loop {
+            let (kind, first, rest) = match expr {
+                Expression::BinaryOperation(op) => (
+                    NodeKind::BinaryOperation(op.operator.clone()),
+                    &*op.left,
+                    Link::Operand(&op.right),
+                ),
+                Expression::UnaryOperation(op) => {
+                    let kind = NodeKind::UnaryOperation {
+                        operator: op.operator.clone(),
+                        postfix: op.postfix,
+                    };
+                    (kind, &*op.operand, Link::Done)
+                }
+                Expression::Call(call) => (
+                    NodeKind::Call,
+                    &*call.function,
+                    Link::Arguments(&call.arguments),
+                ),
+                Expression::Index(index) => {
+                    (NodeKind::Index, &*index.array, Link::Operand(&index.index))
+                }
+                Expression::Selector(array) => (NodeKind::Selector, &**array, Link::Done),
+                expr => break self.expression(expr, span, parent),
+            };
+            let id = self.add(kind, span, parent);
+            links.push((id, rest));
+            expr = &first.node;
+            span = first.span;
+            parent = Some(id);
+        };
+        let top = links.first().map_or(top, |&(id, _)| id);
+        while let Some((id, rest)) = links.pop() {
+            match rest {
+                Link::Operand(operand) => {
+                    self.spanned_expression(operand, id);
+                }
+                Link::Arguments(arguments) => self.arguments(arguments, id),
+                Link::Done => {}
+            }
+        }
+        top
+    }
+
+    fn arguments(&mut self, arguments: &[Spanned<Argument>], id: NodeId) {
+        for argument in arguments {
+            let (name, value) = match &argument.node {
+                Argument::Positional(value) => (None, Some(value)),
+                Argument::Named { name, value } => (Some(self.intern(name)), Some(value)),
+                Argument::Skipped => (None, None),
+            };
+            let argument = self.add(NodeKind::Argument { name }, argument.span, Some(id));
+            if let Some(value) = value {
+                self.spanned_expression(value, argument);
+            }
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal, span: Span, parent: Option<NodeId>) -> NodeId {
+        match literal {
+            Literal::Integer(v) => self.add(NodeKind::Integer(*v), span, parent),
+            Literal::Float(v) => self.add(NodeKind::Float(*v), span, parent),
+            Literal::Bool(v) => self.add(NodeKind::Bool(*v), span, parent),
+            Literal::Char(v) => self.add(NodeKind::Char(*v), span, parent),
+            Literal::String(v) => self.add(NodeKind::String(v.clone()), span, parent),
+            Literal::Array(array) => {
+                let kind = NodeKind::Array {
+                    ellipsis: array.ellipsis,
+                };
+                let id = self.add(kind, span, parent);
+                for element in &array.elements {
+                    self.spanned_expression(element, id);
+                }
+                id
+            }
+        }
+    }
+
+    // the declarations are added apart from `expression`, which recurses for
+    // every level of an expression and so is kept small.
+    fn variable(&mut self, variable: &Variable, span: Span, parent: Option<NodeId>) -> NodeId {
+        let name = self.intern(&variable.name);
+        let id = self.add(
+            NodeKind::Variable {
+                name,
+                tag: variable.tag.clone(),
+                storage: variable.storage,
+                is_stock: variable.is_stock,
+                is_const: variable.is_const,
+            },
+            span,
+            parent,
+        );
+        self.dimensions(&variable.dimensions, span, id);
+        if let Some(initialiser) = &variable.initialiser {
+            self.spanned_expression(initialiser, id);
+        }
+        id
+    }
+
+    fn function(&mut self, function: &Function, span: Span, parent: Option<NodeId>) -> NodeId {
+        let name = self.intern(&function.name);
+        let id = self.add(
+            NodeKind::Function {
+                kind: function.kind.clone(),
+                is_public: function.is_public,
+                is_stock: function.is_stock,
+                is_static: function.is_static,
+                name,
+                tag: function.tag.clone(),
+                native_alias: function.native_alias.clone(),
+                states: function.states.clone(),
+                operator: function.operator.clone(),
+            },
+            span,
+            parent,
+        );
+        for parameter in &function.parameters {
+            let p = &parameter.node;
+            let name = p.name.as_ref().map(|name| self.intern(name));
+            let parameter = self.add(
+                NodeKind::Parameter {
+                    name,
+                    tags: p.tags.clone(),
+                    is_const: p.is_const,
+                    is_reference: p.is_reference,
+                    is_variadic: p.is_variadic,
+                },
+                parameter.span,
+                Some(id),
+            );
+            self.dimensions(&p.dimensions, span, parameter);
+            if let Some(default) = &p.default {
+                self.spanned_expression(default, parameter);
+            }
+        }
+        if let Some(body) = &function.body {
+            // the braces are not kept, so the block spans its
+            // statements, or the function when there are none.
+            let start = body.first().map_or(span, |s| s.span);
+            let end = body.last().map_or(span, |s| s.span);
+            let block = self.add(NodeKind::Block, start.to(end), Some(id));
+            for statement in body {
+                self.statement(statement, block);
+            }
+        }
+        id
+    }
+
+    fn enumeration(&mut self, e: &Enum, span: Span, parent: Option<NodeId>) -> NodeId {
+        let name = e.name.as_ref().map(|name| self.intern(name));
+        let id = self.add(
+            NodeKind::Enum {
+                name,
+                tag: e.tag.clone(),
+                increment: e.increment.as_ref().map(|i| i.operator.clone()),
+            },
+            span,
+            parent,
+        );
+        if let Some(increment) = &e.increment {
+            self.spanned_expression(&increment.amount, id);
+        }
+        for member in &e.members {
+            let m = &member.node;
+            let name = self.intern(&m.name);
+            let member = self.add(
+                NodeKind::EnumMember {
+                    name,
+                    tag: m.tag.clone(),
+                },
+                member.span,
+                Some(id),
+            );
+            if let Some(size) = &m.size {
+                let dimension = self.add(NodeKind::Dimension, size.span, Some(member));
+                self.spanned_expression(size, dimension);
+            }
+            if let Some(value) = &m.value {
+                self.spanned_expression(value, member);
+            }
+        }
+        id
+    }
+
+    // dimensions adds a `Dimension` for each dimension, an empty one takes
+    // the span of the declaration since it has no size to take it from.
+    fn dimensions(&mut self, dimensions: &[Option<Spanned<Expression>>], span: Span, id: NodeId) {
+        for dimension in dimensions {
+            match dimension {
+                Some(size) => {
+                    let dimension = self.add(NodeKind::Dimension, size.span, Some(id));
+                    self.spanned_expression(size, dimension);
+                }
+                None => {
+                    self.add(NodeKind::Dimension, span, Some(id));
+                }
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &Spanned<Statement>, parent: NodeId) -> NodeId {
+        let span = statement.span;
+        let parent = Some(parent);
+        match &statement.node {
+            Statement::Block(body) => {
+                let id = self.add(NodeKind::Block, span, parent);
+                for statement in body {
+                    self.statement(statement, id);
+                }
+                id
+            }
+            Statement::Declaration(variables) => {
+                let id = self.add(NodeKind::Declaration, span, parent);
+                for variable in variables {
+                    self.node(variable, Some(id));
+                }
+                id
+            }
+            Statement::Expression(expr) => {
+                let id = self.add(NodeKind::ExpressionStatement, span, parent);
+                self.spanned_expression(expr, id);
+                id
+            }
+            Statement::If(s) => self.if_chain(s, span, parent),
+            Statement::While(s) => {
+                let id = self.add(NodeKind::While, span, parent);
+                self.spanned_expression(&s.condition, id);
+                self.statement(&s.body, id);
+                id
+            }
+            Statement::DoWhile(s) => {
+                let id = self.add(NodeKind::DoWhile, span, parent);
+                self.statement(&s.body, id);
+                self.spanned_expression(&s.condition, id);
+                id
+            }
+            Statement::For(s) => self.for_loop(s, span, parent),
+            Statement::Switch(s) => self.switch(s, span, parent),
+            Statement::State(s) => self.state_transition(s, span, parent),
+            Statement::Label(name) => {
+                let name = self.intern(name);
+                self.add(NodeKind::Label(name), span, parent)
+            }
+            Statement::Goto(name) => {
+                let name = self.intern(name);
+                self.add(NodeKind::Goto(name), span, parent)
+            }
+            Statement::Return(value) => {
+                let id = self.add(NodeKind::Return, span, parent);
+                if let Some(value) = value {
+                    self.spanned_expression(value, id);
+                }
+                id
+            }
+            Statement::Break => self.add(NodeKind::Break, span, parent),
+            Statement::Continue => self.add(NodeKind::Continue, span, parent),
+            Statement::Empty => self.add(NodeKind::Empty, span, parent),
+            Statement::Error => self.add(NodeKind::Error, span, parent),
+        }
+    }
+
+    // if_chain adds an `if` and every `else if` after it in a loop. The
+    // `else` branch is the last child, so the nodes are numbered as they
+    // would be by recursing into it.
+    fn if_chain(&mut self, mut s: &If, mut span: Span, mut parent: Option<NodeId>) -> NodeId {
+        let mut first = None;
+        loop {
+            let kind = NodeKind::If {
+                has_otherwise: s.otherwise.is_some(),
+            };
+            let id = self.add(kind, span, parent);
+            first.get_or_insert(id);
+            self.spanned_expression(&s.condition, id);
+            self.statement(&s.body, id);
+            match &s.otherwise {
+                Some(Spanned {
+                    node: Statement::If(next),
+                    span: next_span,
+                }) => {
+                    s = next;
+                    span = *next_span;
+                    parent = Some(id);
+                }
+                Some(otherwise) => {
+                    self.statement(otherwise, id);
+                    break;
+                }
+                None => break,
+            }
+        }
+        first.unwrap()
+    }
+
+    // like the declarations, the statements that never make a chain are
+    // added apart from `statement` to keep it small.
+    fn for_loop(&mut self, s: &For, span: Span, parent: Option<NodeId>) -> NodeId {
+        let kind = NodeKind::For {
+            has_initialiser: s.initialiser.is_some(),
+            has_condition: s.condition.is_some(),
+            has_increment: s.increment.is_some(),
+        };
+        let id = self.add(kind, span, parent);
+        if let Some(initialiser) = &s.initialiser {
+            self.statement(initialiser, id);
+        }
+        if let Some(condition) = &s.condition {
+            self.spanned_expression(condition, id);
+        }
+        if let Some(increment) = &s.increment {
+            self.spanned_expression(increment, id);
+        }
+        self.statement(&s.body, id);
+        id
+    }
+
+    fn switch(&mut self, s: &Switch, span: Span, parent: Option<NodeId>) -> NodeId {
+        let id = self.add(NodeKind::Switch, span, parent);
+        self.spanned_expression(&s.subject, id);
+        for case in &s.cases {
+            let c = &case.node;
+            let case = self.add(NodeKind::Case, case.span, Some(id));
+            for label in &c.labels {
+                match label {
+                    CaseLabel::Value(value) => {
+                        self.spanned_expression(value, case);
+                    }
+                    CaseLabel::Range(first, last) => {
+                        let range = self.add(NodeKind::Range, first.span.to(last.span), Some(case));
+                        self.spanned_expression(first, range);
+                        self.spanned_expression(last, range);
+                    }
+                }
+            }
+            self.statement(&c.body, case);
+        }
+        if let Some(default) = &s.default {
+            self.statement(default, id);
+        }
+        id
+    }
+
+    fn state_transition(
+        &mut self,
+        s: &StateTransition,
+        span: Span,
+        parent: Option<NodeId>,
+    ) -> NodeId {
+        let kind = NodeKind::StateTransition {
+            automaton: s.automaton.clone(),
+            state: s.state.clone(),
+        };
+        let id = self.add(kind, span, parent);
+        if let Some(condition) = &s.condition {
+            self.spanned_expression(condition, id);
+        }
+        id
+    }
+}
+
+impl ops::Index<NodeId> for Ast {
+    type Output = AstNode;
+
+    fn index(&self, id: NodeId) -> &AstNode {
+        self.get(id)
+    }
+}
+
+/// Event is a step of a `Walk`, entering a node before any of its children
+/// or leaving it after all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Enter(NodeId),
+    Leave(NodeId),
+}
+
+/// Walk goes through a subtree in source order, keeping the nodes it is in
+/// on a stack of its own rather than recursing, so it walks a chain of any
+/// length.
+#[derive(Debug)]
+pub struct Walk<'a> {
+    ast: &'a Ast,
+    start: Option<NodeId>,
+    // the nodes entered and not yet left, each with its next child.
+    stack: Vec<(NodeId, usize)>,
+}
+
+impl<'a> Walk<'a> {
+    /// skip_children leaves the node just entered without entering its
+    /// children, the next event is the one leaving it.
+    pub fn skip_children(&mut self) {
+        if let Some((id, next)) = self.stack.last_mut() {
+            *next = self.ast.children(*id).len();
+        }
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        if let Some(id) = self.start.take() {
+            self.stack.push((id, 0));
+            return Some(Event::Enter(id));
+        }
+        let (id, next) = self.stack.last_mut()?;
+        match self.ast.children(*id).get(*next) {
+            Some(&child) => {
+                *next += 1;
+                self.stack.push((child, 0));
+                Some(Event::Enter(child))
+            }
+            None => {
+                let id = *id;
+                self.stack.pop();
+                Some(Event::Leave(id))
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+#[cfg(test)]
+use crate::arena::{Ast, Event, NodeId, NodeKind};
+#[cfg(test)]
+use crate::intern::Interner;
+#[cfg(test)]
+use crate::test_utils::arena;
+#[cfg(test)]
+use crate::token::TokenType;
+
+#[cfg(test)]
+use std::collections::HashMap;
+
+#[cfg(test)]
+fn kinds(ast: &Ast, ids: &[NodeId]) -> Vec<String> {
+    ids.iter()
+        .map(|&id| {
+            let kind = format!("{:?}", ast.kind(id));
+            kind.split(|c: char| !c.is_alphanumeric())
+                .next()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn test_arena_structure() {
+    let mut interner = Interner::new();
+    let ast = arena(
+        "new a[2][] = {1, 2};\n\
+         f(x, y = 1) {\n    if (x) return g(.a = x, _); else y++;\n}\n",
+        &mut interner,
+    );
+
+    let root = ast.root();
+    assert_eq!(ast.kind(root), &NodeKind::Root);
+    assert_eq!(ast.parent(root), None);
+    assert_eq!(
+        kinds(&ast, ast.children(root)),
+        vec!["Variable", "Function"]
+    );
+
+    let variable = ast.children(root)[0];
+    assert_eq!(
+        kinds(&ast, ast.children(variable)),
+        vec!["Dimension", "Dimension", "Array"]
+    );
+    assert_eq!(ast.children(ast.children(variable)[1]).len(), 0);
+
+    let function = ast.children(root)[1];
+    assert_eq!(
+        kinds(&ast, ast.children(function)),
+        vec!["Parameter", "Parameter", "Block"]
+    );
+    let body = ast.children(function)[2];
+    let s = ast.children(body)[0];
+    assert_eq!(
+        ast.kind(s),
+        &NodeKind::If {
+            has_otherwise: true
+        }
+    );
+    assert_eq!(
+        kinds(&ast, ast.children(s)),
+        vec!["Symbol", "Return", "ExpressionStatement"]
+    );
+
+    let call = ast.children(ast.children(s)[1])[0];
+    assert_eq!(
+        kinds(&ast, ast.children(call)),
+        vec!["Symbol", "Argument", "Argument"]
+    );
+    let skipped = ast.children(call)[2];
+    assert_eq!(ast.kind(skipped), &NodeKind::Argument { name: None });
+    assert!(ast.children(skipped).is_empty());
+
+    // every node is the parent of its children, and comes before them
+    for id in ast.ids() {
+        for &child in ast.children(id) {
+            assert_eq!(ast.parent(child), Some(id));
+            assert!(child > id);
+        }
+    }
+}
+
+// chains are added in a loop, but numbered as if recursing into each node in
+// turn
+#[test]
+fn test_arena_chains() {
+    let mut interner = Interner::new();
+    let ast = arena(
+        "f() {\n    if (a) {} else if (b) x = y[1][2] + g(3)(4) - z++; else if (c) {} else {}\n}\n",
+        &mut interner,
+    );
+
+    fn walk(ast: &Ast, id: NodeId, order: &mut Vec<NodeId>) {
+        order.push(id);
+        for &child in ast.children(id) {
+            walk(ast, child, order);
+        }
+    }
+    let mut order = vec![];
+    walk(&ast, ast.root(), &mut order);
+    assert_eq!(order, ast.ids().collect::<Vec<_>>());
+
+    let body = ast.children(ast.children(ast.root())[0])[0];
+    let s = ast.children(body)[0];
+    let otherwise = ast.children(s)[2];
+    assert_eq!(
+        kinds(&ast, ast.children(otherwise)),
+        vec!["Symbol", "ExpressionStatement", "If"]
+    );
+    let assignment = ast.children(ast.children(otherwise)[1])[0];
+    let sum = ast.children(ast.children(assignment)[1])[0];
+    assert_eq!(kinds(&ast, ast.children(sum)), vec!["Index", "Call"]);
+}
+
+#[test]
+fn test_arena_navigation() {
+    let mut interner = Interner::new();
+    let ast = arena("f() {\n    a = b + c;\n}\nnew d;\n", &mut interner);
+    let function = ast.children(ast.root())[0];
+    let c = ast
+        .descendants(function)
+        .find(|&id| ast.kind(id) == &NodeKind::Symbol(interner.get("c").unwrap()))
+        .unwrap();
+
+    assert_eq!(
+        kinds(&ast, &ast.ancestors(c).collect::<Vec<_>>()),
+        vec![
+            "BinaryOperation",
+            "BinaryOperation",
+            "ExpressionStatement",
+            "Block",
+            "Function",
+            "Root"
+        ]
+    );
+    assert_eq!(ast.span(c).line_start, 2);
+    assert_eq!(ast.span(c).column_start, 13);
+
+    // descendants stop at the end of the subtree
+    let descendants: Vec<_> = ast.descendants(function).collect();
+    assert_eq!(descendants.len(), 8);
+    assert_eq!(descendants[0], function);
+    assert_eq!(ast.descendants(ast.root()).count(), ast.len());
+    assert_eq!(ast.descendants(c).collect::<Vec<_>>(), vec![c]);
+
+    match ast.kind(ast.parent(c).unwrap()) {
+        NodeKind::BinaryOperation(op) => assert_eq!(op.token_type, TokenType::Plus),
+        k => panic!("expected binary operation, got {:?}", k),
+    }
+}
+
+#[test]
+fn test_arena_side_table() {
+    let mut interner = Interner::new();
+    let ast = arena("f(a) {\n    return a * 2;\n}\n", &mut interner);
+
+    // record the depth of every node, then read it back by id
+    let mut depths = HashMap::new();
+    for id in ast.ids() {
+        let depth = ast.parent(id).map_or(0, |p| depths[&p] + 1);
+        depths.insert(id, depth);
+    }
+
+    let two = ast
+        .ids()
+        .find(|&id| ast.kind(id) == &NodeKind::Integer(2))
+        .unwrap();
+    assert_eq!(depths[&two], 5);
+    assert_eq!(ast[two].parent, ast.parent(two));
+}
+
+#[test]
+fn test_arena_walk() {
+    let mut interner = Interner::new();
+    let ast = arena("f() {\n    a = sizeof b + c;\n}\n", &mut interner);
+
+    // the walk enters and leaves each node as recursing would, and skipping
+    // a node leaves it without entering its children
+    fn walk(ast: &Ast, id: NodeId, events: &mut Vec<Event>) {
+        events.push(Event::Enter(id));
+        if let NodeKind::UnaryOperation { .. } = ast.kind(id) {
+        } else {
+            for &child in ast.children(id) {
+                walk(ast, child, events);
+            }
+        }
+        events.push(Event::Leave(id));
+    }
+    let mut expected = vec![];
+    walk(&ast, ast.root(), &mut expected);
+
+    let mut events = vec![];
+    let mut walk = ast.walk(ast.root());
+    while let Some(event) = walk.next() {
+        if let Event::Enter(id) = event {
+            if let NodeKind::UnaryOperation { .. } = ast.kind(id) {
+                walk.skip_children();
+            }
+        }
+        events.push(event);
+    }
+    assert_eq!(events, expected);
+    assert_eq!(events.len(), 2 * (ast.len() - 1));
+
+    // a walk can start anywhere, and ends with the node it started at
+    let function = ast.children(ast.root())[0];
+    let events: Vec<_> = ast.walk(function).collect();
+    assert_eq!(events.first(), Some(&Event::Enter(function)));
+    assert_eq!(events.last(), Some(&Event::Leave(function)));
+    assert_eq!(events.len(), 2 * ast.descendants(function).count());
+}
@@ -1,150 +1,2296 @@
+use crate::cst::Checkpoint;
+use crate::cst::Event;
+use crate::cst::SyntaxKind;
+use crate::error::Diagnostic;
+use crate::error::ParseError;
+use crate::lexer::unescape;
+use crate::token::Span;
 use crate::token::Token;
 use crate::token::TokenType;
+use crate::token::TokenValue;
 
-#[derive(Debug)]
+use std::fmt;
+use std::mem;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+/// Spanned is an AST node along with the span of source it was parsed from.
+/// Spans are ignored when comparing so that trees parsed from differently
+/// laid out source compare equal, compare `span` directly where it matters.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Spanned<T> {
+        Spanned { node, span }
+    }
+}
+
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Spanned<T> {
+        Spanned::new(node, Span::default())
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+impl<T> DerefMut for Spanned<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.node
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Spanned<T>) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T: PartialEq> PartialEq<T> for Spanned<T> {
+    fn eq(&self, other: &T) -> bool {
+        self.node == *other
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Spanned<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.node.fmt(f)?;
+        write!(f, " @ {}", self.span)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Expression {
     GlobalScope,
+    /// Variable is the declaration of a variable or constant, which is only
+    /// found as a node of its own, at the top level or in a `Declaration`
+    /// statement. A name used inside an expression is a `Symbol`.
     Variable(Box<Variable>),
+    Function(Box<Function>),
+    Enum(Box<Enum>),
     BinaryOperation(Box<BinaryOperation>),
+    UnaryOperation(Box<UnaryOperation>),
+    TernaryOperation(Box<TernaryOperation>),
+    TagOverride(Box<TagOverride>),
+    Call(Box<Call>),
+    Index(Box<Index>),
+    /// Symbol is a name used in an expression, such as `a` in `a + 1`. It
+    /// only holds the name, the tag, dimensions and storage belong to the
+    /// `Variable` that declares it, and a tag written at the use, as in
+    /// `_:a`, is a `TagOverride` around it. The name may also be that of a
+    /// function, a constant or an enum member, see `resolve` for finding its
+    /// declaration.
+    Symbol(String),
+    /// Tag is a tag on its own, which is only found as the operand of
+    /// `tagof`, such as `tagof(Float:)`.
+    Tag(String),
+    /// Selector is an array followed by empty brackets, which is only found
+    /// as the operand of `sizeof`, such as `sizeof a[]` for the size of the
+    /// second dimension of `a`.
+    Selector(Box<Spanned<Expression>>),
+    Literal(Literal),
+    Error,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Variable {
     pub name: String,
     pub tag: String,
+    pub dimensions: Vec<Option<Spanned<Expression>>>,
+    pub initialiser: Option<Spanned<Expression>>,
+    pub storage: StorageClass,
+    pub is_stock: bool,
+    pub is_const: bool,
 }
 
-#[derive(Debug)]
+/// StorageClass is how a variable is stored, from the keywords it is declared
+/// with in any order. `static` and `public` make it `Static` or `Public`, a
+/// declaration with neither is `New`, except that a bare `const` declares a
+/// symbolic constant rather than a variable. `stock` and `const` are flags of
+/// the variable beside it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StorageClass {
+    New,
+    Static,
+    Public,
+    Const,
+}
+
+/// Function is a function definition or declaration. Plain, `stock`,
+/// `public` and `static` functions are definitions if they have a body,
+/// otherwise they are prototypes.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Function {
+    pub kind: FunctionKind,
+    pub is_public: bool,
+    pub is_stock: bool,
+    pub is_static: bool,
+    pub name: String,
+    pub tag: String,
+    pub parameters: Vec<Spanned<Parameter>>,
+    pub body: Option<Vec<Spanned<Statement>>>,
+    pub native_alias: Option<NativeAlias>,
+    pub states: Option<StateList>,
+    pub operator: Option<Token>,
+}
+
+impl Function {
+    /// is_operator reports whether this is a user-defined operator such as
+    /// `Float:operator+(Float:a, Float:b)`, its name is `operator` followed
+    /// by the operator itself.
+    pub fn is_operator(&self) -> bool {
+        self.operator.is_some()
+    }
+
+    /// is_state_entry reports whether this is an `entry()` function, which
+    /// runs when its automaton switches to one of its states.
+    pub fn is_state_entry(&self) -> bool {
+        self.name == "entry" && self.states.is_some()
+    }
+}
+
+/// StateList is the `<automaton:state, ...>` qualifier on a function that
+/// belongs to a state machine. An empty list, written `<>`, is the fallback
+/// implementation used when no other state matches.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateList {
+    pub automaton: Option<String>,
+    pub states: Vec<String>,
+}
+
+/// Parameter is a single function parameter, a variadic parameter such as
+/// `{Float,_}:...` has no name.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parameter {
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+    pub is_const: bool,
+    pub is_reference: bool,
+    pub is_variadic: bool,
+    pub dimensions: Vec<Option<Spanned<Expression>>>,
+    pub default: Option<Spanned<Expression>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FunctionKind {
+    Definition,
+    Forward,
+    Native,
+}
+
+/// NativeAlias is the part after `=` in a native declaration, either a
+/// negative index such as `= -1` or the name it is exported as.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NativeAlias {
+    Index(i32),
+    Name(String),
+}
+
+/// Enum is an enumeration such as `enum E_PLAYER { E_NAME[24], Float:E_HEALTH }`
+/// or `enum E:(<<= 1) { A = 1, B }`, the name of an enum is also a tag.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Enum {
+    pub name: Option<String>,
+    pub tag: Option<String>,
+    pub increment: Option<EnumIncrement>,
+    pub members: Vec<Spanned<EnumMember>>,
+}
+
+/// EnumIncrement is the operator and amount used to step from one member to
+/// the next, by default this is `+= 1`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumIncrement {
+    pub operator: Token,
+    pub amount: Spanned<Expression>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumMember {
+    pub name: String,
+    pub tag: String,
+    pub size: Option<Spanned<Expression>>,
+    pub value: Option<Spanned<Expression>>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BinaryOperation {
     pub operator: Token,
-    pub left: Box<Expression>,
-    pub right: Box<Expression>,
+    pub left: Box<Spanned<Expression>>,
+    pub right: Box<Spanned<Expression>>,
+}
+
+/// UnaryOperation is a prefix operator such as `-a`, `++a`, `tagof a` or
+/// `sizeof a`, or a postfix operator such as `a++` or `10 char`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnaryOperation {
+    pub operator: Token,
+    pub operand: Box<Spanned<Expression>>,
+    pub postfix: bool,
+}
+
+/// TernaryOperation is the conditional operator `condition ? left : right`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TernaryOperation {
+    pub condition: Box<Spanned<Expression>>,
+    pub left: Box<Spanned<Expression>>,
+    pub right: Box<Spanned<Expression>>,
+}
+
+/// TagOverride changes the tag of an expression, such as `_:value` or
+/// `Float:float(i)`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TagOverride {
+    pub tag: String,
+    pub operand: Box<Spanned<Expression>>,
 }
 
-/// Node represents an actual AST node on the syntax graph.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Call {
+    pub function: Box<Spanned<Expression>>,
+    pub arguments: Vec<Spanned<Argument>>,
+}
+
+/// Argument is a single argument at a call site, either positional, named
+/// such as `.name = value` or skipped with `_` to use the default value.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Argument {
+    Positional(Spanned<Expression>),
+    Named {
+        name: String,
+        value: Spanned<Expression>,
+    },
+    Skipped,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Index {
+    pub array: Box<Spanned<Expression>>,
+    pub index: Box<Spanned<Expression>>,
+}
+
+// The links of a chain such as `a + b + c`, `f()()`, `a[0][0]` or `else if`
+// each own the next, and generated code can chain far more of them than the
+// stack could recurse through. So dropping a link takes the links below it
+// apart one at a time rather than letting each drop the next.
+
+impl Drop for BinaryOperation {
+    fn drop(&mut self) {
+        dismantle(&mut self.left);
+    }
+}
+
+impl Drop for UnaryOperation {
+    fn drop(&mut self) {
+        dismantle(&mut self.operand);
+    }
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        dismantle(&mut self.function);
+    }
+}
+
+impl Drop for Index {
+    fn drop(&mut self) {
+        dismantle(&mut self.array);
+    }
+}
+
+// dismantle drops the chain held by the first operand of a link. Each link
+// has its own first operand taken before it is dropped, so it has nothing
+// below it to drop.
+fn dismantle(first: &mut Spanned<Expression>) {
+    let mut next = mem::replace(&mut first.node, Expression::Error);
+    loop {
+        let first = match &mut next {
+            Expression::BinaryOperation(op) => &mut op.left,
+            Expression::UnaryOperation(op) => &mut op.operand,
+            Expression::Call(call) => &mut call.function,
+            Expression::Index(index) => &mut index.array,
+            Expression::Selector(array) => array,
+            _ => return,
+        };
+        next = mem::replace(&mut first.node, Expression::Error);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Statement {
+    Block(Vec<Spanned<Statement>>),
+    Declaration(Vec<Node>),
+    Expression(Spanned<Expression>),
+    If(Box<If>),
+    While(Box<While>),
+    DoWhile(Box<DoWhile>),
+    For(Box<For>),
+    Switch(Box<Switch>),
+    State(Box<StateTransition>),
+    Label(String),
+    Goto(String),
+    Return(Option<Spanned<Expression>>),
+    Break,
+    Continue,
+    Empty,
+    Error,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct If {
+    pub condition: Spanned<Expression>,
+    pub body: Spanned<Statement>,
+    pub otherwise: Option<Spanned<Statement>>,
+}
+
+impl Drop for If {
+    fn drop(&mut self) {
+        let mut otherwise = self.otherwise.take();
+        while let Some(Spanned {
+            node: Statement::If(mut next),
+            ..
+        }) = otherwise
+        {
+            otherwise = next.otherwise.take();
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct While {
+    pub condition: Spanned<Expression>,
+    pub body: Spanned<Statement>,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DoWhile {
+    pub body: Spanned<Statement>,
+    pub condition: Spanned<Expression>,
+}
+
+/// For is a `for` loop, the initialiser is either a declaration or an
+/// expression statement.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct For {
+    pub initialiser: Option<Spanned<Statement>>,
+    pub condition: Option<Spanned<Expression>>,
+    pub increment: Option<Spanned<Expression>>,
+    pub body: Spanned<Statement>,
+}
+
+/// Switch is a `switch` statement, Pawn cases never fall through so each
+/// case holds exactly one statement.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Switch {
+    pub subject: Spanned<Expression>,
+    pub cases: Vec<Spanned<Case>>,
+    pub default: Option<Spanned<Statement>>,
+}
+
+/// StateTransition is a `state (condition) automaton:name;` statement, which
+/// switches the automaton to another state if the condition holds.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateTransition {
+    pub condition: Option<Spanned<Expression>>,
+    pub automaton: Option<String>,
+    pub state: String,
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Case {
+    pub labels: Vec<CaseLabel>,
+    pub body: Spanned<Statement>,
+}
+
+/// CaseLabel is a single value or an inclusive range such as `5..10` in a
+/// `case` list.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CaseLabel {
+    Value(Spanned<Expression>),
+    Range(Spanned<Expression>, Spanned<Expression>),
+}
+
+/// Literal is a constant written directly in the source. `true` and `false`
+/// are the predefined constants of the `bool` tag.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Literal {
+    Integer(i32),
+    Float(f32),
+    Bool(bool),
+    Char(char),
+    String(StringLiteral),
+    Array(Box<Array>),
+}
+
+/// StringLiteral is a string such as `"abc"`, or a packed string such as
+/// `!"abc"`. Adjacent strings are joined into one by the compiler, so
+/// `"abc" "def"` and `"abc" ... "def"` are a single literal with two parts.
+/// Each part keeps its escape sequences as written and its own span.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StringLiteral {
+    pub packed: bool,
+    pub parts: Vec<Spanned<String>>,
+}
+
+impl StringLiteral {
+    /// new returns an unpacked string of a single part. The text is used as
+    /// written, so escape sequences in it are kept.
+    pub fn new(text: &str) -> StringLiteral {
+        StringLiteral {
+            packed: false,
+            parts: vec![Spanned::from(String::from(text))],
+        }
+    }
+
+    /// value returns the text the literal stands for, with the parts joined
+    /// and their escape sequences replaced.
+    pub fn value(&self) -> String {
+        self.parts.iter().map(|part| unescape(part)).collect()
+    }
+}
+
+/// Array is an array initialiser such as `{1, 2, 3}`. When it ends with an
+/// ellipsis, as in `{0, 1, ...}`, the remaining elements continue the
+/// progression of the last two.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Array {
+    pub elements: Vec<Spanned<Expression>>,
+    pub ellipsis: bool,
+}
+
+/// Node represents an actual AST node on the syntax graph. Like `Spanned`,
+/// the span is ignored when comparing nodes. Only the global scope has
+/// children, the declarations at the top level of the file.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
     pub expr: Expression,
+    pub span: Span,
     pub tokens: Vec<Token>,
     pub children: Vec<Node>,
 }
 
+impl Node {
+    pub fn new(expr: Expression) -> Node {
+        Node {
+            expr,
+            span: Span::default(),
+            tokens: vec![],
+            children: vec![],
+        }
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        self.expr == other.expr && self.tokens == other.tokens && self.children == other.children
+    }
+}
+
+/// Options change how the parser reads source. Some of them can also be
+/// switched from the source itself with a `#pragma`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Options {
+    /// optional_semicolons lets a statement end at the end of a line instead
+    /// of a `;`, as long as the statement is complete there. It is the same
+    /// as `#pragma semicolon 0`, and on by default as in pawncc. Turn it off,
+    /// or use `#pragma semicolon 1`, to report every missing `;`.
+    pub optional_semicolons: bool,
+    /// max_depth is how deeply statements and expressions may nest inside
+    /// one another, such as brackets, blocks, the operand of a unary operator
+    /// or the right side of an assignment. The parser recurses for each
+    /// level, so going deeper is an error rather than a stack overflow.
+    /// Chains such as `a || b || c`, `f()()`, `a[0][0]` or `else if` are read
+    /// in a loop and may be any length.
+    pub max_depth: usize,
+}
+
+/// DEFAULT_MAX_DEPTH is the default nesting limit. A level of brackets takes
+/// around 12 KB of stack in a debug build, so this leaves room to spare on an
+/// 8 MB stack for the parser.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            optional_semicolons: true,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    options: Options,
+    // whether `tag:` is treated as a tag override in expressions, this is
+    // switched off between the `?` and `:` of a ternary operation.
+    allow_tags: bool,
+    // whether an array may be followed by empty brackets, as in the operand
+    // of `sizeof a[]`.
+    allow_selectors: bool,
+    // how deeply the node being parsed is nested, see Options::max_depth.
+    depth: usize,
+    diagnostics: Vec<Diagnostic>,
+    // events build the lossless syntax tree, see the cst module.
+    events: Vec<Event>,
+    checkpoints: usize,
+    open: usize,
     pub root: Node,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
+        Parser::with_options(tokens, Options::default())
+    }
+
+    pub fn with_options(tokens: Vec<Token>, options: Options) -> Parser {
         Parser {
-            tokens: tokens,
+            tokens: tokens
+                .into_iter()
+                .filter(|t| t.token_type != TokenType::Comment)
+                .collect(),
             current: 0,
-            root: Node {
-                expr: Expression::GlobalScope,
-                tokens: vec![],
-                children: vec![],
-            },
+            options,
+            allow_tags: true,
+            allow_selectors: false,
+            depth: 0,
+            diagnostics: vec![],
+            events: vec![],
+            checkpoints: 0,
+            open: 0,
+            root: Node::new(Expression::GlobalScope),
         }
     }
 
-    pub fn parse(&mut self) -> Result<(), String> {
+    /// parse parses every top-level declaration. An error does not stop the
+    /// parser, it leaves an `Error` node in place of the declaration and skips
+    /// ahead to the next one. Every error found is returned at the end.
+    pub fn parse(&mut self) -> Result<(), Vec<Diagnostic>> {
+        if let (Some(first), Some(last)) = (self.tokens.first(), self.tokens.last()) {
+            self.root.span = first.span().to(last.span());
+        }
+
         while self.current < self.tokens.len() {
-            let token = &self.tokens[self.current];
+            if self.peek_is(TokenType::Directive) {
+                self.skip_directive();
+                continue;
+            }
 
-            let node = match token.token_type {
-                TokenType::New => Some(self.parse_declaration()?),
-                _ => None,
-            };
+            let start = self.current;
+            let start_span = self.current_span();
+            let checkpoint = self.checkpoint();
+            self.depth = 0;
+            match self.parse_top_level() {
+                Ok(nodes) => self.root.children.extend(nodes),
+                Err(diagnostic) => {
+                    // a declaration nested too deeply is skipped from its
+                    // start, so that the braces it opened are counted too.
+                    if matches!(diagnostic.error, ParseError::NestingTooDeep { .. }) {
+                        self.current = start;
+                    }
+                    self.report(diagnostic);
+                    self.close_to(checkpoint);
+                    self.synchronise(start, is_declaration_start);
+                    // there is no outer block at the top level so a closing
+                    // brace here belongs to whatever failed to parse.
+                    if self.peek_is(TokenType::RightBrace) {
+                        self.current += 1;
+                    }
+                    self.start_at(checkpoint, SyntaxKind::Error);
+                    self.finish();
+
+                    let mut node = Node::new(Expression::Error);
+                    node.span = self.span_from(start_span);
+                    self.root.children.push(node);
+                }
+            }
+        }
+
+        if self.diagnostics.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.diagnostics))
+        }
+    }
+
+    // parse_fragment parses the whole input with `f`, for the entry points in
+    // the parse module. Input that `f` leaves over is an error, and if `f`
+    // fails the node is built by `fallback` from the span of the input.
+    pub(crate) fn parse_fragment<T>(
+        &mut self,
+        f: impl FnOnce(&mut Parser) -> Result<T, Diagnostic>,
+        fallback: impl FnOnce(Span) -> T,
+    ) -> (T, Vec<Diagnostic>) {
+        while self.peek_is(TokenType::Directive) {
+            self.skip_directive();
+        }
+
+        let node = match f(self) {
+            Ok(node) => {
+                if self.current < self.tokens.len() {
+                    let found = self.peek_type();
+                    self.report(self.error(ParseError::TrailingInput { found }));
+                }
+                node
+            }
+            Err(diagnostic) => {
+                self.report(diagnostic);
+                let span = match (self.tokens.first(), self.tokens.last()) {
+                    (Some(first), Some(last)) => first.span().to(last.span()),
+                    _ => Span::default(),
+                };
+                fallback(span)
+            }
+        };
+
+        (node, std::mem::take(&mut self.diagnostics))
+    }
+
+    pub(crate) fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn start(&mut self, kind: SyntaxKind) {
+        self.events.push(Event::Start {
+            kind,
+            token: self.current,
+        });
+        self.open += 1;
+    }
+
+    fn finish(&mut self) {
+        self.events.push(Event::Finish {
+            token: self.current,
+        });
+        self.open -= 1;
+    }
+
+    // node wraps everything consumed by `f` in a syntax node of `kind`.
+    fn node<T>(
+        &mut self,
+        kind: SyntaxKind,
+        f: impl FnOnce(&mut Parser) -> Result<T, Diagnostic>,
+    ) -> Result<T, Diagnostic> {
+        self.start(kind);
+        let result = f(self);
+        self.finish();
+        result
+    }
+
+    // checkpoint marks a place that a node can later be started from with
+    // start_at, once it is known what the node is.
+    fn checkpoint(&mut self) -> Checkpoint {
+        self.events.push(Event::Checkpoint {
+            token: self.current,
+        });
+        self.checkpoints += 1;
+        Checkpoint {
+            index: self.checkpoints - 1,
+            open: self.open,
+        }
+    }
 
-            if let Some(node) = node {
-                self.root.children.push(node);
+    fn start_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        self.events.push(Event::StartAt {
+            kind,
+            checkpoint: checkpoint.index,
+            token: self.current,
+        });
+        self.open += 1;
+    }
+
+    // close_to finishes the nodes left open by an error since `checkpoint`.
+    fn close_to(&mut self, checkpoint: Checkpoint) {
+        while self.open > checkpoint.open {
+            self.finish();
+        }
+    }
+
+    pub(crate) fn parse_top_level(&mut self) -> Result<Vec<Node>, Diagnostic> {
+        let start = self.current_span();
+        match self.peek_type() {
+            Some(TokenType::Enum) => {
+                let mut node = self.node(SyntaxKind::Enum, |p| p.parse_enum())?;
+                node.span = self.span_from(start);
+                Ok(vec![node])
+            }
+            Some(TokenType::New) | Some(TokenType::Const) => self.parse_declaration(),
+            Some(TokenType::Forward)
+            | Some(TokenType::Native)
+            | Some(TokenType::Public)
+            | Some(TokenType::Stock)
+            | Some(TokenType::Static)
+            | Some(TokenType::Symbol) => {
+                if self.peek_function() {
+                    let mut node = self.node(SyntaxKind::Function, |p| p.parse_function())?;
+                    node.span = self.span_from(start);
+                    Ok(vec![node])
+                } else {
+                    self.parse_declaration()
+                }
             }
+            found => Err(self.error(ParseError::ExpectedDeclaration { found })),
+        }
+    }
 
+    // synchronise skips the rest of something that failed to parse. It stops
+    // after a `;` or a balanced `{...}`, or before a `}` that closes an outer
+    // block or a keyword for which `is_start` is true. At least one token is
+    // always skipped so the same error can not be reported twice.
+    fn synchronise(&mut self, start: usize, is_start: fn(TokenType) -> bool) {
+        // a brace is skipped along with the block it opens
+        if self.current == start && !self.peek_is(TokenType::LeftBrace) {
             self.current += 1;
         }
 
-        Ok(())
+        let mut depth = 0;
+        while let Some(token) = self.peek_type() {
+            match token {
+                TokenType::Semicolon if depth == 0 => {
+                    self.current += 1;
+                    return;
+                }
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 0 => return,
+                TokenType::RightBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.current += 1;
+                        // an array initialiser ends with a semicolon
+                        if self.peek_is(TokenType::Semicolon) {
+                            self.current += 1;
+                        }
+                        return;
+                    }
+                }
+                t if depth == 0 && is_start(t) => return,
+                _ => (),
+            }
+            self.current += 1;
+        }
+    }
+
+    // report records a diagnostic, unless it is at the same place as the last
+    // one, which happens when an error such as an unexpected end of file is
+    // seen by several enclosing blocks.
+    fn report(&mut self, diagnostic: Diagnostic) {
+        if self.diagnostics.last().map(|d| d.span) != Some(diagnostic.span) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn current_span(&self) -> Span {
+        match self.tokens.get(self.current) {
+            Some(token) => token.span(),
+            None => self
+                .tokens
+                .last()
+                .map(|t| t.span().end())
+                .unwrap_or_default(),
+        }
+    }
+
+    // previous_span returns the span of the last token consumed.
+    fn previous_span(&self) -> Span {
+        match self.current.checked_sub(1).and_then(|i| self.tokens.get(i)) {
+            Some(token) => token.span(),
+            None => self.current_span(),
+        }
+    }
+
+    // span_from returns a span from `start` to the end of the last token
+    // consumed.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous_span())
+    }
+
+    fn spanned<T>(&self, node: T, start: Span) -> Spanned<T> {
+        Spanned::new(node, self.span_from(start))
+    }
+
+    fn error(&self, error: ParseError) -> Diagnostic {
+        Diagnostic::new(error, self.current_span())
+    }
+
+    // unexpected reports that the current token is not one of `expected`.
+    fn unexpected(&self, expected: &[TokenType]) -> Diagnostic {
+        self.error(ParseError::ExpectedToken {
+            expected: expected.to_vec(),
+            found: self.peek_type(),
+        })
+    }
+
+    fn unclosed_block(&self, open: Span) -> Diagnostic {
+        self.error(ParseError::UnclosedBlock)
+            .with_label(open, "the block starts here")
     }
 
     fn next(&mut self) -> &Token {
         self.current += 1;
-        &self.tokens[self.current]
+        &self.tokens[self.current - 1]
     }
 
-    fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+    fn peek_type(&self) -> Option<TokenType> {
+        self.tokens.get(self.current).map(|t| t.token_type)
     }
 
-    fn parse_declaration(&mut self) -> Result<Node, String> {
-        let token = match &self.next().token_type {
-            // TODO: deal with new const, new static, new stock etc
-            TokenType::Symbol => TokenType::Symbol,
-            token @ _ => return Err(format!("expected symbol, found {:?}", token)),
-        };
+    fn peek_is(&self, token_type: TokenType) -> bool {
+        self.peek_type() == Some(token_type)
+    }
+
+    fn peek_tag(&self) -> bool {
+        self.is_tag_at(self.current)
+    }
+
+    // is_tag_at checks for a `tag:` prefix, the colon must immediately follow
+    // the tag name so `a ? b : c` is never mistaken for one.
+    fn is_tag_at(&self, index: usize) -> bool {
+        match (self.tokens.get(index), self.tokens.get(index + 1)) {
+            (Some(tag), Some(colon)) => {
+                tag.token_type == TokenType::Symbol
+                    && colon.token_type == TokenType::Colon
+                    && tag.line_end == colon.line_start
+                    && tag.column_end == colon.column_start
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, token_type: TokenType) -> Result<&Token, Diagnostic> {
+        match self.peek_type() {
+            Some(t) if t == token_type => Ok(self.next()),
+            _ => Err(self.unexpected(&[token_type])),
+        }
+    }
+
+    // peek_function looks past any specifiers and the return tag to check
+    // whether a declaration is a function rather than a variable.
+    fn peek_function(&self) -> bool {
+        let mut index = self.current;
+        loop {
+            match self.tokens.get(index).map(|t| t.token_type) {
+                Some(TokenType::Forward) | Some(TokenType::Native) => return true,
+                Some(TokenType::Public) | Some(TokenType::Stock) | Some(TokenType::Static) => {
+                    index += 1
+                }
+                _ => break,
+            }
+        }
+        if self.is_tag_at(index) {
+            index += 2;
+        }
+        if self.tokens.get(index).map(|t| t.token_type) == Some(TokenType::Operator) {
+            return true;
+        }
 
-        let mut node = self.parse_symbol()?;
+        matches!(
+            (
+                self.tokens.get(index).map(|t| t.token_type),
+                self.tokens.get(index + 1).map(|t| t.token_type),
+            ),
+            (Some(TokenType::Symbol), Some(TokenType::LeftBracket))
+        )
+    }
 
-        if self.peek().token_type == TokenType::Equal {
+    fn parse_declaration(&mut self) -> Result<Vec<Node>, Diagnostic> {
+        // (new|static|stock|public|const)* <symbol> (= <expr>)(, ...)* ;
+        // const <symbol> = <expr>(, ...)* ;
+        self.start(SyntaxKind::Declaration);
+        let mut storage = None;
+        let mut is_stock = false;
+        let mut is_const = false;
+        loop {
+            match self.peek_type() {
+                Some(TokenType::New) => {
+                    storage.get_or_insert(StorageClass::New);
+                }
+                Some(TokenType::Static) => storage = Some(StorageClass::Static),
+                Some(TokenType::Public) => storage = Some(StorageClass::Public),
+                Some(TokenType::Stock) => is_stock = true,
+                Some(TokenType::Const) => is_const = true,
+                _ => break,
+            }
             self.current += 1;
-            node.children.push(self.parse_expression()?);
+        }
+        let storage = match storage {
+            Some(storage) => storage,
+            None if is_stock => StorageClass::New,
+            None if is_const => StorageClass::Const,
+            None => {
+                return Err(self.unexpected(&[
+                    TokenType::New,
+                    TokenType::Static,
+                    TokenType::Stock,
+                    TokenType::Public,
+                    TokenType::Const,
+                ]))
+            }
         };
 
+        let mut nodes = vec![];
+        loop {
+            self.start(SyntaxKind::Variable);
+            let start = self.current_span();
+            let mut variable = self.parse_symbol()?;
+            variable.storage = storage;
+            variable.is_stock = is_stock;
+            variable.is_const = is_const;
+            if self.peek_is(TokenType::Assign) {
+                self.current += 1;
+                variable.initialiser = Some(self.parse_expression()?);
+            }
+
+            let mut node = Node::new(Expression::Variable(Box::new(variable)));
+            node.span = self.span_from(start);
+            self.finish();
+            nodes.push(node);
+
+            if !self.peek_is(TokenType::Comma) {
+                break;
+            }
+            self.current += 1;
+        }
+
         self.expect_semicolon()?;
+        self.finish();
 
-        Ok(node)
+        Ok(nodes)
     }
 
-    fn expect_symbol(&mut self) -> Result<TokenType, String> {
-        match &self.next().token_type {
-            TokenType::Symbol => Ok(TokenType::Symbol),
-            token @ _ => Err(format!("expected symbol, found {:?}", token)),
+    // skip_directive skips a preprocessor directive, which runs until the end
+    // of the line it starts on. The only directive that changes parsing is
+    // `#pragma semicolon`, which is applied here.
+    fn skip_directive(&mut self) {
+        self.start(SyntaxKind::Directive);
+        let start = self.current + 1;
+        let line = self.next().line_start;
+        while let Some(token) = self.tokens.get(self.current) {
+            if token.line_start != line {
+                break;
+            }
+            self.current += 1;
         }
-    }
+        self.finish();
 
-    fn expect_semicolon(&mut self) -> Result<TokenType, String> {
-        match &self.next().token_type {
-            TokenType::Semicolon => Ok(TokenType::Semicolon),
-            token @ _ => Err(format!("expected semicolon, found {:?}", token)),
+        let mut words = self.tokens[start..self.current].iter().map(|t| &t.value);
+        if let (
+            Some(Some(TokenValue::String(pragma))),
+            Some(Some(TokenValue::String(option))),
+            Some(Some(TokenValue::Integer(value))),
+        ) = (words.next(), words.next(), words.next())
+        {
+            if pragma == "pragma" && option == "semicolon" {
+                self.options.optional_semicolons = *value == 0;
+            }
         }
     }
 
-    fn parse_expression(&mut self) -> Result<Node, String> {
-        Err(String::from("not implemented"))
-    }
+    fn parse_function(&mut self) -> Result<Node, Diagnostic> {
+        // (forward|native|public|stock|static)* (tag:)(ident|operator<op>)(<params>)
+        // (<states>) (<body>|;)
+        let mut function = Function {
+            kind: FunctionKind::Definition,
+            is_public: false,
+            is_stock: false,
+            is_static: false,
+            name: String::new(),
+            tag: String::from("_"),
+            parameters: vec![],
+            body: None,
+            native_alias: None,
+            states: None,
+            operator: None,
+        };
 
-    fn parse_symbol(&mut self) -> Result<Node, String> {
-        // (tag:)ident([<expr>])
-        let token = self.expect_symbol()?;
+        loop {
+            match self.peek_type() {
+                Some(TokenType::Forward) => function.kind = FunctionKind::Forward,
+                Some(TokenType::Native) => function.kind = FunctionKind::Native,
+                Some(TokenType::Public) => function.is_public = true,
+                Some(TokenType::Stock) => function.is_stock = true,
+                Some(TokenType::Static) => function.is_static = true,
+                _ => break,
+            }
+            self.current += 1;
+        }
 
-        if self.peek().token_type == TokenType::Colon {
+        if let Some(tag) = self.parse_tag() {
+            function.tag = tag;
+        }
+        if self.peek_is(TokenType::Operator) {
             self.current += 1;
+            let operator = match self.peek_type() {
+                Some(t) if is_overloadable(t) => self.next().clone(),
+                found => return Err(self.error(ParseError::InvalidOperator { found })),
+            };
+            function.name = format!("operator{}", operator.token_type);
+            function.operator = Some(operator);
+        } else {
+            function.name = self.expect_symbol()?;
+        }
 
-            let tag = &token;
-            let symbol = self.expect_symbol()?;
+        function.parameters = self.node(SyntaxKind::ParameterList, |p| {
+            p.expect(TokenType::LeftBracket)?;
+            p.parse_parameters()
+        })?;
 
-            return Ok(Node {
-                expr: Expression::Variable(Box::new(Variable {
-                    name: symbol.to_string(),
-                    tag: tag.to_string(),
-                })),
-                tokens: vec![],
-                children: vec![],
-            });
-        } else {
-            return Ok(Node {
-                expr: Expression::Variable(Box::new(Variable {
-                    name: token.to_string(),
-                    tag: String::from("_"),
-                })),
-                tokens: vec![],
-                children: vec![],
-            });
+        if self.peek_is(TokenType::LowerThan) {
+            function.states = Some(self.parse_state_list()?);
+        }
+
+        match function.kind {
+            FunctionKind::Native => {
+                if self.peek_is(TokenType::Assign) {
+                    self.current += 1;
+                    function.native_alias = Some(self.parse_native_alias()?);
+                }
+                self.expect_semicolon()?;
+            }
+            FunctionKind::Forward => {
+                self.expect_semicolon()?;
+            }
+            FunctionKind::Definition => match self.peek_type() {
+                Some(TokenType::LeftBrace) => function.body = Some(self.parse_block()?),
+                Some(TokenType::Semicolon) => self.current += 1,
+                // the body of a function may also be a single statement
+                _ => function.body = Some(vec![self.parse_statement()?]),
+            },
         }
+
+        Ok(Node::new(Expression::Function(Box::new(function))))
     }
 
-    fn parse_literal_scalar(&mut self) -> Result<Node, String> {
-        // (tag:)(+|-)literal
-        Err(String::from("not implemented"))
+    fn parse_enum(&mut self) -> Result<Node, Diagnostic> {
+        // enum (tag:)(ident)((<op>= <expr>)) { <members> } (;)
+        self.expect(TokenType::Enum)?;
+
+        let tag = self.parse_tag();
+        let name = if self.peek_is(TokenType::Symbol) {
+            Some(self.expect_symbol()?)
+        } else {
+            None
+        };
+
+        let increment = if self.peek_is(TokenType::LeftBracket) {
+            self.current += 1;
+            let operator = match self.peek_type() {
+                Some(t) if is_assignment(t) && t != TokenType::Assign => self.next().clone(),
+                _ => return Err(self.unexpected(&COMPOUND_ASSIGNMENTS)),
+            };
+            let amount = self.parse_expression()?;
+            self.expect(TokenType::RightBracket)?;
+            Some(EnumIncrement { operator, amount })
+        } else {
+            None
+        };
+
+        self.expect(TokenType::LeftBrace)?;
+        let mut members = vec![];
+        while !self.peek_is(TokenType::RightBrace) {
+            let start = self.current_span();
+            let member = self.node(SyntaxKind::EnumMember, |p| p.parse_enum_member())?;
+            members.push(self.spanned(member, start));
+            if !self.peek_is(TokenType::Comma) {
+                break;
+            }
+            self.current += 1;
+        }
+        self.expect(TokenType::RightBrace)?;
+
+        if self.peek_is(TokenType::Semicolon) {
+            self.current += 1;
+        }
+
+        Ok(Node::new(Expression::Enum(Box::new(Enum {
+            name,
+            tag,
+            increment,
+            members,
+        }))))
+    }
+
+    fn parse_enum_member(&mut self) -> Result<EnumMember, Diagnostic> {
+        // (tag:)ident([<expr>])(= <expr>)
+        let tag = self.parse_tag().unwrap_or_else(|| String::from("_"));
+        let name = self.expect_symbol()?;
+
+        let size = if self.peek_is(TokenType::LeftSquare) {
+            self.current += 1;
+            let size = self.parse_expression()?;
+            self.expect(TokenType::RightSquare)?;
+            Some(size)
+        } else {
+            None
+        };
+
+        let value = if self.peek_is(TokenType::Assign) {
+            self.current += 1;
+            Some(self.parse_expression()?)
+        } else {
+            None
+        };
+
+        Ok(EnumMember {
+            name,
+            tag,
+            size,
+            value,
+        })
+    }
+
+    fn parse_parameters(&mut self) -> Result<Vec<Spanned<Parameter>>, Diagnostic> {
+        self.parse_list(TokenType::RightBracket, |p| {
+            let start = p.current_span();
+            let parameter = p.node(SyntaxKind::Parameter, |p| p.parse_parameter())?;
+            Ok(p.spanned(parameter, start))
+        })
+    }
+
+    fn parse_parameter(&mut self) -> Result<Parameter, Diagnostic> {
+        // (const) (&) (tag:|{tag,...}:)(ident([<expr>])*(= <expr>)|...)
+        let mut parameter = Parameter {
+            name: None,
+            tags: vec![],
+            is_const: false,
+            is_reference: false,
+            is_variadic: false,
+            dimensions: vec![],
+            default: None,
+        };
+
+        if self.peek_is(TokenType::Const) {
+            self.current += 1;
+            parameter.is_const = true;
+        }
+        if self.peek_is(TokenType::BitAnd) {
+            self.current += 1;
+            parameter.is_reference = true;
+        }
+        parameter.tags = self.parse_tags()?;
+
+        if self.peek_is(TokenType::Elipsis) {
+            self.current += 1;
+            parameter.is_variadic = true;
+            return Ok(parameter);
+        }
+
+        parameter.name = Some(self.expect_symbol()?);
+        parameter.dimensions = self.parse_dimensions()?;
+
+        if self.peek_is(TokenType::Assign) {
+            self.current += 1;
+            parameter.default = Some(self.parse_expression()?);
+        }
+
+        Ok(parameter)
+    }
+
+    // parse_tags parses a single `tag:` or a tag list such as `{Float,_}:`,
+    // returning `_` if there is no tag.
+    fn parse_tags(&mut self) -> Result<Vec<String>, Diagnostic> {
+        if let Some(tag) = self.parse_tag() {
+            return Ok(vec![tag]);
+        }
+        if !self.peek_is(TokenType::LeftBrace) {
+            return Ok(vec![String::from("_")]);
+        }
+
+        self.node(SyntaxKind::Tag, |p| {
+            p.current += 1;
+            let tags = p.parse_list(TokenType::RightBrace, |p| p.expect_symbol())?;
+            p.expect(TokenType::Colon)?;
+            Ok(tags)
+        })
+    }
+
+    // parse_list parses items separated by commas until the closing token,
+    // the opening token must already be consumed.
+    fn parse_list<T>(
+        &mut self,
+        close: TokenType,
+        mut f: impl FnMut(&mut Parser) -> Result<T, Diagnostic>,
+    ) -> Result<Vec<T>, Diagnostic> {
+        let mut items = vec![];
+
+        if self.peek_is(close) {
+            self.current += 1;
+            return Ok(items);
+        }
+
+        loop {
+            items.push(f(self)?);
+            match self.peek_type() {
+                Some(TokenType::Comma) => self.current += 1,
+                Some(t) if t == close => {
+                    self.current += 1;
+                    return Ok(items);
+                }
+                _ => return Err(self.unexpected(&[TokenType::Comma, close])),
+            }
+        }
+    }
+
+    fn parse_state_list(&mut self) -> Result<StateList, Diagnostic> {
+        // <(automaton:)(state(, state)*)>
+        self.start(SyntaxKind::StateList);
+        self.expect(TokenType::LowerThan)?;
+
+        let automaton = self.parse_tag();
+        let mut states = vec![];
+        while !self.peek_is(TokenType::GreaterThan) {
+            states.push(self.expect_symbol()?);
+            if !self.peek_is(TokenType::Comma) {
+                break;
+            }
+            self.current += 1;
+        }
+        self.expect(TokenType::GreaterThan)?;
+        self.finish();
+
+        Ok(StateList { automaton, states })
+    }
+
+    fn parse_native_alias(&mut self) -> Result<NativeAlias, Diagnostic> {
+        match self.peek_type() {
+            Some(TokenType::Symbol) => Ok(NativeAlias::Name(self.expect_symbol()?)),
+            Some(TokenType::Minus) => {
+                self.current += 1;
+                match &self.expect(TokenType::Integer)?.value {
+                    // -2147483648 is lexed as a number that has already
+                    // wrapped around to i32::MIN
+                    Some(TokenValue::Integer(v)) => Ok(NativeAlias::Index(v.wrapping_neg())),
+                    _ => Err(self.unexpected(&[TokenType::Integer])),
+                }
+            }
+            _ => Err(self.unexpected(&[TokenType::Symbol, TokenType::Minus])),
+        }
+    }
+
+    fn parse_block(&mut self) -> Result<Vec<Spanned<Statement>>, Diagnostic> {
+        self.start(SyntaxKind::Block);
+        let open = self.expect(TokenType::LeftBrace)?.span();
+
+        let mut statements = vec![];
+        loop {
+            match self.peek_type() {
+                Some(TokenType::RightBrace) => {
+                    self.current += 1;
+                    self.finish();
+                    return Ok(statements);
+                }
+                Some(TokenType::Directive) => self.skip_directive(),
+                Some(_) => statements.push(self.parse_statement_or_recover()?),
+                None => return Err(self.unclosed_block(open)),
+            }
+        }
+    }
+
+    // parse_statement_or_recover parses a statement, if that fails the error
+    // is recorded and the parser skips to the next statement. Nesting too
+    // deeply is not recovered from here, since the statements around would
+    // only nest too deeply again, the whole declaration is skipped instead.
+    fn parse_statement_or_recover(&mut self) -> Result<Spanned<Statement>, Diagnostic> {
+        let start = self.current;
+        let start_span = self.current_span();
+        let checkpoint = self.checkpoint();
+        match self.parse_statement() {
+            Ok(statement) => Ok(statement),
+            Err(diagnostic) if matches!(diagnostic.error, ParseError::NestingTooDeep { .. }) => {
+                Err(diagnostic)
+            }
+            Err(diagnostic) => {
+                self.report(diagnostic);
+                self.close_to(checkpoint);
+                self.synchronise(start, is_statement_start);
+                self.start_at(checkpoint, SyntaxKind::Error);
+                self.finish();
+                Ok(self.spanned(Statement::Error, start_span))
+            }
+        }
+    }
+
+    pub(crate) fn parse_statement(&mut self) -> Result<Spanned<Statement>, Diagnostic> {
+        self.nested(|p| p.parse_statement_kind())
+    }
+
+    fn parse_statement_kind(&mut self) -> Result<Spanned<Statement>, Diagnostic> {
+        let start = self.current_span();
+        let statement = match self.peek_type() {
+            Some(TokenType::LeftBrace) => Ok(Statement::Block(self.parse_block()?)),
+            Some(TokenType::New) | Some(TokenType::Static) | Some(TokenType::Const) => {
+                Ok(Statement::Declaration(self.parse_declaration()?))
+            }
+            Some(TokenType::If) => self.node(SyntaxKind::If, |p| p.parse_if()),
+            Some(TokenType::While) => self.node(SyntaxKind::While, |p| p.parse_while()),
+            Some(TokenType::Do) => self.node(SyntaxKind::DoWhile, |p| p.parse_do_while()),
+            Some(TokenType::For) => self.node(SyntaxKind::For, |p| p.parse_for()),
+            Some(TokenType::Switch) => self.node(SyntaxKind::Switch, |p| p.parse_switch()),
+            Some(TokenType::State) => {
+                self.node(SyntaxKind::StateTransition, |p| p.parse_state_transition())
+            }
+            Some(TokenType::Goto) => self.node(SyntaxKind::Goto, |p| {
+                p.current += 1;
+                let label = p.expect_symbol()?;
+                p.expect_semicolon()?;
+                Ok(Statement::Goto(label))
+            }),
+            Some(TokenType::Return) => self.node(SyntaxKind::Return, |p| {
+                p.current += 1;
+                let value = if p.peek_is(TokenType::Semicolon) || p.at_line_end() {
+                    None
+                } else {
+                    Some(p.parse_expression()?)
+                };
+                p.expect_semicolon()?;
+                Ok(Statement::Return(value))
+            }),
+            Some(TokenType::Break) => self.node(SyntaxKind::Break, |p| {
+                p.current += 1;
+                p.expect_semicolon()?;
+                Ok(Statement::Break)
+            }),
+            Some(TokenType::Continue) => self.node(SyntaxKind::Continue, |p| {
+                p.current += 1;
+                p.expect_semicolon()?;
+                Ok(Statement::Continue)
+            }),
+            Some(TokenType::Semicolon) => self.node(SyntaxKind::Empty, |p| {
+                p.current += 1;
+                Ok(Statement::Empty)
+            }),
+            // like pawncc, `name:` at the start of a statement is always a
+            // label and never a tag override.
+            Some(TokenType::Symbol) if self.peek_tag() => self.node(SyntaxKind::Label, |p| {
+                let label = symbol_name(p.next());
+                p.current += 1;
+                Ok(Statement::Label(label))
+            }),
+            _ => self.node(SyntaxKind::ExpressionStatement, |p| {
+                let expr = p.parse_comma_expression()?;
+                p.expect_semicolon()?;
+                Ok(Statement::Expression(expr))
+            }),
+        }?;
+
+        Ok(self.spanned(statement, start))
+    }
+
+    fn parse_condition(&mut self) -> Result<Spanned<Expression>, Diagnostic> {
+        self.expect(TokenType::LeftBracket)?;
+        let condition = self.with_tags(true, |p| p.parse_comma_expression())?;
+        self.expect(TokenType::RightBracket)?;
+        Ok(condition)
+    }
+
+    // parse_if parses an `if` statement. Each `else if` is an `if` nested in
+    // the `else` of the one before, but a chain of them is read in a loop,
+    // since generated code can have thousands.
+    fn parse_if(&mut self) -> Result<Statement, Diagnostic> {
+        let open = self.open;
+        let mut branches = vec![];
+        let result = self.parse_if_chain(&mut branches);
+        // the nodes of the `else if`s all end where the chain does
+        while self.open > open {
+            self.finish();
+        }
+        let mut otherwise = result?;
+
+        loop {
+            let (start, condition, body) = branches.pop().unwrap();
+            let statement = Statement::If(Box::new(If {
+                condition,
+                body,
+                otherwise,
+            }));
+            if branches.is_empty() {
+                return Ok(statement);
+            }
+            otherwise = Some(self.spanned(statement, start));
+        }
+    }
+
+    // parse_if_chain parses the condition and body of an `if` and of each
+    // `else if` after it, returning the final `else` if there is one.
+    fn parse_if_chain(
+        &mut self,
+        branches: &mut Vec<(Span, Spanned<Expression>, Spanned<Statement>)>,
+    ) -> Result<Option<Spanned<Statement>>, Diagnostic> {
+        loop {
+            let start = self.current_span();
+            self.expect(TokenType::If)?;
+            let condition = self.parse_condition()?;
+            let body = self.parse_statement()?;
+            branches.push((start, condition, body));
+
+            if !self.peek_is(TokenType::Else) {
+                return Ok(None);
+            }
+            self.current += 1;
+            if !self.peek_is(TokenType::If) {
+                return self.parse_statement().map(Some);
+            }
+            self.start(SyntaxKind::If);
+        }
+    }
+
+    fn parse_while(&mut self) -> Result<Statement, Diagnostic> {
+        self.expect(TokenType::While)?;
+        let condition = self.parse_condition()?;
+        let body = self.parse_statement()?;
+
+        Ok(Statement::While(Box::new(While { condition, body })))
+    }
+
+    fn parse_do_while(&mut self) -> Result<Statement, Diagnostic> {
+        self.expect(TokenType::Do)?;
+        let body = self.parse_statement()?;
+        self.expect(TokenType::While)?;
+        let condition = self.parse_condition()?;
+        self.expect_semicolon()?;
+
+        Ok(Statement::DoWhile(Box::new(DoWhile { body, condition })))
+    }
+
+    fn parse_for(&mut self) -> Result<Statement, Diagnostic> {
+        // for ((<decl>|<expr>); <expr>; <expr>) <statement>
+        self.expect(TokenType::For)?;
+        self.expect(TokenType::LeftBracket)?;
+
+        let initialiser = match self.peek_type() {
+            Some(TokenType::Semicolon) => {
+                self.current += 1;
+                None
+            }
+            Some(TokenType::New) => {
+                let start = self.current_span();
+                let declaration = Statement::Declaration(self.parse_declaration()?);
+                Some(self.spanned(declaration, start))
+            }
+            _ => {
+                let start = self.current_span();
+                let expr = self.parse_comma_expression()?;
+                self.expect(TokenType::Semicolon)?;
+                Some(self.spanned(Statement::Expression(expr), start))
+            }
+        };
+
+        let condition = if self.peek_is(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.parse_comma_expression()?)
+        };
+        self.expect(TokenType::Semicolon)?;
+
+        let increment = if self.peek_is(TokenType::RightBracket) {
+            None
+        } else {
+            Some(self.parse_comma_expression()?)
+        };
+        self.expect(TokenType::RightBracket)?;
+
+        let body = self.parse_statement()?;
+
+        Ok(Statement::For(Box::new(For {
+            initialiser,
+            condition,
+            increment,
+            body,
+        })))
+    }
+
+    fn parse_state_transition(&mut self) -> Result<Statement, Diagnostic> {
+        // state ((<expr>)) (automaton:)ident;
+        self.expect(TokenType::State)?;
+
+        let condition = if self.peek_is(TokenType::LeftBracket) {
+            Some(self.parse_condition()?)
+        } else {
+            None
+        };
+        let automaton = self.parse_tag();
+        let state = self.expect_symbol()?;
+        self.expect_semicolon()?;
+
+        Ok(Statement::State(Box::new(StateTransition {
+            condition,
+            automaton,
+            state,
+        })))
+    }
+
+    fn parse_switch(&mut self) -> Result<Statement, Diagnostic> {
+        // switch (<expr>) { (case <labels>: <statement>)* (default: <statement>) }
+        self.expect(TokenType::Switch)?;
+        let subject = self.parse_condition()?;
+        let open = self.expect(TokenType::LeftBrace)?.span();
+
+        let mut switch = Switch {
+            subject,
+            cases: vec![],
+            default: None,
+        };
+        let mut default_span: Option<Span> = None;
+
+        loop {
+            match self.peek_type() {
+                Some(TokenType::Case) => {
+                    self.start(SyntaxKind::Case);
+                    let start = self.current_span();
+                    self.current += 1;
+                    let labels = self.with_tags(false, |p| p.parse_case_labels())?;
+                    self.expect(TokenType::Colon)?;
+                    let body = self.parse_statement_or_recover()?;
+                    self.finish();
+                    let case = self.spanned(Case { labels, body }, start);
+                    switch.cases.push(case);
+                }
+                Some(TokenType::Default) => {
+                    self.start(SyntaxKind::Case);
+                    let span = self.next().span();
+                    self.expect(TokenType::Colon)?;
+                    let body = self.parse_statement_or_recover()?;
+                    self.finish();
+                    match default_span {
+                        // keep the first default and carry on parsing
+                        Some(first) => self.report(
+                            Diagnostic::new(ParseError::MultipleDefaults, span)
+                                .with_label(first, "the first default is here")
+                                .with_note("a switch can only have one default case"),
+                        ),
+                        None => {
+                            default_span = Some(span);
+                            switch.default = Some(body);
+                        }
+                    }
+                }
+                Some(TokenType::RightBrace) => {
+                    self.current += 1;
+                    return Ok(Statement::Switch(Box::new(switch)));
+                }
+                Some(_) => {
+                    return Err(self.unexpected(&[
+                        TokenType::Case,
+                        TokenType::Default,
+                        TokenType::RightBrace,
+                    ]))
+                }
+                None => return Err(self.unclosed_block(open)),
+            }
+        }
+    }
+
+    fn parse_case_labels(&mut self) -> Result<Vec<CaseLabel>, Diagnostic> {
+        // case labels are constant so the ternary operator is not allowed
+        // here, it would be ambiguous with the colon that ends the list.
+        let mut labels = vec![];
+        loop {
+            let value = self.parse_binary(0)?;
+            if self.peek_is(TokenType::Range) {
+                self.current += 1;
+                labels.push(CaseLabel::Range(value, self.parse_binary(0)?));
+            } else {
+                labels.push(CaseLabel::Value(value));
+            }
+
+            if !self.peek_is(TokenType::Comma) {
+                return Ok(labels);
+            }
+            self.current += 1;
+        }
+    }
+
+    fn expect_symbol(&mut self) -> Result<String, Diagnostic> {
+        match self.peek_type() {
+            Some(TokenType::Symbol) => Ok(symbol_name(self.next())),
+            _ => Err(self.unexpected(&[TokenType::Symbol])),
+        }
+    }
+
+    // expect_semicolon expects the end of a statement, which is a `;` or,
+    // with optional semicolons, the end of the line.
+    fn expect_semicolon(&mut self) -> Result<(), Diagnostic> {
+        match self.peek_type() {
+            Some(TokenType::Semicolon) => {
+                self.current += 1;
+                Ok(())
+            }
+            _ if self.at_line_end() => Ok(()),
+            _ => Err(self.unexpected(&[TokenType::Semicolon])),
+        }
+    }
+
+    // at_line_end reports whether a statement may end before the next token
+    // without a `;`. As in pawncc, that is when semicolons are optional and
+    // the next token is on a later line, or there is no next token.
+    fn at_line_end(&self) -> bool {
+        if !self.options.optional_semicolons || self.current == 0 {
+            return false;
+        }
+        match self.tokens.get(self.current) {
+            Some(token) => token.line_start > self.tokens[self.current - 1].line_end,
+            None => true,
+        }
+    }
+
+    fn parse_tag(&mut self) -> Option<String> {
+        if !self.peek_tag() {
+            return None;
+        }
+        self.start(SyntaxKind::Tag);
+        let tag = symbol_name(self.next());
+        self.current += 1;
+        self.finish();
+        Some(tag)
+    }
+
+    // nested runs `f` one level deeper, see Options::max_depth. The depth is
+    // put back afterwards even if `f` fails part of the way through.
+    fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Parser) -> Result<T, Diagnostic>,
+    ) -> Result<T, Diagnostic> {
+        let depth = self.depth;
+        if self.depth >= self.options.max_depth {
+            return Err(self.error(ParseError::NestingTooDeep {
+                limit: self.options.max_depth,
+            }));
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth = depth;
+        result
+    }
+
+    // with_tags runs `f` with tag overrides switched on or off, restoring the
+    // previous setting afterwards.
+    fn with_tags<T>(
+        &mut self,
+        allow: bool,
+        f: impl FnOnce(&mut Parser) -> Result<T, Diagnostic>,
+    ) -> Result<T, Diagnostic> {
+        let previous = self.allow_tags;
+        self.allow_tags = allow;
+        let result = f(self);
+        self.allow_tags = previous;
+        result
+    }
+
+    // with_selectors runs `f` with empty brackets after an array allowed or
+    // not, restoring the previous setting afterwards.
+    fn with_selectors<T>(
+        &mut self,
+        allow: bool,
+        f: impl FnOnce(&mut Parser) -> Result<T, Diagnostic>,
+    ) -> Result<T, Diagnostic> {
+        let previous = self.allow_selectors;
+        self.allow_selectors = allow;
+        let result = f(self);
+        self.allow_selectors = previous;
+        result
+    }
+
+    fn parse_expression(&mut self) -> Result<Spanned<Expression>, Diagnostic> {
+        self.parse_assignment()
+    }
+
+    // parse_comma_expression parses expressions joined by the comma operator,
+    // which is only allowed where a comma cannot separate something else.
+    pub(crate) fn parse_comma_expression(&mut self) -> Result<Spanned<Expression>, Diagnostic> {
+        let checkpoint = self.checkpoint();
+        let mut left = self.parse_expression()?;
+
+        while self.peek_is(TokenType::Comma) {
+            self.start_at(checkpoint, SyntaxKind::BinaryOperation);
+            let operator = self.next().clone();
+            let right = self.parse_expression()?;
+            self.finish();
+            left = binary_operation(operator, left, right);
+        }
+
+        Ok(left)
+    }
+
+    fn parse_assignment(&mut self) -> Result<Spanned<Expression>, Diagnostic> {
+        let checkpoint = self.checkpoint();
+        let left = self.parse_ternary()?;
+
+        match self.peek_type() {
+            Some(t) if is_assignment(t) => {
+                self.start_at(checkpoint, SyntaxKind::BinaryOperation);
+                let operator = self.next().clone();
+                let right = self.nested(|p| p.parse_assignment())?;
+                self.finish();
+                Ok(binary_operation(operator, left, right))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn parse_ternary(&mut self) -> Result<Spanned<Expression>, Diagnostic> {
+        let checkpoint = self.checkpoint();
+        let condition = self.parse_binary(0)?;
+
+        if !self.peek_is(TokenType::Question) {
+            return Ok(condition);
+        }
+        self.start_at(checkpoint, SyntaxKind::TernaryOperation);
+        self.current += 1;
+
+        // `b:` in `a ? b: c` is the ternary colon, not a tag.
+        let left = self.with_tags(false, |p| p.nested(|p| p.parse_ternary()))?;
+        self.expect(TokenType::Colon)?;
+        let right = self.nested(|p| p.parse_ternary())?;
+        self.finish();
+
+        let span = condition.span.to(right.span);
+        Ok(Spanned::new(
+            Expression::TernaryOperation(Box::new(TernaryOperation {
+                condition: Box::new(condition),
+                left: Box::new(left),
+                right: Box::new(right),
+            })),
+            span,
+        ))
+    }
+
+    // parse_binary is a precedence climbing parser for left-associative binary
+    // operators that bind tighter than `precedence`.
+    fn parse_binary(&mut self, precedence: u8) -> Result<Spanned<Expression>, Diagnostic> {
+        let checkpoint = self.checkpoint();
+        let mut left = self.parse_unary()?;
+
+        while let Some(next) = self.peek_type().and_then(binary_precedence) {
+            if next <= precedence {
+                break;
+            }
+            self.start_at(checkpoint, SyntaxKind::BinaryOperation);
+            let operator = self.next().clone();
+            let right = self.parse_binary(next)?;
+            self.finish();
+            left = binary_operation(operator, left, right);
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Spanned<Expression>, Diagnostic> {
+        let start = self.current_span();
+        if self.allow_tags && self.peek_tag() {
+            let expr = self.node(SyntaxKind::TagOverride, |p| {
+                let tag = p.parse_tag().unwrap();
+                let operand = p.nested(|p| p.parse_unary())?;
+                Ok(Expression::TagOverride(Box::new(TagOverride {
+                    tag,
+                    operand: Box::new(operand),
+                })))
+            })?;
+            return Ok(self.spanned(expr, start));
+        }
+
+        match self.peek_type() {
+            Some(TokenType::Bang) if self.is_packed_string_at(self.current) => self.parse_postfix(),
+            Some(TokenType::Minus)
+            | Some(TokenType::Bang)
+            | Some(TokenType::Tilde)
+            | Some(TokenType::PlusPlus)
+            | Some(TokenType::MinusMinus) => {
+                let expr = self.node(SyntaxKind::UnaryOperation, |p| {
+                    let operator = p.next().clone();
+                    let operand = p.nested(|p| p.parse_unary())?;
+                    Ok(Expression::UnaryOperation(Box::new(UnaryOperation {
+                        operator,
+                        operand: Box::new(operand),
+                        postfix: false,
+                    })))
+                })?;
+                Ok(self.spanned(expr, start))
+            }
+            Some(TokenType::Tagof) | Some(TokenType::Sizeof) | Some(TokenType::Defined) => {
+                let expr = self.node(SyntaxKind::UnaryOperation, |p| {
+                    let operator = p.next().clone();
+                    let operand = match operator.token_type {
+                        TokenType::Tagof => p.parse_tagof_operand()?,
+                        TokenType::Defined => p.nested(|p| p.parse_unary())?,
+                        _ => p.with_selectors(true, |p| p.nested(|p| p.parse_unary()))?,
+                    };
+                    Ok(Expression::UnaryOperation(Box::new(UnaryOperation {
+                        operator,
+                        operand: Box::new(operand),
+                        postfix: false,
+                    })))
+                })?;
+                Ok(self.spanned(expr, start))
+            }
+            _ => self.parse_postfix(),
+        }
+    }
+
+    // parse_tagof_operand parses what follows `tagof`, which is either an
+    // expression or a tag on its own in brackets such as `(Float:)`.
+    fn parse_tagof_operand(&mut self) -> Result<Spanned<Expression>, Diagnostic> {
+        let is_tag = matches!(
+            (
+                self.peek_type(),
+                self.tokens.get(self.current + 1).map(|t| t.token_type),
+                self.tokens.get(self.current + 2).map(|t| t.token_type),
+                self.tokens.get(self.current + 3).map(|t| t.token_type),
+            ),
+            (
+                Some(TokenType::LeftBracket),
+                Some(TokenType::Symbol),
+                Some(TokenType::Colon),
+                Some(TokenType::RightBracket)
+            )
+        );
+        if !is_tag {
+            return self.nested(|p| p.parse_unary());
+        }
+
+        self.current += 1;
+        let start = self.current_span();
+        let tag = self.node(SyntaxKind::Tag, |p| Ok(p.parse_tag().unwrap()))?;
+        let operand = self.spanned(Expression::Tag(tag), start);
+        self.expect(TokenType::RightBracket)?;
+        Ok(operand)
+    }
+
+    fn parse_postfix(&mut self) -> Result<Spanned<Expression>, Diagnostic> {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.parse_primary()?;
+
+        loop {
+            let start = expr.span;
+            let postfix = match self.peek_type() {
+                Some(TokenType::LeftBracket) => {
+                    self.start_at(checkpoint, SyntaxKind::Call);
+                    let arguments = self.node(SyntaxKind::ArgumentList, |p| {
+                        p.current += 1;
+                        p.with_selectors(false, |p| p.with_tags(true, |p| p.parse_arguments()))
+                    })?;
+                    Expression::Call(Box::new(Call {
+                        function: Box::new(expr),
+                        arguments,
+                    }))
+                }
+                Some(TokenType::LeftSquare)
+                    if self.allow_selectors
+                        && self.tokens.get(self.current + 1).map(|t| t.token_type)
+                            == Some(TokenType::RightSquare) =>
+                {
+                    self.start_at(checkpoint, SyntaxKind::Selector);
+                    self.current += 2;
+                    Expression::Selector(Box::new(expr))
+                }
+                Some(TokenType::LeftSquare) => {
+                    self.start_at(checkpoint, SyntaxKind::Index);
+                    self.current += 1;
+                    let index = self
+                        .with_selectors(false, |p| p.with_tags(true, |p| p.parse_expression()))?;
+                    self.expect(TokenType::RightSquare)?;
+                    Expression::Index(Box::new(Index {
+                        array: Box::new(expr),
+                        index: Box::new(index),
+                    }))
+                }
+                // `char` converts a number of characters to cells, as in `s[10 char]`
+                Some(TokenType::PlusPlus) | Some(TokenType::MinusMinus) | Some(TokenType::Char) => {
+                    self.start_at(checkpoint, SyntaxKind::UnaryOperation);
+                    let operator = self.next().clone();
+                    Expression::UnaryOperation(Box::new(UnaryOperation {
+                        operator,
+                        operand: Box::new(expr),
+                        postfix: true,
+                    }))
+                }
+                _ => return Ok(expr),
+            };
+            self.finish();
+            expr = self.spanned(postfix, start);
+        }
+    }
+
+    fn parse_arguments(&mut self) -> Result<Vec<Spanned<Argument>>, Diagnostic> {
+        self.parse_list(TokenType::RightBracket, |p| {
+            let start = p.current_span();
+            let argument = p.node(SyntaxKind::Argument, |p| p.parse_argument())?;
+            Ok(p.spanned(argument, start))
+        })
+    }
+
+    fn parse_argument(&mut self) -> Result<Argument, Diagnostic> {
+        // <expr>|.ident = <expr>|_
+        if self.peek_is(TokenType::Dot) {
+            self.current += 1;
+            let name = self.expect_symbol()?;
+            self.expect(TokenType::Assign)?;
+            let value = self.parse_expression()?;
+            return Ok(Argument::Named { name, value });
+        }
+
+        let skipped = match (
+            self.tokens.get(self.current),
+            self.tokens.get(self.current + 1).map(|t| t.token_type),
+        ) {
+            (Some(token), Some(TokenType::Comma))
+            | (Some(token), Some(TokenType::RightBracket)) => {
+                token.value == Some(TokenValue::String(String::from("_")))
+            }
+            _ => false,
+        };
+        if skipped {
+            self.current += 1;
+            return Ok(Argument::Skipped);
+        }
+
+        Ok(Argument::Positional(self.parse_expression()?))
+    }
+
+    fn parse_primary(&mut self) -> Result<Spanned<Expression>, Diagnostic> {
+        let start = self.current_span();
+        let expr = match self.peek_type() {
+            Some(TokenType::Symbol) if self.peek_bool().is_some() => {
+                self.node(SyntaxKind::Literal, |p| p.parse_literal_scalar())
+            }
+            Some(TokenType::Symbol) => self.node(SyntaxKind::Name, |p| {
+                Ok(Expression::Symbol(symbol_name(p.next())))
+            }),
+            Some(TokenType::Integer)
+            | Some(TokenType::Float)
+            | Some(TokenType::Character)
+            | Some(TokenType::Literal)
+            | Some(TokenType::Bang) => self.node(SyntaxKind::Literal, |p| p.parse_literal_scalar()),
+            Some(TokenType::LeftBrace) => {
+                self.node(SyntaxKind::Array, |p| p.nested(|p| p.parse_array()))
+            }
+            // the brackets are kept in the span of the expression inside them
+            Some(TokenType::LeftBracket) => self.node(SyntaxKind::Paren, |p| {
+                p.current += 1;
+                let expr = p.with_tags(true, |p| p.nested(|p| p.parse_expression()))?;
+                p.expect(TokenType::RightBracket)?;
+                Ok(expr.node)
+            }),
+            found => Err(self.error(ParseError::ExpectedExpression { found })),
+        }?;
+
+        Ok(self.spanned(expr, start))
+    }
+
+    fn parse_symbol(&mut self) -> Result<Variable, Diagnostic> {
+        // (tag:)ident([<expr>])*
+        let tag = self.parse_tag().unwrap_or_else(|| String::from("_"));
+        let name = self.expect_symbol()?;
+        let dimensions = self.parse_dimensions()?;
+
+        Ok(Variable {
+            name,
+            tag,
+            dimensions,
+            initialiser: None,
+            storage: StorageClass::New,
+            is_stock: false,
+            is_const: false,
+        })
+    }
+
+    // parse_dimensions parses array dimensions such as `[10][E_DATA][]`, an
+    // empty dimension has its size taken from the initialiser.
+    fn parse_dimensions(&mut self) -> Result<Vec<Option<Spanned<Expression>>>, Diagnostic> {
+        let mut dimensions = vec![];
+        while self.peek_is(TokenType::LeftSquare) {
+            self.start(SyntaxKind::Dimension);
+            self.current += 1;
+            if self.peek_is(TokenType::RightSquare) {
+                dimensions.push(None);
+            } else {
+                dimensions.push(Some(self.with_tags(true, |p| p.parse_expression())?));
+            }
+            self.expect(TokenType::RightSquare)?;
+            self.finish();
+        }
+        Ok(dimensions)
+    }
+
+    fn parse_array(&mut self) -> Result<Expression, Diagnostic> {
+        // { (<expr>(, <expr>)*(, ...)) }
+        self.expect(TokenType::LeftBrace)?;
+
+        let mut array = Array {
+            elements: vec![],
+            ellipsis: false,
+        };
+        self.with_tags(true, |p| {
+            while !p.peek_is(TokenType::RightBrace) {
+                if p.peek_is(TokenType::Elipsis) {
+                    p.current += 1;
+                    array.ellipsis = true;
+                    break;
+                }
+                array.elements.push(p.parse_expression()?);
+                if !p.peek_is(TokenType::Comma) {
+                    break;
+                }
+                p.current += 1;
+            }
+            Ok(())
+        })?;
+        self.expect(TokenType::RightBrace)?;
+
+        Ok(Expression::Literal(Literal::Array(Box::new(array))))
+    }
+
+    fn parse_literal_scalar(&mut self) -> Result<Expression, Diagnostic> {
+        // literal, tags and signs are handled by parse_unary
+        if let Some(value) = self.peek_bool() {
+            self.current += 1;
+            return Ok(Expression::Literal(Literal::Bool(value)));
+        }
+        if self.peek_is(TokenType::Literal) || self.is_packed_string_at(self.current) {
+            return Ok(Expression::Literal(Literal::String(self.parse_string()?)));
+        }
+
+        let token = self.next();
+        let literal = match (token.token_type, &token.value) {
+            (TokenType::Integer, Some(TokenValue::Integer(v))) => Literal::Integer(*v),
+            (TokenType::Float, Some(TokenValue::Float(v))) => Literal::Float(*v),
+            (TokenType::Character, Some(TokenValue::Integer(v))) => Literal::Char(
+                std::char::from_u32(*v as u32).unwrap_or(std::char::REPLACEMENT_CHARACTER),
+            ),
+            (t, _) => return Err(self.error(ParseError::ExpectedExpression { found: Some(t) })),
+        };
+        Ok(Expression::Literal(literal))
+    }
+
+    fn parse_string(&mut self) -> Result<StringLiteral, Diagnostic> {
+        // (!)"..." ((...) "...")*
+        let packed = self.is_packed_string_at(self.current);
+        if packed {
+            self.current += 1;
+        }
+        let mut parts = vec![];
+        loop {
+            let start = self.current_span();
+            let part = symbol_name(self.expect(TokenType::Literal)?);
+            parts.push(self.spanned(part, start));
+
+            let next = match self.peek_type() {
+                Some(TokenType::Elipsis) => self.current + 1,
+                _ => self.current,
+            };
+            match self.tokens.get(next).map(|t| t.token_type) {
+                Some(TokenType::Literal) => self.current = next,
+                _ => break,
+            }
+        }
+
+        Ok(StringLiteral { packed, parts })
+    }
+
+    // is_packed_string_at checks for a packed string such as `!"abc"`, the
+    // quote must immediately follow the `!` or it is a logical not.
+    fn is_packed_string_at(&self, index: usize) -> bool {
+        match (self.tokens.get(index), self.tokens.get(index + 1)) {
+            (Some(bang), Some(string)) => {
+                bang.token_type == TokenType::Bang
+                    && string.token_type == TokenType::Literal
+                    && bang.line_end == string.line_start
+                    && bang.column_end == string.column_start
+            }
+            _ => false,
+        }
+    }
+
+    // peek_bool returns the value of a `true` or `false` constant.
+    fn peek_bool(&self) -> Option<bool> {
+        match self.tokens.get(self.current).map(|t| &t.value) {
+            Some(Some(TokenValue::String(name))) if self.peek_is(TokenType::Symbol) => {
+                match name.as_str() {
+                    "true" => Some(true),
+                    "false" => Some(false),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn binary_operation(
+    operator: Token,
+    left: Spanned<Expression>,
+    right: Spanned<Expression>,
+) -> Spanned<Expression> {
+    let span = left.span.to(right.span);
+    Spanned::new(
+        Expression::BinaryOperation(Box::new(BinaryOperation {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+        })),
+        span,
+    )
+}
+
+fn symbol_name(token: &Token) -> String {
+    match &token.value {
+        Some(TokenValue::String(name)) => name.clone(),
+        _ => token.token_type.to_string(),
+    }
+}
+
+const COMPOUND_ASSIGNMENTS: [TokenType; 11] = [
+    TokenType::PlusAssign,
+    TokenType::MinusAssign,
+    TokenType::AsteriskAssign,
+    TokenType::SlashAssign,
+    TokenType::PercentAssign,
+    TokenType::BitAndAssign,
+    TokenType::BitOrAssign,
+    TokenType::BitXorAssign,
+    TokenType::BitLeftAssign,
+    TokenType::BitRightAssign,
+    TokenType::BitRightLogicalAssign,
+];
+
+pub(crate) fn is_assignment(token_type: TokenType) -> bool {
+    token_type == TokenType::Assign || COMPOUND_ASSIGNMENTS.contains(&token_type)
+}
+
+// compound_operator returns the operator a compound assignment applies, such
+// as `+` for `+=`.
+pub(crate) fn compound_operator(operator: TokenType) -> TokenType {
+    match operator {
+        TokenType::PlusAssign => TokenType::Plus,
+        TokenType::MinusAssign => TokenType::Minus,
+        TokenType::AsteriskAssign => TokenType::Asterisk,
+        TokenType::SlashAssign => TokenType::Slash,
+        TokenType::PercentAssign => TokenType::Percent,
+        TokenType::BitAndAssign => TokenType::BitAnd,
+        TokenType::BitOrAssign => TokenType::BitOr,
+        TokenType::BitXorAssign => TokenType::BitXor,
+        TokenType::BitLeftAssign => TokenType::BitLeft,
+        TokenType::BitRightAssign => TokenType::BitRight,
+        TokenType::BitRightLogicalAssign => TokenType::BitRightLogical,
+        t => t,
+    }
+}
+
+fn is_declaration_start(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Forward
+            | TokenType::Native
+            | TokenType::Public
+            | TokenType::Stock
+            | TokenType::Static
+            | TokenType::New
+            | TokenType::Const
+            | TokenType::Enum
+            | TokenType::Directive
+    )
+}
+
+fn is_statement_start(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::If
+            | TokenType::While
+            | TokenType::Do
+            | TokenType::For
+            | TokenType::Switch
+            | TokenType::Case
+            | TokenType::Default
+            | TokenType::Return
+            | TokenType::Break
+            | TokenType::Continue
+            | TokenType::Goto
+            | TokenType::State
+            | TokenType::New
+            | TokenType::Static
+            | TokenType::Const
+            | TokenType::Directive
+    )
+}
+
+// is_overloadable reports whether an operator can be redefined for a tag.
+fn is_overloadable(token_type: TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Plus
+            | TokenType::Minus
+            | TokenType::Asterisk
+            | TokenType::Slash
+            | TokenType::Percent
+            | TokenType::PlusPlus
+            | TokenType::MinusMinus
+            | TokenType::Equal
+            | TokenType::NotEqual
+            | TokenType::LowerThan
+            | TokenType::LowerThanEqual
+            | TokenType::GreaterThan
+            | TokenType::GreaterThanEqual
+            | TokenType::Bang
+            | TokenType::Assign
+    )
+}
+
+// binary_precedence returns how tightly a binary operator binds, higher binds
+// tighter. Unlike C, pawncc binds the bitwise operators tighter than the
+// relational and equality operators.
+pub(crate) fn binary_precedence(token_type: TokenType) -> Option<u8> {
+    match token_type {
+        TokenType::Or => Some(1),
+        TokenType::And => Some(2),
+        TokenType::Equal | TokenType::NotEqual => Some(3),
+        TokenType::LowerThan
+        | TokenType::LowerThanEqual
+        | TokenType::GreaterThan
+        | TokenType::GreaterThanEqual => Some(4),
+        TokenType::BitOr => Some(5),
+        TokenType::BitXor => Some(6),
+        TokenType::BitAnd => Some(7),
+        TokenType::BitLeft | TokenType::BitRight | TokenType::BitRightLogical => Some(8),
+        TokenType::Plus | TokenType::Minus => Some(9),
+        TokenType::Asterisk | TokenType::Slash | TokenType::Percent => Some(10),
+        _ => None,
     }
 }
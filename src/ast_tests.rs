@@ -1,7 +1,15 @@
 #[cfg(test)]
-use crate::ast::Parser;
+use crate::ast::{
+    Argument, Array, CaseLabel, Enum, EnumMember, Expression, Function, FunctionKind, Literal,
+    NativeAlias, Options, Parameter, Parser, Spanned, StateList, StateTransition, Statement,
+    StorageClass, StringLiteral, Variable, DEFAULT_MAX_DEPTH,
+};
+#[cfg(test)]
+use crate::error::ParseError;
 #[cfg(test)]
 use crate::lexer::Lexer;
+#[cfg(test)]
+use crate::token::{Span, TokenType};
 
 #[test]
 fn test_basic() {
@@ -10,3 +18,1257 @@ fn test_basic() {
 
     println!("{:?}", p.root);
 }
+
+#[cfg(test)]
+fn parse_initialiser(source: &str) -> Expression {
+    let mut p = Parser::new(Lexer::new(source).lex());
+    p.parse().expect("failed to parse");
+
+    match p.root.children.remove(0).expr {
+        Expression::Variable(v) => v.initialiser.expect("expected an initialiser").node,
+        e => panic!("expected variable, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_tag_override_call() {
+    let expr = parse_initialiser("new a = _:GetPlayerState(id);");
+
+    let tag = match expr {
+        Expression::TagOverride(tag) => tag,
+        e => panic!("expected tag override, got {:?}", e),
+    };
+    assert_eq!(tag.tag, "_");
+    match tag.operand.node {
+        Expression::Call(call) => {
+            assert_eq!(*call.function, Expression::Symbol("GetPlayerState".into()));
+            assert_eq!(
+                call.arguments,
+                vec![Spanned::from(Argument::Positional(
+                    Expression::Symbol("id".into()).into()
+                ))]
+            );
+        }
+        e => panic!("expected call, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_tag_override_binds_tighter_than_binary() {
+    let expr = parse_initialiser("new Float:a = Float:i + 1.5;");
+
+    match expr {
+        Expression::BinaryOperation(op) => {
+            assert_eq!(op.operator.token_type, TokenType::Plus);
+            assert!(matches!(op.left.node, Expression::TagOverride(_)));
+            assert_eq!(*op.right, Expression::Literal(Literal::Float(1.5)));
+        }
+        e => panic!("expected binary operation, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_bitwise_binds_tighter_than_comparison() {
+    // unlike C, pawncc binds `&`, `^` and `|` tighter than the relational and
+    // equality operators
+    let cases = [
+        ("new a = 4 & 1 == 0;", TokenType::Equal, TokenType::BitAnd),
+        (
+            "new a = 8 ^ 8 != 0;",
+            TokenType::NotEqual,
+            TokenType::BitXor,
+        ),
+        ("new a = b | c < d;", TokenType::LowerThan, TokenType::BitOr),
+        ("new a = b == c | d;", TokenType::Equal, TokenType::BitOr),
+    ];
+    for (source, outer, inner) in cases.iter() {
+        let op = match parse_initialiser(source) {
+            Expression::BinaryOperation(op) => op,
+            e => panic!("expected binary operation, got {:?}", e),
+        };
+        assert_eq!(op.operator.token_type, *outer, "{}", source);
+        let operand = if matches!(op.left.node, Expression::BinaryOperation(_)) {
+            &op.left.node
+        } else {
+            &op.right.node
+        };
+        match operand {
+            Expression::BinaryOperation(op) => {
+                assert_eq!(op.operator.token_type, *inner, "{}", source)
+            }
+            e => panic!("{}: expected binary operation, got {:?}", source, e),
+        }
+    }
+}
+
+#[test]
+fn test_tag_override_ternary() {
+    // the colon after `b` belongs to the ternary, even without spaces
+    let expr = parse_initialiser("new a = c?b:Text3D:INVALID_3DTEXT_ID;");
+
+    match expr {
+        Expression::TernaryOperation(op) => {
+            assert_eq!(*op.condition, Expression::Symbol("c".into()));
+            assert_eq!(*op.left, Expression::Symbol("b".into()));
+            match op.right.node {
+                Expression::TagOverride(tag) => {
+                    assert_eq!(tag.tag, "Text3D");
+                    assert_eq!(*tag.operand, Expression::Symbol("INVALID_3DTEXT_ID".into()));
+                }
+                e => panic!("expected tag override, got {:?}", e),
+            }
+        }
+        e => panic!("expected ternary, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_tag_override_inside_ternary_brackets() {
+    let expr = parse_initialiser("new a = c ? (bool:x) : false;");
+
+    match expr {
+        Expression::TernaryOperation(op) => {
+            assert!(matches!(op.left.node, Expression::TagOverride(_)));
+        }
+        e => panic!("expected ternary, got {:?}", e),
+    }
+}
+
+#[cfg(test)]
+fn parse_functions(source: &str) -> Vec<Function> {
+    let mut p = Parser::new(Lexer::new(source).lex());
+    p.parse().expect("failed to parse");
+
+    p.root
+        .children
+        .into_iter()
+        .map(|n| match n.expr {
+            Expression::Function(f) => *f,
+            e => panic!("expected function, got {:?}", e),
+        })
+        .collect()
+}
+
+#[test]
+fn test_function_specifiers() {
+    let functions = parse_functions(
+        "
+#include <a_samp>
+
+forward OnTick();
+public OnTick() {}
+stock Float:Half(Float:value) {
+    return_value = value / 2.0;
+}
+static stock Helper(a, b);
+main() {
+    new a = 4;
+    Helper(a, 5);
+}
+",
+    );
+
+    assert_eq!(functions.len(), 5);
+
+    assert_eq!(functions[0].kind, FunctionKind::Forward);
+    assert_eq!(functions[0].name, "OnTick");
+    assert_eq!(functions[0].body, None);
+
+    assert_eq!(functions[1].kind, FunctionKind::Definition);
+    assert!(functions[1].is_public);
+    assert_eq!(functions[1].body, Some(vec![]));
+
+    assert!(functions[2].is_stock);
+    assert_eq!(functions[2].tag, "Float");
+    assert_eq!(functions[2].parameters.len(), 1);
+    assert_eq!(functions[2].parameters[0].name, Some("value".into()));
+    assert_eq!(functions[2].parameters[0].tags, vec!["Float"]);
+    assert_eq!(functions[2].body.as_ref().map(|b| b.len()), Some(1));
+
+    assert!(functions[3].is_static && functions[3].is_stock);
+    assert_eq!(functions[3].parameters.len(), 2);
+    assert_eq!(functions[3].body, None);
+
+    assert!(!functions[4].is_public && !functions[4].is_stock);
+    assert_eq!(functions[4].name, "main");
+    assert_eq!(functions[4].body.as_ref().map(|b| b.len()), Some(2));
+}
+
+#[test]
+fn test_function_natives() {
+    let functions = parse_functions(
+        "
+native Float:floatsqroot(Float:value);
+native SendClientMessage(playerid, color, const message[]) = -1;
+native print2(const s[]) = print;
+native Lowest() = -2147483648;
+",
+    );
+
+    assert_eq!(functions.len(), 4);
+    assert!(functions.iter().all(|f| f.kind == FunctionKind::Native));
+    assert_eq!(functions[0].tag, "Float");
+    assert_eq!(functions[0].native_alias, None);
+    assert_eq!(functions[1].native_alias, Some(NativeAlias::Index(-1)));
+    assert_eq!(
+        functions[2].native_alias,
+        Some(NativeAlias::Name("print".into()))
+    );
+    assert_eq!(
+        functions[3].native_alias,
+        Some(NativeAlias::Index(i32::MIN))
+    );
+}
+
+#[test]
+fn test_function_parameters() {
+    let functions = parse_functions(
+        r#"
+stock Params(&ref, const arr[], grid[][], Float:x = 1.0, str[] = "", &a = 0, data[E_DATA], {Float,_}:...);
+forward Format(const fmt[], tag:...);
+"#,
+    );
+
+    let params = &functions[0].parameters;
+    assert_eq!(params.len(), 8);
+
+    assert_eq!(params[0].name, Some("ref".into()));
+    assert!(params[0].is_reference && !params[0].is_const);
+    assert_eq!(params[0].tags, vec!["_"]);
+
+    assert!(params[1].is_const);
+    assert_eq!(params[1].dimensions, vec![None]);
+
+    assert_eq!(params[2].dimensions, vec![None, None]);
+
+    assert_eq!(params[3].tags, vec!["Float"]);
+    assert_eq!(
+        params[3].default,
+        Some(Expression::Literal(Literal::Float(1.0)).into())
+    );
+
+    assert_eq!(params[4].dimensions, vec![None]);
+    assert_eq!(
+        params[4].default,
+        Some(Expression::Literal(Literal::String(StringLiteral::new(""))).into())
+    );
+
+    assert!(params[5].is_reference);
+    assert_eq!(
+        params[5].default,
+        Some(Expression::Literal(Literal::Integer(0)).into())
+    );
+
+    assert_eq!(
+        params[6].dimensions,
+        vec![Some(Expression::Symbol("E_DATA".into()).into())]
+    );
+
+    assert_eq!(
+        params[7],
+        Parameter {
+            name: None,
+            tags: vec!["Float".into(), "_".into()],
+            is_const: false,
+            is_reference: false,
+            is_variadic: true,
+            dimensions: vec![],
+            default: None,
+        }
+    );
+
+    let params = &functions[1].parameters;
+    assert!(params[1].is_variadic);
+    assert_eq!(params[1].tags, vec!["tag"]);
+}
+
+#[test]
+fn test_call_named_and_skipped_arguments() {
+    let expr = parse_initialiser("new a = func(a, _, .name = 5, c);");
+
+    match expr {
+        Expression::Call(call) => assert_eq!(
+            call.arguments,
+            vec![
+                Spanned::from(Argument::Positional(Expression::Symbol("a".into()).into())),
+                Spanned::from(Argument::Skipped),
+                Argument::Named {
+                    name: "name".into(),
+                    value: Expression::Literal(Literal::Integer(5)).into()
+                }
+                .into(),
+                Spanned::from(Argument::Positional(Expression::Symbol("c".into()).into())),
+            ]
+        ),
+        e => panic!("expected call, got {:?}", e),
+    }
+}
+
+#[cfg(test)]
+fn parse_body(source: &str) -> Vec<Statement> {
+    parse_functions(source)
+        .remove(0)
+        .body
+        .expect("expected function body")
+        .into_iter()
+        .map(|s| s.node)
+        .collect()
+}
+
+#[test]
+fn test_statement_if_else() {
+    let body = parse_body(
+        "
+main() {
+    new a;
+    if(a == 3) {
+        a++;
+    } else if(a != 3) {
+        a--;
+    } else {
+        a = 0;
+    }
+    return a;
+}
+",
+    );
+
+    assert_eq!(body.len(), 3);
+    assert!(matches!(body[0], Statement::Declaration(_)));
+    match &body[1] {
+        Statement::If(s) => {
+            assert!(matches!(s.body.node, Statement::Block(_)));
+            match s.otherwise.as_deref() {
+                Some(Statement::If(s)) => {
+                    assert!(matches!(s.otherwise.as_deref(), Some(Statement::Block(_))))
+                }
+                s => panic!("expected else if, got {:?}", s),
+            }
+        }
+        s => panic!("expected if, got {:?}", s),
+    }
+    assert_eq!(
+        body[2],
+        Statement::Return(Some(Expression::Symbol("a".into()).into()))
+    );
+}
+
+#[test]
+fn test_statement_loops() {
+    let body = parse_body(
+        "
+main() {
+    for(new i = 0; i < 10; i++) continue;
+    for(i = 0, j = 1; ; ) {
+        break;
+    }
+    while(a) a--;
+    do {
+        a++;
+    } while(a < 5);
+}
+",
+    );
+
+    assert_eq!(body.len(), 4);
+    match &body[0] {
+        Statement::For(s) => {
+            assert!(matches!(
+                s.initialiser.as_deref(),
+                Some(Statement::Declaration(_))
+            ));
+            assert!(s.condition.is_some() && s.increment.is_some());
+            assert_eq!(s.body.node, Statement::Continue);
+        }
+        s => panic!("expected for, got {:?}", s),
+    }
+    match &body[1] {
+        Statement::For(s) => {
+            match s.initialiser.as_deref() {
+                Some(Statement::Expression(Spanned {
+                    node: Expression::BinaryOperation(op),
+                    ..
+                })) => assert_eq!(op.operator.token_type, TokenType::Comma),
+                s => panic!("expected comma expression, got {:?}", s),
+            }
+            assert_eq!(s.condition, None);
+            assert_eq!(s.increment, None);
+        }
+        s => panic!("expected for, got {:?}", s),
+    }
+    assert!(matches!(body[2], Statement::While(_)));
+    assert!(matches!(body[3], Statement::DoWhile(_)));
+}
+
+#[test]
+fn test_statement_switch() {
+    let body = parse_body(
+        "
+main() {
+    switch(a) {
+        case 1, 2, 5..10: b = 1;
+        case MY_CONST: {
+            b = 2;
+        }
+        default: b = 3;
+    }
+}
+",
+    );
+
+    let switch = match &body[0] {
+        Statement::Switch(s) => s,
+        s => panic!("expected switch, got {:?}", s),
+    };
+    assert_eq!(switch.subject, Expression::Symbol("a".into()));
+    assert_eq!(switch.cases.len(), 2);
+    assert_eq!(
+        switch.cases[0].labels,
+        vec![
+            CaseLabel::Value(Expression::Literal(Literal::Integer(1)).into()),
+            CaseLabel::Value(Expression::Literal(Literal::Integer(2)).into()),
+            CaseLabel::Range(
+                Expression::Literal(Literal::Integer(5)).into(),
+                Expression::Literal(Literal::Integer(10)).into()
+            ),
+        ]
+    );
+    assert_eq!(
+        switch.cases[1].labels,
+        vec![CaseLabel::Value(
+            Expression::Symbol("MY_CONST".into()).into()
+        )]
+    );
+    assert!(matches!(switch.cases[1].body.node, Statement::Block(_)));
+    assert!(matches!(
+        switch.default.as_deref(),
+        Some(Statement::Expression(_))
+    ));
+}
+
+#[test]
+fn test_statement_labels() {
+    let body = parse_body(
+        "
+main() {
+retry:
+    if(Attempt()) goto retry;
+    ;
+}
+",
+    );
+
+    assert_eq!(body[0], Statement::Label("retry".into()));
+    match &body[1] {
+        Statement::If(s) => assert_eq!(s.body.node, Statement::Goto("retry".into())),
+        s => panic!("expected if, got {:?}", s),
+    }
+    assert_eq!(body[2], Statement::Empty);
+}
+
+#[cfg(test)]
+fn parse_enums(source: &str) -> Vec<Enum> {
+    let mut p = Parser::new(Lexer::new(source).lex());
+    p.parse().expect("failed to parse");
+
+    p.root
+        .children
+        .into_iter()
+        .map(|n| match n.expr {
+            Expression::Enum(e) => *e,
+            e => panic!("expected enum, got {:?}", e),
+        })
+        .collect()
+}
+
+#[test]
+fn test_enum_struct() {
+    let enums = parse_enums(
+        "
+enum E_PLAYER {
+    E_NAME[MAX_PLAYER_NAME],
+    Float:E_HEALTH,
+    E_ID,
+}
+",
+    );
+
+    assert_eq!(enums[0].name, Some("E_PLAYER".into()));
+    assert_eq!(enums[0].tag, None);
+    assert_eq!(enums[0].increment, None);
+    assert_eq!(
+        enums[0].members,
+        vec![
+            EnumMember {
+                name: "E_NAME".into(),
+                tag: "_".into(),
+                size: Some(Expression::Symbol("MAX_PLAYER_NAME".into()).into()),
+                value: None,
+            },
+            EnumMember {
+                name: "E_HEALTH".into(),
+                tag: "Float".into(),
+                size: None,
+                value: None,
+            },
+            EnumMember {
+                name: "E_ID".into(),
+                tag: "_".into(),
+                size: None,
+                value: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_enum_increment() {
+    let enums = parse_enums(
+        "
+enum (<<= 1) { A = 1, B, C };
+enum E:(+=2) { D }
+",
+    );
+
+    assert_eq!(enums[0].name, None);
+    let increment = enums[0].increment.as_ref().unwrap();
+    assert_eq!(increment.operator.token_type, TokenType::BitLeftAssign);
+    assert_eq!(increment.amount, Expression::Literal(Literal::Integer(1)));
+    assert_eq!(enums[0].members.len(), 3);
+    assert_eq!(
+        enums[0].members[0].value,
+        Some(Expression::Literal(Literal::Integer(1)).into())
+    );
+
+    assert_eq!(enums[1].name, None);
+    assert_eq!(enums[1].tag, Some("E".into()));
+    let increment = enums[1].increment.as_ref().unwrap();
+    assert_eq!(increment.operator.token_type, TokenType::PlusAssign);
+    assert_eq!(increment.amount, Expression::Literal(Literal::Integer(2)));
+}
+
+#[cfg(test)]
+fn parse_variable(source: &str) -> (Variable, Option<Expression>) {
+    let mut p = Parser::new(Lexer::new(source).lex());
+    p.parse().expect("failed to parse");
+
+    match p.root.children.remove(0).expr {
+        Expression::Variable(mut v) => {
+            let initialiser = v.initialiser.take().map(|i| i.node);
+            (*v, initialiser)
+        }
+        e => panic!("expected variable, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_array_dimensions() {
+    let (var, initialiser) = parse_variable("new gPlayerData[MAX_PLAYERS][E_PLAYER];");
+
+    assert_eq!(var.name, "gPlayerData");
+    assert_eq!(
+        var.dimensions,
+        vec![
+            Some(Expression::Symbol("MAX_PLAYERS".into()).into()),
+            Some(Expression::Symbol("E_PLAYER".into()).into()),
+        ]
+    );
+    assert_eq!(initialiser, None);
+
+    let (var, _) = parse_variable("new packed[10 char];");
+    match var.dimensions[0].as_deref() {
+        Some(Expression::UnaryOperation(op)) => {
+            assert_eq!(op.operator.token_type, TokenType::Char);
+            assert!(op.postfix);
+        }
+        d => panic!("expected char operator, got {:?}", d),
+    }
+}
+
+#[test]
+fn test_array_initialisers() {
+    let (var, initialiser) = parse_variable("new a[3][] = {{1},{2,3},{}};");
+
+    assert_eq!(
+        var.dimensions,
+        vec![Some(Expression::Literal(Literal::Integer(3)).into()), None]
+    );
+    assert_eq!(
+        initialiser,
+        Some(Expression::Literal(Literal::Array(Box::new(Array {
+            elements: vec![
+                Expression::Literal(Literal::Array(Box::new(Array {
+                    elements: vec![Expression::Literal(Literal::Integer(1)).into()],
+                    ellipsis: false,
+                })))
+                .into(),
+                Expression::Literal(Literal::Array(Box::new(Array {
+                    elements: vec![
+                        Expression::Literal(Literal::Integer(2)).into(),
+                        Expression::Literal(Literal::Integer(3)).into()
+                    ],
+                    ellipsis: false,
+                })))
+                .into(),
+                Expression::Literal(Literal::Array(Box::new(Array {
+                    elements: vec![],
+                    ellipsis: false,
+                })))
+                .into(),
+            ],
+            ellipsis: false,
+        }))))
+    );
+
+    let (_, initialiser) = parse_variable("new b[10] = {0, 1, ...};");
+    assert_eq!(
+        initialiser,
+        Some(Expression::Literal(Literal::Array(Box::new(Array {
+            elements: vec![
+                Expression::Literal(Literal::Integer(0)).into(),
+                Expression::Literal(Literal::Integer(1)).into()
+            ],
+            ellipsis: true,
+        }))))
+    );
+
+    let (var, initialiser) = parse_variable(r#"new s[] = "hello";"#);
+    assert_eq!(var.dimensions, vec![None]);
+    assert_eq!(
+        initialiser,
+        Some(Expression::Literal(Literal::String(StringLiteral::new(
+            "hello"
+        ))))
+    );
+
+    let (_, initialiser) = parse_variable(r#"new data[E_PLAYER] = {"name", Float:100.0, 5};"#);
+    match initialiser {
+        Some(Expression::Literal(Literal::Array(array))) => {
+            assert_eq!(array.elements.len(), 3);
+            assert!(matches!(array.elements[1].node, Expression::TagOverride(_)));
+        }
+        i => panic!("expected array, got {:?}", i),
+    }
+}
+
+#[cfg(test)]
+fn parse_variables(source: &str) -> Vec<Variable> {
+    let mut p = Parser::new(Lexer::new(source).lex());
+    p.parse().expect("failed to parse");
+
+    p.root
+        .children
+        .into_iter()
+        .filter_map(|n| match n.expr {
+            Expression::Variable(v) => Some(*v),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_declaration_multiple_declarators() {
+    let vars = parse_variables("new a, Float:b = 1.0, c[4];");
+
+    assert_eq!(vars.len(), 3);
+    assert_eq!(vars[0].name, "a");
+    assert_eq!(vars[1].tag, "Float");
+    assert_eq!(
+        vars[2].dimensions,
+        vec![Some(Expression::Literal(Literal::Integer(4)).into())]
+    );
+    assert!(vars
+        .iter()
+        .all(|v| v.storage == StorageClass::New && !v.is_const));
+}
+
+#[test]
+fn test_declaration_storage_classes() {
+    let vars = parse_variables(
+        "
+static gCount;
+static const gName[] = \"rook\";
+new const gLimit = 10;
+stock const Float:gScale = 2.0, gOther;
+public gPublic;
+const MAX_THINGS = 5;
+static Helper() {}
+",
+    );
+
+    let classes: Vec<_> = vars
+        .iter()
+        .map(|v| (v.storage, v.is_stock, v.is_const))
+        .collect();
+    assert_eq!(
+        classes,
+        vec![
+            (StorageClass::Static, false, false),
+            (StorageClass::Static, false, true),
+            (StorageClass::New, false, true),
+            (StorageClass::New, true, true),
+            (StorageClass::New, true, true),
+            (StorageClass::Public, false, false),
+            (StorageClass::Const, false, true),
+        ]
+    );
+}
+
+#[test]
+fn test_declaration_specifier_order() {
+    // pawncc takes const, stock, static and public in any order
+    let cases = [
+        ("static stock a;", StorageClass::Static, true, false),
+        ("stock static a;", StorageClass::Static, true, false),
+        (
+            "static stock const X = 5;",
+            StorageClass::Static,
+            true,
+            true,
+        ),
+        (
+            "const static stock X = 5;",
+            StorageClass::Static,
+            true,
+            true,
+        ),
+        ("new static a;", StorageClass::Static, false, false),
+        ("new stock a;", StorageClass::New, true, false),
+        ("stock new a;", StorageClass::New, true, false),
+        ("new const stock a;", StorageClass::New, true, true),
+        ("public stock a;", StorageClass::Public, true, false),
+        ("stock public const a;", StorageClass::Public, true, true),
+        ("new public a;", StorageClass::Public, false, false),
+        ("const stock X = 5;", StorageClass::New, true, true),
+        ("const X = 5;", StorageClass::Const, false, true),
+    ];
+    for (source, storage, is_stock, is_const) in cases.iter() {
+        let vars = parse_variables(source);
+        assert_eq!(vars.len(), 1, "{}", source);
+        assert_eq!(
+            (vars[0].storage, vars[0].is_stock, vars[0].is_const),
+            (*storage, *is_stock, *is_const),
+            "{}",
+            source
+        );
+    }
+
+    let body = parse_body("main() {\n    new static calls;\n    static const limit = 2;\n}\n");
+    let classes: Vec<_> = body
+        .iter()
+        .flat_map(|s| match s {
+            Statement::Declaration(nodes) => nodes.iter(),
+            s => panic!("expected declaration, got {:?}", s),
+        })
+        .map(|n| match &n.expr {
+            Expression::Variable(v) => (v.storage, v.is_const),
+            e => panic!("expected variable, got {:?}", e),
+        })
+        .collect();
+    assert_eq!(
+        classes,
+        vec![(StorageClass::Static, false), (StorageClass::Static, true)]
+    );
+}
+
+#[test]
+fn test_declaration_locals() {
+    let body = parse_body(
+        "
+main() {
+    static calls;
+    new const a = 1, b = 2;
+    for(new i = 0, j = 10; i < j; i++, j--) {}
+}
+",
+    );
+
+    match &body[0] {
+        Statement::Declaration(nodes) => assert_eq!(nodes.len(), 1),
+        s => panic!("expected declaration, got {:?}", s),
+    }
+    match &body[1] {
+        Statement::Declaration(nodes) => assert_eq!(nodes.len(), 2),
+        s => panic!("expected declaration, got {:?}", s),
+    }
+    match &body[2] {
+        Statement::For(s) => match s.initialiser.as_deref() {
+            Some(Statement::Declaration(nodes)) => assert_eq!(nodes.len(), 2),
+            s => panic!("expected declaration, got {:?}", s),
+        },
+        s => panic!("expected for, got {:?}", s),
+    }
+}
+
+#[test]
+fn test_state_functions() {
+    let functions = parse_functions(
+        "
+main() {
+    state idle;
+}
+public OnTick() <idle> {}
+public OnTick() <auto:stateA, stateB> {
+    state (IsReady()) auto:stateB;
+}
+public OnTick() <> {}
+entry() <idle> {}
+",
+    );
+
+    assert_eq!(functions[0].states, None);
+    assert_eq!(
+        functions[0].body,
+        Some(vec![Statement::State(Box::new(StateTransition {
+            condition: None,
+            automaton: None,
+            state: "idle".into(),
+        }))
+        .into()])
+    );
+
+    assert_eq!(
+        functions[1].states,
+        Some(StateList {
+            automaton: None,
+            states: vec!["idle".into()],
+        })
+    );
+
+    assert_eq!(
+        functions[2].states,
+        Some(StateList {
+            automaton: Some("auto".into()),
+            states: vec!["stateA".into(), "stateB".into()],
+        })
+    );
+    match &functions[2].body.as_ref().unwrap()[0].node {
+        Statement::State(s) => {
+            assert!(s.condition.is_some());
+            assert_eq!(s.automaton, Some("auto".into()));
+            assert_eq!(s.state, "stateB");
+        }
+        s => panic!("expected state transition, got {:?}", s),
+    }
+
+    assert_eq!(
+        functions[3].states,
+        Some(StateList {
+            automaton: None,
+            states: vec![],
+        })
+    );
+    assert!(!functions[3].is_state_entry());
+    assert!(functions[4].is_state_entry());
+}
+
+#[test]
+fn test_operator_overloads() {
+    let functions = parse_functions(
+        "
+native Float:operator*(Float:oper1, Float:oper2) = floatmul;
+forward Float:operator%(Float:oper1, Float:oper2);
+stock Float:operator+(Float:a, Float:b) {
+    return Float:(_:a + _:b);
+}
+stock Fixed:operator=(value) return Fixed:(value << 16);
+stock Float:operator-(Float:a) return a ^ Float:cellmin;
+stock bool:operator!(Float:a) return !_:a;
+Add(a, b) return a + b;
+",
+    );
+
+    let operators: Vec<(String, Option<TokenType>, usize)> = functions
+        .iter()
+        .map(|f| {
+            (
+                f.name.clone(),
+                f.operator.as_ref().map(|o| o.token_type),
+                f.parameters.len(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        operators,
+        vec![
+            ("operator*".into(), Some(TokenType::Asterisk), 2),
+            ("operator%".into(), Some(TokenType::Percent), 2),
+            ("operator+".into(), Some(TokenType::Plus), 2),
+            ("operator=".into(), Some(TokenType::Assign), 1),
+            ("operator-".into(), Some(TokenType::Minus), 1),
+            ("operator!".into(), Some(TokenType::Bang), 1),
+            ("Add".into(), None, 2),
+        ]
+    );
+    assert_eq!(functions[0].kind, FunctionKind::Native);
+    assert_eq!(
+        functions[0].native_alias,
+        Some(NativeAlias::Name("floatmul".into()))
+    );
+    assert_eq!(functions[1].kind, FunctionKind::Forward);
+    assert!(functions[2].is_operator() && functions[2].is_stock);
+    assert_eq!(functions[2].tag, "Float");
+    assert!(!functions[6].is_operator());
+}
+
+#[test]
+fn test_parse_errors() {
+    let mut p = Parser::new(Lexer::new("new a = ;\nstock operator~(a) {}\nnew b c;").lex());
+    let diagnostics = p.parse().expect_err("expected errors");
+
+    assert_eq!(diagnostics.len(), 3);
+
+    assert_eq!(
+        diagnostics[0].error,
+        ParseError::ExpectedExpression {
+            found: Some(TokenType::Semicolon)
+        }
+    );
+    assert_eq!(diagnostics[0].code(), 29);
+    assert_eq!(
+        diagnostics[0].span,
+        Span {
+            line_start: 1,
+            line_end: 1,
+            column_start: 9,
+            column_end: 10,
+        }
+    );
+
+    assert_eq!(
+        diagnostics[1].error,
+        ParseError::InvalidOperator {
+            found: Some(TokenType::Tilde)
+        }
+    );
+
+    assert_eq!(diagnostics[2].code(), 1);
+    assert_eq!(diagnostics[2].error.expected(), &[TokenType::Semicolon][..]);
+    assert_eq!(diagnostics[2].error.found(), Some(TokenType::Symbol));
+}
+
+#[cfg(test)]
+fn parse_optional(source: &str) -> Result<Vec<Statement>, Vec<TokenType>> {
+    let options = Options {
+        optional_semicolons: true,
+        ..Options::default()
+    };
+    let mut p = Parser::with_options(Lexer::new(source).lex(), options);
+    match p.parse() {
+        Ok(_) => {}
+        Err(diagnostics) => {
+            return Err(diagnostics.iter().filter_map(|d| d.error.found()).collect())
+        }
+    }
+
+    let function = match p.root.children.remove(0).expr {
+        Expression::Function(f) => f,
+        e => panic!("expected function, got {:?}", e),
+    };
+    Ok(function
+        .body
+        .expect("expected function body")
+        .into_iter()
+        .map(|s| s.node)
+        .collect())
+}
+
+#[test]
+fn test_optional_semicolons() {
+    let body = parse_optional("f() {\n    a = 1\n    b = 2;\n    return\n}\nnew c = 3\n")
+        .expect("failed to parse");
+    assert_eq!(body.len(), 3);
+    assert!(matches!(body[0], Statement::Expression(_)));
+    assert!(matches!(body[1], Statement::Expression(_)));
+    assert_eq!(body[2], Statement::Return(None));
+
+    // Statements on the same line still need a semicolon between them.
+    assert_eq!(
+        parse_optional("f() {\n    a = 1 b = 2\n}"),
+        Err(vec![TokenType::Symbol])
+    );
+
+    // As in pawncc semicolons are optional by default, without the option a
+    // newline does not end a statement.
+    let mut p = Parser::new(Lexer::new("f() {\n    a = 1\n}").lex());
+    assert!(p.parse().is_ok());
+    let options = Options {
+        optional_semicolons: false,
+        ..Options::default()
+    };
+    let mut p = Parser::with_options(Lexer::new("f() {\n    a = 1\n}").lex(), options);
+    assert!(p.parse().is_err());
+}
+
+#[test]
+fn test_optional_semicolons_ambiguous() {
+    // An incomplete expression continues on the next line.
+    let body = parse_optional("f() {\n    a = b +\n        c\n}").expect("failed to parse");
+    assert_eq!(body.len(), 1);
+
+    // Like pawncc, an expression that could go on does, so a bracket or a
+    // minus on the next line belongs to the statement above it.
+    let body = parse_optional("f() {\n    a = b\n    (c)\n}").expect("failed to parse");
+    assert_eq!(body.len(), 1);
+    match &body[0] {
+        Statement::Expression(expr) => match &expr.node {
+            Expression::BinaryOperation(op) => {
+                assert!(matches!(op.right.node, Expression::Call(_)))
+            }
+            e => panic!("expected assignment, got {:?}", e),
+        },
+        s => panic!("expected expression, got {:?}", s),
+    }
+
+    let body = parse_optional("f() {\n    a = b\n    -c\n}").expect("failed to parse");
+    assert_eq!(body.len(), 1);
+
+    // A return at the end of a line has no value, the next line is a
+    // statement of its own.
+    let body = parse_optional("f() {\n    return\n    a\n}").expect("failed to parse");
+    assert_eq!(body.len(), 2);
+    assert_eq!(body[0], Statement::Return(None));
+
+    // The semicolons in a for loop header are never optional.
+    assert!(parse_optional("f() {\n    for (i = 0\n    i < 3; i++) {}\n}").is_err());
+}
+
+#[test]
+fn test_pragma_semicolon() {
+    let source =
+        "#pragma semicolon 0\nf() {\n    a = 1\n}\n#pragma semicolon 1\ng() {\n    a = 1\n}";
+    let mut p = Parser::new(Lexer::new(source).lex());
+    let diagnostics = p.parse().expect_err("expected errors");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].error.expected(), &[TokenType::Semicolon][..]);
+    assert_eq!(diagnostics[0].span.line_start, 8);
+}
+
+#[test]
+fn test_literals() {
+    let literal = |source: &str| match parse_initialiser(source) {
+        Expression::Literal(literal) => literal,
+        e => panic!("expected literal, got {:?}", e),
+    };
+
+    assert_eq!(literal("new a = 5;"), Literal::Integer(5));
+    assert_eq!(literal("new a = 2.5;"), Literal::Float(2.5));
+    assert_eq!(literal("new a = true;"), Literal::Bool(true));
+    assert_eq!(literal("new a = false;"), Literal::Bool(false));
+    assert_eq!(literal(r"new a = '\n';"), Literal::Char('\n'));
+    assert!(matches!(literal("new a[] = {1, 2};"), Literal::Array(_)));
+
+    match literal(r#"new a[] = !"abc";"#) {
+        Literal::String(string) => {
+            assert!(string.packed);
+            assert_eq!(string.value(), "abc");
+        }
+        l => panic!("expected string, got {:?}", l),
+    }
+
+    // a `!` apart from the string is a logical not
+    match parse_initialiser(r#"new a = ! "abc";"#) {
+        Expression::UnaryOperation(op) => assert_eq!(
+            op.operand.node,
+            Expression::Literal(Literal::String(StringLiteral::new("abc")))
+        ),
+        e => panic!("expected unary operation, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_string_concatenation() {
+    let source = "new a[] = \"abc\" \"\\x41\"\n    ... \"1\";";
+    let string = match parse_initialiser(source) {
+        Expression::Literal(Literal::String(string)) => string,
+        e => panic!("expected string, got {:?}", e),
+    };
+
+    assert!(!string.packed);
+    assert_eq!(
+        string.parts,
+        vec!["abc".to_string(), r"\x41".to_string()]
+            .into_iter()
+            .chain(Some("1".to_string()))
+            .map(Spanned::from)
+            .collect::<Vec<_>>()
+    );
+    // each part is unescaped on its own, so `\x41` does not run into `1`
+    assert_eq!(string.value(), "abcA1");
+
+    let spans: Vec<Span> = string.parts.iter().map(|p| p.span).collect();
+    assert_eq!(
+        spans,
+        vec![
+            Span {
+                line_start: 1,
+                line_end: 1,
+                column_start: 11,
+                column_end: 16,
+            },
+            Span {
+                line_start: 1,
+                line_end: 1,
+                column_start: 17,
+                column_end: 23,
+            },
+            Span {
+                line_start: 2,
+                line_end: 2,
+                column_start: 9,
+                column_end: 12,
+            },
+        ]
+    );
+
+    let mut p = Parser::new(Lexer::new(source).lex());
+    p.parse().expect("failed to parse");
+    let initialiser = match &p.root.children[0].expr {
+        Expression::Variable(v) => v.initialiser.as_ref().unwrap(),
+        e => panic!("expected variable, got {:?}", e),
+    };
+    assert_eq!(initialiser.span, spans[0].to(spans[2]));
+}
+
+// on_default_stack runs `f` on a thread with the 8 MB stack of a main thread,
+// rather than the smaller default of spawned test threads.
+#[cfg(test)]
+fn on_default_stack(f: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+// nest puts `inner` inside `depth` copies of `open` and `close`.
+#[cfg(test)]
+fn nest(open: &str, inner: &str, close: &str, depth: usize) -> String {
+    format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth))
+}
+
+#[test]
+fn test_nesting_too_deep() {
+    on_default_stack(|| {
+        let depth = 100_000;
+        let sources = vec![
+            format!("new a = {};", nest("(", "1", ")", depth)),
+            format!("new a = {}1;", "-".repeat(depth)),
+            format!("new a[] = {};", nest("{", "1", "}", depth)),
+            format!("new a = {}1;", "b ? 1 : ".repeat(depth)),
+            format!("f() {{ {}; }}", "a = ".repeat(depth) + "1"),
+            format!("f() {}", nest("{", "", "}", depth)),
+            format!("f() {{ {} }}", nest("if (a) ", ";", "", depth)),
+        ];
+
+        for source in sources {
+            let mut p = Parser::new(Lexer::new(&format!("{}\ng() {{}}", source)).lex());
+            let diagnostics = p.parse().expect_err("expected an error");
+
+            assert_eq!(diagnostics.len(), 1, "{}: {:?}", &source[..20], diagnostics);
+            assert_eq!(
+                diagnostics[0].error,
+                ParseError::NestingTooDeep {
+                    limit: DEFAULT_MAX_DEPTH
+                },
+                "{}",
+                &source[..20]
+            );
+            // the declaration is skipped and the parser carries on after it
+            assert_eq!(p.root.children.len(), 2);
+            assert!(matches!(p.root.children[1].expr, Expression::Function(_)));
+        }
+    });
+}
+
+#[test]
+fn test_nesting_within_limit() {
+    on_default_stack(|| {
+        let depth = DEFAULT_MAX_DEPTH - 1;
+        let sources = vec![
+            format!("new a = {};", nest("(", "1", ")", depth)),
+            format!("new a[] = {};", nest("{", "1", "}", depth)),
+            format!("new a = {};", "1 + ".repeat(depth) + "1"),
+            format!("f() {}", nest("{", "", "}", depth)),
+        ];
+
+        for source in sources {
+            let mut p = Parser::new(Lexer::new(&source).lex());
+            p.parse().expect("failed to parse");
+            crate::printer::print(&p.root);
+        }
+
+        let options = Options {
+            max_depth: 10,
+            ..Options::default()
+        };
+        let mut p = Parser::with_options(
+            Lexer::new("new a = (((((((((((1)))))))))));").lex(),
+            options,
+        );
+        let diagnostics = p.parse().expect_err("expected an error");
+        assert_eq!(
+            diagnostics[0].error,
+            ParseError::NestingTooDeep { limit: 10 }
+        );
+    });
+}
+
+// chains such as `a + b + c` or `else if` are read, dropped and walked in
+// loops rather than by recursing, so generated code can chain any number of
+// them
+#[test]
+fn test_long_chains() {
+    on_default_stack(|| {
+        let terms = 100_000;
+        let sources = vec![
+            format!("new a = {}1;", "1 + ".repeat(terms)),
+            format!("new b = x{};", " || y".repeat(terms)),
+            format!("new c = d{};", "[0]".repeat(terms)),
+            format!("new e = f{};", "()".repeat(terms)),
+            format!("new g = {}1;", "h(1)[0]++ * ".repeat(terms)),
+            format!("f() {{ if (a) {{}} {} }}", "else if (a) {} ".repeat(terms)),
+        ];
+
+        for source in sources {
+            let mut p = Parser::new(Lexer::new(&source).lex());
+            p.parse().expect("failed to parse");
+            let mut interner = crate::intern::Interner::new();
+            let ast = crate::arena::Ast::new(&p.root, &mut interner);
+            crate::resolve::resolve(&ast, &mut interner);
+            crate::tags::check(&p.root);
+            drop(p);
+            crate::cst::parse(&source);
+        }
+    });
+}
+
+// the passes over the parser's own tree recurse down a chain, but take
+// little enough stack to walk one far longer than any written by hand
+#[test]
+fn test_long_chains_walked() {
+    on_default_stack(|| {
+        let terms = 1_100;
+        let sources = vec![
+            format!("new a = {}1;", "1 + ".repeat(terms)),
+            format!("new c = d{};", "[0]".repeat(terms)),
+            format!("f() {{ if (a) {{}} {} }}", "else if (a) {} ".repeat(terms)),
+        ];
+
+        for source in sources {
+            let mut p = Parser::new(Lexer::new(&source).lex());
+            p.parse().expect("failed to parse");
+            crate::printer::print(&p.root);
+            crate::const_eval::Constants::new().declare_file(&p.root);
+            crate::fold::Fold::fold_node(&mut Identity, p.root);
+        }
+    });
+}
+
+#[cfg(test)]
+struct Identity;
+
+#[cfg(test)]
+impl crate::fold::Fold for Identity {}
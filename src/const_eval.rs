@@ -0,0 +1,556 @@
+use crate::ast::compound_operator;
+use crate::ast::CaseLabel;
+use crate::ast::Enum;
+use crate::ast::Expression;
+use crate::ast::Literal;
+use crate::ast::Node;
+use crate::ast::Spanned;
+use crate::ast::StorageClass;
+use crate::ast::UnaryOperation;
+use crate::ast::Variable;
+use crate::error::Diagnostic;
+use crate::error::ParseError;
+use crate::token::Span;
+use crate::token::TokenType;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// DEFAULT_CELL_BITS is the size of a cell, pawncc is built with 32 bit cells
+/// unless told otherwise and every value is one cell.
+const DEFAULT_CELL_BITS: u32 = 32;
+
+/// CHAR_BITS is the size of a character in a packed string.
+const CHAR_BITS: i64 = 8;
+
+/// Value is the value of a constant expression. Every value in Pawn is a
+/// cell, a `Float:` value is a cell holding the bits of a float and its
+/// arithmetic is that of the operators in `float.inc`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    Integer(i32),
+    Float(f32),
+}
+
+impl Value {
+    /// cell returns the cell holding the value, for a float that is its bits.
+    pub fn cell(self) -> i32 {
+        match self {
+            Value::Integer(value) => value,
+            Value::Float(value) => value.to_bits() as i32,
+        }
+    }
+
+    /// is_true reports whether the value counts as true in a condition.
+    pub fn is_true(self) -> bool {
+        match self {
+            Value::Integer(value) => value != 0,
+            Value::Float(value) => value != 0.0,
+        }
+    }
+
+    // float returns the value as a float, converting an integer the way
+    // `float()` does.
+    fn float(self) -> f32 {
+        match self {
+            Value::Integer(value) => value as f32,
+            Value::Float(value) => value,
+        }
+    }
+}
+
+/// InvalidCellBits is the error from `Constants::set_cell_bits` for a size
+/// pawncc can not be built with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidCellBits(pub u32);
+
+impl fmt::Display for InvalidCellBits {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a cell can not be {} bits, only 16, 32 or 64", self.0)
+    }
+}
+
+// Symbol is what a name stands for when evaluating.
+#[derive(Debug, Clone, PartialEq)]
+enum Symbol {
+    Constant(Value),
+    // an array with the size of each dimension, or a scalar with none.
+    Variable(Vec<i32>),
+    Function,
+    // `__line`, the line it is used on.
+    Line,
+}
+
+/// Constants evaluates constant expressions the way pawncc does, for array
+/// sizes, `case` labels and `#if` conditions. It knows the constants pawncc
+/// predefines, and more are added with `define` or by declaring constants,
+/// enums and arrays with `declare`.
+///
+/// Integer arithmetic wraps at 32 bits, and `/` and `%` round towards
+/// negative infinity as in pawncc. An operator with a `Float:` operand does
+/// float arithmetic, converting an integer operand the way `float.inc`
+/// does.
+///
+/// Names are not scoped, they are known from the point they are declared.
+/// The constants local to a block can be declared into a clone, which is
+/// dropped at the end of the block.
+#[derive(Debug, Clone)]
+pub struct Constants {
+    symbols: HashMap<String, Symbol>,
+    cell_bits: u32,
+}
+
+impl Default for Constants {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Constants {
+    /// new returns the constants pawncc predefines, with its default options.
+    pub fn new() -> Constants {
+        let mut constants = Constants {
+            symbols: HashMap::new(),
+            cell_bits: DEFAULT_CELL_BITS,
+        };
+        constants.define_cell_constants();
+        let predefined = [
+            ("charbits", CHAR_BITS as i32),
+            ("charmax", 255),
+            ("charmin", 0),
+            ("__Pawn", 0x030A),
+            ("debug", 1),
+        ];
+        for (name, value) in predefined.iter() {
+            constants.define(name, Value::Integer(*value));
+        }
+        constants
+            .symbols
+            .insert(String::from("__line"), Symbol::Line);
+        constants
+    }
+
+    /// set_cell_bits sets the size of a cell, 16, 32 or 64 bits as pawncc may
+    /// be built with, and the predefined constants that follow from it:
+    /// `cellbits`, `cellmax`, `cellmin` and `ucharmax`. Values are 32 bits
+    /// whatever the size of a cell, so with 64 bit cells `cellmax` and
+    /// `cellmin` are the limits of a value rather than of a cell. Any other
+    /// size is an error and changes nothing.
+    pub fn set_cell_bits(&mut self, bits: u32) -> Result<(), InvalidCellBits> {
+        match bits {
+            16 | 32 | 64 => {
+                self.cell_bits = bits;
+                self.define_cell_constants();
+                Ok(())
+            }
+            _ => Err(InvalidCellBits(bits)),
+        }
+    }
+
+    // define_cell_constants defines the predefined constants that follow from
+    // the size of a cell.
+    fn define_cell_constants(&mut self) {
+        let bits = self.cell_bits;
+        let value_bits = bits.min(32);
+        let predefined = [
+            ("cellbits", bits as i64),
+            ("cellmax", (1i64 << (value_bits - 1)) - 1),
+            ("cellmin", -(1i64 << (value_bits - 1))),
+            ("ucharmax", (1i64 << (value_bits - CHAR_BITS as u32)) - 1),
+        ];
+        for (name, value) in predefined.iter() {
+            self.define(name, Value::Integer(*value as i32));
+        }
+    }
+
+    /// define defines a constant, or changes the value of one, such as a
+    /// macro given on the command line or a predefined constant that depends
+    /// on the compiler options.
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.symbols
+            .insert(String::from(name), Symbol::Constant(value));
+    }
+
+    /// get returns the value of a constant.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.symbols.get(name) {
+            Some(Symbol::Constant(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// declare declares the names in a declaration, either top-level or
+    /// local. A `const` gets its value, an enum the values of its members
+    /// and its own value, which is its size. Variables and functions are
+    /// known but have no value, the sizes of arrays are kept for `sizeof`.
+    /// A name whose value is in error is still declared, as zero.
+    pub fn declare(&mut self, node: &Node) -> Vec<Diagnostic> {
+        let mut diagnostics = vec![];
+        match &node.expr {
+            Expression::Variable(variable) if variable.storage == StorageClass::Const => {
+                let value = match &variable.initialiser {
+                    Some(initialiser) => self.eval(initialiser),
+                    None => Err(Diagnostic::new(ParseError::NotConstant, node.span)),
+                };
+                let value = value.unwrap_or_else(|diagnostic| {
+                    diagnostics.push(diagnostic);
+                    Value::Integer(0)
+                });
+                self.define(&variable.name, value);
+            }
+            Expression::Variable(variable) => {
+                let dimensions = self.dimensions(variable, &mut diagnostics);
+                self.symbols
+                    .insert(variable.name.clone(), Symbol::Variable(dimensions));
+            }
+            Expression::Enum(e) => self.declare_enum(e, &mut diagnostics),
+            Expression::Function(function) => {
+                self.symbols.insert(function.name.clone(), Symbol::Function);
+            }
+            _ => {}
+        }
+        diagnostics
+    }
+
+    /// declare_file declares every top-level declaration of a file in order.
+    pub fn declare_file(&mut self, root: &Node) -> Vec<Diagnostic> {
+        root.children
+            .iter()
+            .flat_map(|node| self.declare(node))
+            .collect()
+    }
+
+    /// eval returns the value of a constant expression.
+    pub fn eval(&self, expr: &Spanned<Expression>) -> Result<Value, Diagnostic> {
+        self.value(&expr.node, expr.span)
+    }
+
+    /// eval_condition returns whether the condition of an `#if` holds. The
+    /// condition is parsed with `parse::parse_expression_str`.
+    pub fn eval_condition(&self, expr: &Spanned<Expression>) -> Result<bool, Diagnostic> {
+        Ok(self.eval(expr)?.is_true())
+    }
+
+    /// eval_array_size returns the size given for a dimension of an array,
+    /// which must be above zero.
+    pub fn eval_array_size(&self, expr: &Spanned<Expression>) -> Result<i32, Diagnostic> {
+        match self.eval(expr)?.cell() {
+            size if size > 0 => Ok(size),
+            _ => Err(Diagnostic::new(ParseError::InvalidArraySize, expr.span)),
+        }
+    }
+
+    /// eval_case_label returns the lowest and highest value a `case` label
+    /// matches, which are the same for a single value.
+    pub fn eval_case_label(&self, label: &CaseLabel) -> Result<(i32, i32), Diagnostic> {
+        match label {
+            CaseLabel::Value(value) => {
+                let value = self.eval(value)?.cell();
+                Ok((value, value))
+            }
+            CaseLabel::Range(low, high) => Ok((self.eval(low)?.cell(), self.eval(high)?.cell())),
+        }
+    }
+
+    fn value(&self, expr: &Expression, span: Span) -> Result<Value, Diagnostic> {
+        match expr {
+            Expression::Literal(Literal::Integer(value)) => Ok(Value::Integer(*value)),
+            Expression::Literal(Literal::Float(value)) => Ok(Value::Float(*value)),
+            Expression::Literal(Literal::Bool(value)) => Ok(Value::Integer(*value as i32)),
+            Expression::Literal(Literal::Char(value)) => Ok(Value::Integer(*value as i32)),
+            Expression::Symbol(name) => match self.symbols.get(name) {
+                Some(Symbol::Constant(value)) => Ok(*value),
+                Some(Symbol::Line) => Ok(Value::Integer(span.line_start)),
+                Some(_) => Err(Diagnostic::new(ParseError::NotConstant, span)),
+                None => Err(Diagnostic::new(
+                    ParseError::UndefinedSymbol { name: name.clone() },
+                    span,
+                )),
+            },
+            // a tag override changes how the cell is read, not its bits
+            Expression::TagOverride(tag) => {
+                let value = self.eval(&tag.operand)?;
+                Ok(match (tag.tag.as_str(), value) {
+                    ("Float", Value::Integer(value)) => Value::Float(f32::from_bits(value as u32)),
+                    ("Float", value) => value,
+                    (_, value) => Value::Integer(value.cell()),
+                })
+            }
+            Expression::UnaryOperation(op) => self.unary(op, span),
+            Expression::BinaryOperation(op) => {
+                let operator = op.operator.token_type;
+                match operator {
+                    TokenType::Comma => {
+                        self.eval(&op.left)?;
+                        self.eval(&op.right)
+                    }
+                    TokenType::And => Ok(Value::Integer(
+                        (self.eval(&op.left)?.is_true() && self.eval(&op.right)?.is_true()) as i32,
+                    )),
+                    TokenType::Or => Ok(Value::Integer(
+                        (self.eval(&op.left)?.is_true() || self.eval(&op.right)?.is_true()) as i32,
+                    )),
+                    _ => {
+                        let left = self.eval(&op.left)?;
+                        let right = self.eval(&op.right)?;
+                        binary(operator, left, right, op.right.span)
+                    }
+                }
+            }
+            Expression::TernaryOperation(op) => {
+                if self.eval(&op.condition)?.is_true() {
+                    self.eval(&op.left)
+                } else {
+                    self.eval(&op.right)
+                }
+            }
+            _ => Err(Diagnostic::new(ParseError::NotConstant, span)),
+        }
+    }
+
+    fn unary(&self, op: &UnaryOperation, span: Span) -> Result<Value, Diagnostic> {
+        match op.operator.token_type {
+            TokenType::Sizeof => self.size_of(&op.operand),
+            // the values of tags are handed out as the compiler meets them,
+            // only that of no tag is known beforehand
+            TokenType::Tagof => match &op.operand.node {
+                Expression::Tag(tag) if tag == "_" => Ok(Value::Integer(0)),
+                _ => Err(Diagnostic::new(ParseError::NotConstant, span)),
+            },
+            // `defined` asks whether a name is known, as in `#if defined X`
+            TokenType::Defined => match &op.operand.node {
+                Expression::Symbol(name) => {
+                    Ok(Value::Integer(self.symbols.contains_key(name) as i32))
+                }
+                _ => Err(Diagnostic::new(ParseError::NotConstant, op.operand.span)),
+            },
+            TokenType::Minus => Ok(match self.eval(&op.operand)? {
+                Value::Integer(value) => Value::Integer(value.wrapping_neg()),
+                Value::Float(value) => Value::Float(-value),
+            }),
+            TokenType::Bang => Ok(Value::Integer(!self.eval(&op.operand)?.is_true() as i32)),
+            TokenType::Tilde => Ok(Value::Integer(!self.eval(&op.operand)?.cell())),
+            // the number of cells taken by that many packed characters
+            TokenType::Char => {
+                let count = i64::from(self.eval(&op.operand)?.cell());
+                Ok(Value::Integer(self.packed_cells(count)))
+            }
+            _ => Err(Diagnostic::new(ParseError::NotConstant, span)),
+        }
+    }
+
+    // size_of returns the size of an array, or of one of its dimensions when
+    // indexed as in `sizeof a[0]` or selected as in `sizeof a[]`. A scalar
+    // has a size of one.
+    fn size_of(&self, operand: &Spanned<Expression>) -> Result<Value, Diagnostic> {
+        let mut expr = operand;
+        let mut depth = 0;
+        let name = loop {
+            match &expr.node {
+                Expression::Index(index) => {
+                    expr = &index.array;
+                    depth += 1;
+                }
+                Expression::Selector(array) => {
+                    expr = array;
+                    depth += 1;
+                }
+                Expression::Symbol(name) => break name,
+                _ => return Err(Diagnostic::new(ParseError::NotConstant, operand.span)),
+            }
+        };
+
+        match self.symbols.get(name) {
+            Some(Symbol::Variable(dimensions)) => match dimensions.get(depth) {
+                Some(size) => Ok(Value::Integer(*size)),
+                None if depth == 0 => Ok(Value::Integer(1)),
+                None => Err(Diagnostic::new(ParseError::NotConstant, operand.span)),
+            },
+            Some(_) if depth == 0 => Ok(Value::Integer(1)),
+            Some(_) => Err(Diagnostic::new(ParseError::NotConstant, operand.span)),
+            None => Err(Diagnostic::new(
+                ParseError::UndefinedSymbol { name: name.clone() },
+                expr.span,
+            )),
+        }
+    }
+
+    // dimensions returns the size of each dimension of a variable. A size
+    // left out is counted from the initialiser, or is zero without one.
+    fn dimensions(&self, variable: &Variable, diagnostics: &mut Vec<Diagnostic>) -> Vec<i32> {
+        let initialiser = variable.initialiser.as_ref().map(|i| &i.node);
+        variable
+            .dimensions
+            .iter()
+            .enumerate()
+            .map(|(depth, dimension)| match dimension {
+                Some(size) => self.eval_array_size(size).unwrap_or_else(|diagnostic| {
+                    diagnostics.push(diagnostic);
+                    0
+                }),
+                None => initialiser.map_or(0, |expr| self.initialised_size(expr, depth)),
+            })
+            .collect()
+    }
+
+    // initialised_size returns the size of a dimension of an array counted from
+    // its initialiser, the largest of the sub-arrays for the inner dimensions. A
+    // string has room for its terminator, a packed one in as many cells as its
+    // characters take.
+    fn initialised_size(&self, expr: &Expression, depth: usize) -> i32 {
+        match expr {
+            Expression::Literal(Literal::Array(array)) if depth == 0 => array.elements.len() as i32,
+            Expression::Literal(Literal::Array(array)) => array
+                .elements
+                .iter()
+                .map(|element| self.initialised_size(&element.node, depth - 1))
+                .max()
+                .unwrap_or(0),
+            Expression::Literal(Literal::String(string)) if depth == 0 => {
+                let length = string.value().chars().count() as i64 + 1;
+                if string.packed {
+                    self.packed_cells(length)
+                } else {
+                    length as i32
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    // packed_cells returns the number of cells taken by that many packed
+    // characters.
+    fn packed_cells(&self, count: i64) -> i32 {
+        let cell_bits = i64::from(self.cell_bits);
+        ((count * CHAR_BITS + cell_bits - 1) / cell_bits) as i32
+    }
+
+    // declare_enum declares the members of an enum and the enum itself. Each
+    // member steps the value by the enum's increment, or by its size if it
+    // has one, and the enum's value is where the last member leaves it.
+    fn declare_enum(&mut self, e: &Enum, diagnostics: &mut Vec<Diagnostic>) {
+        let mut report = |result: Result<Value, Diagnostic>| {
+            result.map(Value::cell).unwrap_or_else(|diagnostic| {
+                diagnostics.push(diagnostic);
+                0
+            })
+        };
+
+        let (operator, step, span) = match &e.increment {
+            Some(increment) => (
+                compound_operator(increment.operator.token_type),
+                report(self.eval(&increment.amount)),
+                increment.amount.span,
+            ),
+            None => (TokenType::Plus, 1, Span::default()),
+        };
+
+        let mut value = 0;
+        for member in &e.members {
+            if let Some(explicit) = &member.node.value {
+                value = report(self.eval(explicit));
+            }
+            let size = match &member.node.size {
+                Some(size) => report(self.eval_array_size(size).map(Value::Integer)),
+                None => step,
+            };
+            self.define(&member.node.name, Value::Integer(value));
+            let span = member.node.size.as_ref().map_or(span, |size| size.span);
+            value = report(binary(
+                operator,
+                Value::Integer(value),
+                Value::Integer(size),
+                span,
+            ));
+        }
+
+        if let Some(name) = &e.name {
+            self.define(name, Value::Integer(value));
+        }
+    }
+}
+
+// binary applies a binary operator to two constants, an error is reported at
+// the span of the right operand.
+fn binary(operator: TokenType, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+    let float = matches!(left, Value::Float(_)) || matches!(right, Value::Float(_));
+    if float && is_float_operator(operator) {
+        return float_binary(operator, left.float(), right.float(), span);
+    }
+
+    let (l, r) = (left.cell(), right.cell());
+    let value = match operator {
+        TokenType::Plus => l.wrapping_add(r),
+        TokenType::Minus => l.wrapping_sub(r),
+        TokenType::Asterisk => l.wrapping_mul(r),
+        TokenType::Slash | TokenType::Percent if r == 0 => {
+            return Err(Diagnostic::new(ParseError::DivisionByZero, span));
+        }
+        TokenType::Slash => {
+            let quotient = l.wrapping_div(r);
+            if l.wrapping_rem(r) != 0 && (l < 0) != (r < 0) {
+                quotient - 1
+            } else {
+                quotient
+            }
+        }
+        TokenType::Percent => {
+            let remainder = l.wrapping_rem(r);
+            if remainder != 0 && (remainder < 0) != (r < 0) {
+                remainder + r
+            } else {
+                remainder
+            }
+        }
+        TokenType::BitAnd => l & r,
+        TokenType::BitOr => l | r,
+        TokenType::BitXor => l ^ r,
+        TokenType::BitLeft => l.wrapping_shl(r as u32),
+        TokenType::BitRight => l.wrapping_shr(r as u32),
+        TokenType::BitRightLogical => (l as u32).wrapping_shr(r as u32) as i32,
+        TokenType::Equal => (l == r) as i32,
+        TokenType::NotEqual => (l != r) as i32,
+        TokenType::LowerThan => (l < r) as i32,
+        TokenType::LowerThanEqual => (l <= r) as i32,
+        TokenType::GreaterThan => (l > r) as i32,
+        TokenType::GreaterThanEqual => (l >= r) as i32,
+        _ => return Err(Diagnostic::new(ParseError::NotConstant, span)),
+    };
+    Ok(Value::Integer(value))
+}
+
+// float_binary applies an operator of `float.inc`. Dividing by zero gives an
+// infinity as it does when the script runs, and there is no `%`.
+fn float_binary(operator: TokenType, l: f32, r: f32, span: Span) -> Result<Value, Diagnostic> {
+    let value = match operator {
+        TokenType::Plus => l + r,
+        TokenType::Minus => l - r,
+        TokenType::Asterisk => l * r,
+        TokenType::Slash => l / r,
+        TokenType::Equal => return Ok(Value::Integer((l == r) as i32)),
+        TokenType::NotEqual => return Ok(Value::Integer((l != r) as i32)),
+        TokenType::LowerThan => return Ok(Value::Integer((l < r) as i32)),
+        TokenType::LowerThanEqual => return Ok(Value::Integer((l <= r) as i32)),
+        TokenType::GreaterThan => return Ok(Value::Integer((l > r) as i32)),
+        TokenType::GreaterThanEqual => return Ok(Value::Integer((l >= r) as i32)),
+        _ => return Err(Diagnostic::new(ParseError::NotConstant, span)),
+    };
+    Ok(Value::Float(value))
+}
+
+fn is_float_operator(operator: TokenType) -> bool {
+    matches!(
+        operator,
+        TokenType::Plus
+            | TokenType::Minus
+            | TokenType::Asterisk
+            | TokenType::Slash
+            | TokenType::Percent
+            | TokenType::Equal
+            | TokenType::NotEqual
+            | TokenType::LowerThan
+            | TokenType::LowerThanEqual
+            | TokenType::GreaterThan
+            | TokenType::GreaterThanEqual
+    )
+}
@@ -0,0 +1,275 @@
+#[cfg(test)]
+use crate::ast::Statement;
+#[cfg(test)]
+use crate::const_eval::{Constants, InvalidCellBits, Value};
+#[cfg(test)]
+use crate::error::Diagnostic;
+#[cfg(test)]
+use crate::parse::{parse_expression_str, parse_file, parse_statement_str};
+
+#[cfg(test)]
+fn eval(constants: &Constants, source: &str) -> Result<Value, Diagnostic> {
+    let parsed = parse_expression_str(source);
+    assert!(parsed.is_ok(), "{}: {:?}", source, parsed.diagnostics);
+    constants.eval(&parsed.node)
+}
+
+#[test]
+fn test_eval_integers() {
+    let mut constants = Constants::new();
+    constants.define("MAX_PLAYERS", Value::Integer(50));
+
+    let cases = [
+        ("MAX_PLAYERS * 2 + 1", 101),
+        // cells wrap at 32 bits
+        ("cellmax + 1", i32::MIN),
+        ("cellmin / -1", i32::MIN),
+        ("65536 * 65536", 0),
+        // division rounds towards negative infinity
+        ("7 / 2", 3),
+        ("-7 / 2", -4),
+        ("-7 % 2", 1),
+        ("7 % -2", -1),
+        ("-8 >> 1", -4),
+        ("-1 >>> 28", 15),
+        ("1 << 33", 2),
+        ("~0 & 240 | 1 ^ 3", 242),
+        // bitwise operators bind tighter than comparisons, unlike C
+        ("4 & 1 == 0", 1),
+        ("8 ^ 8 != 0", 0),
+        ("1 | 2 > 2", 1),
+        ("cellbits / charbits", 4),
+        ("5 char", 2),
+        ("'a' + true", 98),
+        ("3 > 2 == 1", 1),
+        ("!5 || 0", 0),
+        // only the branch taken is evaluated
+        ("true ? 3 : 1 / 0", 3),
+        ("0 && 1 / 0", 0),
+        ("\n__line", 2),
+        ("sizeof cellbits", 1),
+        ("tagof(_:)", 0),
+    ];
+    for (source, expected) in cases.iter() {
+        assert_eq!(
+            eval(&constants, source),
+            Ok(Value::Integer(*expected)),
+            "{}",
+            source
+        );
+    }
+}
+
+// set_cell_bits changes the predefined constants and the size of packed
+// characters along with the size of a cell
+#[test]
+fn test_eval_cell_bits() {
+    let root = parse_file("new p[] = !\"abcde\";");
+    assert!(root.is_ok(), "{:?}", root.diagnostics);
+    let mut constants = Constants::new();
+    assert_eq!(constants.set_cell_bits(16), Ok(()));
+    assert!(constants.declare_file(&root.node).is_empty());
+
+    let cases = [
+        ("cellbits", 16),
+        ("cellmax", 32767),
+        ("cellmin", -32768),
+        ("ucharmax", 255),
+        ("5 char", 3),
+        ("sizeof p", 3),
+    ];
+    for (source, expected) in cases.iter() {
+        assert_eq!(
+            eval(&constants, source),
+            Ok(Value::Integer(*expected)),
+            "{}",
+            source
+        );
+    }
+
+    assert_eq!(constants.set_cell_bits(64), Ok(()));
+    assert_eq!(constants.get("cellbits"), Some(Value::Integer(64)));
+    assert_eq!(constants.get("cellmax"), Some(Value::Integer(i32::MAX)));
+    assert_eq!(eval(&constants, "9 char"), Ok(Value::Integer(2)));
+
+    // sizes pawncc can not be built with are refused and change nothing
+    for bits in [0, 7, 8, 24, 128].iter() {
+        assert_eq!(constants.set_cell_bits(*bits), Err(InvalidCellBits(*bits)));
+    }
+    assert_eq!(constants.get("cellbits"), Some(Value::Integer(64)));
+    assert_eq!(eval(&constants, "9 char"), Ok(Value::Integer(2)));
+    assert_eq!(
+        InvalidCellBits(8).to_string(),
+        "a cell can not be 8 bits, only 16, 32 or 64"
+    );
+}
+
+#[test]
+fn test_eval_floats() {
+    let constants = Constants::new();
+
+    let cases = [
+        ("1.5 * 2", Value::Float(3.0)),
+        ("1 - 0.5", Value::Float(0.5)),
+        ("-(1.0 / 4)", Value::Float(-0.25)),
+        ("1.0 / 0", Value::Float(f32::INFINITY)),
+        ("2.0 > 1", Value::Integer(1)),
+        ("!0.0", Value::Integer(1)),
+        // a tag override keeps the bits of the cell
+        ("_:1.0", Value::Integer(0x3F80_0000)),
+        ("Float:1065353216 + 1", Value::Float(2.0)),
+        ("1.0 & -16777216", Value::Integer(0x3F00_0000)),
+    ];
+    for (source, expected) in cases.iter() {
+        assert_eq!(eval(&constants, source), Ok(*expected), "{}", source);
+    }
+    assert_eq!(Value::Float(1.0).cell(), 0x3F80_0000);
+}
+
+#[test]
+fn test_eval_errors() {
+    let root = parse_file("new x;\nf() {}");
+    let mut constants = Constants::new();
+    assert!(constants.declare_file(&root.node).is_empty());
+
+    let cases = [
+        ("1 / 0", 904, 5),
+        ("10 % (2 - 2)", 904, 6),
+        ("missing + 1", 17, 1),
+        ("x * 2", 8, 1),
+        ("f()", 8, 1),
+        ("\"abc\"", 8, 1),
+        ("1.0 % 2", 8, 7),
+        ("sizeof x[0]", 8, 8),
+        ("sizeof x[]", 8, 8),
+        ("tagof(Float:)", 8, 1),
+        ("defined 1", 8, 9),
+    ];
+    for (source, code, column) in cases.iter() {
+        let diagnostic = eval(&constants, source).unwrap_err();
+        assert_eq!(
+            (diagnostic.code(), diagnostic.span.column_start),
+            (*code, *column),
+            "{}",
+            source
+        );
+    }
+    assert_eq!(
+        eval(&constants, "1 / 0").unwrap_err().to_string(),
+        "1:5: error 904: division by zero"
+    );
+}
+
+#[test]
+fn test_eval_declarations() {
+    let root = parse_file(
+        "const SIZE = 4;\n\
+         const Float:SCALE = 1.5 * SIZE;\n\
+         enum E { E_X, E_Y[SIZE], E_Z }\n\
+         enum (<<= 1) { F_A = 1, F_B, F_C }\n\
+         enum G (*= 2) { G_A = 1, G_B, G_C }\n\
+         new a[E];\n\
+         new s[] = \"abc\", p[] = !\"abcde\";\n\
+         new m[][] = {{1, 2}, {3, 4, 5}};\n\
+         new b[SIZE - 4];\n\
+         const BAD = 1 / 0;\n\
+         new c[BAD + 1];\n\
+         new t[2][3][4];",
+    );
+    assert!(root.is_ok(), "{:?}", root.diagnostics);
+    let mut constants = Constants::new();
+    let diagnostics = constants.declare_file(&root.node);
+
+    let errors: Vec<_> = diagnostics
+        .iter()
+        .map(|d| (d.code(), d.span.line_start))
+        .collect();
+    // a constant in error is zero from then on
+    assert_eq!(errors, vec![(9, 9), (904, 10)]);
+
+    let values = [
+        ("SIZE", 4),
+        ("E_X", 0),
+        ("E_Y", 1),
+        ("E_Z", 5),
+        ("E", 6),
+        ("F_A", 1),
+        ("F_B", 2),
+        ("F_C", 4),
+        ("G_C", 4),
+        ("G", 8),
+        ("BAD", 0),
+    ];
+    for (name, expected) in values.iter() {
+        assert_eq!(
+            constants.get(name),
+            Some(Value::Integer(*expected)),
+            "{}",
+            name
+        );
+    }
+    assert_eq!(constants.get("SCALE"), Some(Value::Float(6.0)));
+
+    let sizes = [
+        ("sizeof a", 6),
+        ("sizeof s", 4),
+        ("sizeof p", 2),
+        ("sizeof m", 2),
+        ("sizeof(m[0])", 3),
+        ("sizeof m[]", 3),
+        ("sizeof(m[])", 3),
+        ("sizeof t[][]", 4),
+        ("sizeof((t[1])[])", 4),
+        ("sizeof c", 1),
+    ];
+    for (source, expected) in sizes.iter() {
+        assert_eq!(
+            eval(&constants, source),
+            Ok(Value::Integer(*expected)),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn test_eval_case_labels_and_conditions() {
+    let root = parse_file("enum { A = 2, B, C }");
+    let mut constants = Constants::new();
+    assert!(constants.declare_file(&root.node).is_empty());
+
+    let statement = parse_statement_str("switch (x) { case A, C: {} case 10..cellbits: {} }");
+    let switch = match statement.node.node {
+        Statement::Switch(switch) => switch,
+        s => panic!("expected a switch, found {:?}", s),
+    };
+    let labels: Vec<_> = switch
+        .cases
+        .iter()
+        .flat_map(|case| case.node.labels.iter())
+        .map(|label| constants.eval_case_label(label))
+        .collect();
+    assert_eq!(labels, vec![Ok((2, 2)), Ok((4, 4)), Ok((10, 32))]);
+
+    // the condition of `#if cellbits == 32 && debug`
+    let condition = parse_expression_str("cellbits == 32 && debug");
+    assert_eq!(constants.eval_condition(&condition.node), Ok(true));
+    constants.define("debug", Value::Integer(0));
+    assert_eq!(constants.eval_condition(&condition.node), Ok(false));
+
+    let cases = [
+        ("defined A", true),
+        ("defined(cellbits) && !defined MISSING", true),
+        ("defined MISSING", false),
+    ];
+    for (source, expected) in cases.iter() {
+        let condition = parse_expression_str(source);
+        assert!(condition.is_ok(), "{}: {:?}", source, condition.diagnostics);
+        assert_eq!(
+            constants.eval_condition(&condition.node),
+            Ok(*expected),
+            "{}",
+            source
+        );
+    }
+}
@@ -0,0 +1,824 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::ast::Expression;
+use crate::ast::Node;
+use crate::ast::Parser;
+use crate::error::Diagnostic;
+use crate::lexer::Lexer;
+use crate::token::LineIndex;
+use crate::token::Token;
+use crate::token::TokenType;
+
+/// SyntaxKind is the kind of an element in the concrete syntax tree. Tokens
+/// keep the type they were lexed with, whitespace between tokens is
+/// `Whitespace` and comments are `Token(TokenType::Comment)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    Token(TokenType),
+    Whitespace,
+
+    // -
+    // Declarations
+    // -
+    Root,
+    Error,
+    Directive,
+    Function,
+    ParameterList,
+    Parameter,
+    Tag,
+    StateList,
+    Declaration,
+    Variable,
+    Dimension,
+    Enum,
+    EnumMember,
+
+    // -
+    // Statements
+    // -
+    Block,
+    ExpressionStatement,
+    If,
+    While,
+    DoWhile,
+    For,
+    Switch,
+    Case,
+    StateTransition,
+    Label,
+    Goto,
+    Return,
+    Break,
+    Continue,
+    Empty,
+
+    // -
+    // Expressions
+    // -
+    BinaryOperation,
+    UnaryOperation,
+    TernaryOperation,
+    TagOverride,
+    Call,
+    ArgumentList,
+    Argument,
+    Index,
+    Selector,
+    Name,
+    Literal,
+    Array,
+    Paren,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Whitespace | SyntaxKind::Token(TokenType::Comment)
+        )
+    }
+}
+
+// Event is recorded by the parser as it goes, the tree is built from these
+// afterwards. `token` is the parser's position when the event happened,
+// every token before it has been consumed.
+#[derive(Debug)]
+pub(crate) enum Event {
+    Start {
+        kind: SyntaxKind,
+        token: usize,
+    },
+    // StartAt starts a node that begins at an earlier checkpoint and takes
+    // everything built since then as its first children, which is how the
+    // left side of a binary operation ends up inside it.
+    StartAt {
+        kind: SyntaxKind,
+        checkpoint: usize,
+        token: usize,
+    },
+    Checkpoint {
+        token: usize,
+    },
+    Finish {
+        token: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Checkpoint {
+    pub(crate) index: usize,
+    // the number of nodes that were open, used to close any left open by an
+    // error before recovering.
+    pub(crate) open: usize,
+}
+
+/// GreenToken is a token in the immutable green tree, it holds the exact
+/// source text including trivia.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, text: &str) -> GreenToken {
+        GreenToken {
+            kind,
+            text: String::from(text),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// GreenNode is an immutable node of the green tree. It knows its width but
+/// not its position, so identical subtrees can be shared.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    width: usize,
+    children: Vec<GreenElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Arc<GreenNode>),
+    Token(Arc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind,
+            GreenElement::Token(token) => token.kind,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.width,
+            GreenElement::Token(token) => token.text.len(),
+        }
+    }
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> GreenNode {
+        GreenNode {
+            kind,
+            width: children.iter().map(GreenElement::width).sum(),
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+// a node is dropped by taking apart the nodes only it holds in a loop, so
+// that dropping a deep tree, such as that of a long chain of operators, does
+// not recurse.
+impl Drop for GreenNode {
+    fn drop(&mut self) {
+        let mut elements = mem::take(&mut self.children);
+        while let Some(element) = elements.pop() {
+            if let GreenElement::Node(node) = element {
+                if let Ok(mut node) = Arc::try_unwrap(node) {
+                    elements.append(&mut node.children);
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut stack = vec![self.children.iter()];
+        while let Some(children) = stack.last_mut() {
+            match children.next() {
+                Some(GreenElement::Node(node)) => stack.push(node.children.iter()),
+                Some(GreenElement::Token(token)) => f.write_str(&token.text)?,
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// SyntaxNode is a cursor into the green tree. It is cheap to clone and knows
+/// its parent and absolute position, neither of which the green tree stores.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Arc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    index: usize,
+    offset: usize,
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Arc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.0.green
+    }
+
+    /// text_range returns the byte range of the node in the source.
+    pub fn text_range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.width
+    }
+
+    pub fn text(&self) -> String {
+        self.0.green.to_string()
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// ancestors returns this node followed by each of its parents.
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        std::iter::successors(Some(self.clone()), SyntaxNode::parent)
+    }
+
+    pub fn children_with_tokens(&self) -> SyntaxElementChildren {
+        SyntaxElementChildren {
+            parent: self.clone(),
+            index: 0,
+            offset: self.0.offset,
+        }
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.children_with_tokens()
+            .filter_map(SyntaxElement::into_node)
+    }
+
+    /// tokens returns the tokens directly inside this node, not those of its
+    /// child nodes.
+    pub fn tokens(&self) -> impl Iterator<Item = SyntaxToken> {
+        self.children_with_tokens()
+            .filter_map(SyntaxElement::into_token)
+    }
+
+    /// descendants returns this node and every node below it in source order.
+    pub fn descendants(&self) -> impl Iterator<Item = SyntaxNode> {
+        let mut stack = vec![self.clone()];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            let children: Vec<SyntaxNode> = node.children().collect();
+            stack.extend(children.into_iter().rev());
+            Some(node)
+        })
+    }
+
+    pub fn next_sibling(&self) -> Option<SyntaxNode> {
+        self.parent()?
+            .children_with_tokens()
+            .skip(self.0.index + 1)
+            .find_map(SyntaxElement::into_node)
+    }
+
+    pub fn prev_sibling(&self) -> Option<SyntaxNode> {
+        self.parent()?
+            .children_with_tokens()
+            .take(self.0.index)
+            .filter_map(SyntaxElement::into_node)
+            .last()
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &SyntaxNode) -> bool {
+        Arc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}@{:?}", self.kind(), self.text_range())
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.green.fmt(f)
+    }
+}
+
+/// SyntaxToken is a cursor to a token in the green tree.
+#[derive(Clone)]
+pub struct SyntaxToken {
+    parent: SyntaxNode,
+    green: Arc<GreenToken>,
+    offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.green.text
+    }
+
+    pub fn green(&self) -> &Arc<GreenToken> {
+        &self.green
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.text.len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+}
+
+impl PartialEq for SyntaxToken {
+    fn eq(&self, other: &SyntaxToken) -> bool {
+        Arc::ptr_eq(&self.green, &other.green) && self.offset == other.offset
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?}@{:?} {:?}",
+            self.kind(),
+            self.text_range(),
+            self.text()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn text_range(&self) -> Range<usize> {
+        match self {
+            SyntaxElement::Node(node) => node.text_range(),
+            SyntaxElement::Token(token) => token.text_range(),
+        }
+    }
+
+    pub fn into_node(self) -> Option<SyntaxNode> {
+        match self {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+
+    pub fn into_token(self) -> Option<SyntaxToken> {
+        match self {
+            SyntaxElement::Node(_) => None,
+            SyntaxElement::Token(token) => Some(token),
+        }
+    }
+}
+
+/// SyntaxElementChildren iterates over the children of a node.
+pub struct SyntaxElementChildren {
+    parent: SyntaxNode,
+    index: usize,
+    offset: usize,
+}
+
+impl Iterator for SyntaxElementChildren {
+    type Item = SyntaxElement;
+
+    fn next(&mut self) -> Option<SyntaxElement> {
+        let green = self.parent.0.green.children.get(self.index)?;
+        let element = match green {
+            GreenElement::Node(node) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                green: node.clone(),
+                parent: Some(self.parent.clone()),
+                index: self.index,
+                offset: self.offset,
+            }))),
+            GreenElement::Token(token) => SyntaxElement::Token(SyntaxToken {
+                parent: self.parent.clone(),
+                green: token.clone(),
+                offset: self.offset,
+            }),
+        };
+        self.index += 1;
+        self.offset += green.width();
+        Some(element)
+    }
+}
+
+/// Parse is the result of parsing a source file into both trees.
+pub struct Parse {
+    green: Arc<GreenNode>,
+    pub ast: Node,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Parse {
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.green
+    }
+
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green.clone())
+    }
+
+    pub fn source_file(&self) -> SourceFile {
+        SourceFile::cast(self.syntax()).unwrap()
+    }
+}
+
+/// parse parses `source` into a lossless syntax tree, the text of which is
+/// always exactly `source`, along with the AST and any diagnostics.
+pub fn parse(source: &str) -> Parse {
+    let tokens = Lexer::new(source).lex();
+    let mut parser = Parser::new(tokens.clone());
+    let diagnostics = parser.parse().err().unwrap_or_default();
+    let green = Builder::new(source, &tokens).build(parser.take_events());
+
+    Parse {
+        green,
+        ast: std::mem::replace(&mut parser.root, Node::new(Expression::GlobalScope)),
+        diagnostics,
+    }
+}
+
+// Builder turns the parser's events into a green tree. The parser only sees
+// tokens without comments so the builder fills in the comments and the
+// whitespace between tokens from the source as it goes.
+struct Builder<'a> {
+    source: &'a str,
+    tokens: Vec<(TokenType, Range<usize>)>,
+    // the index in `tokens` of each token the parser sees
+    significant: Vec<usize>,
+    // the next token in `tokens` to add and the byte offset it is added at
+    next: usize,
+    offset: usize,
+    stack: Vec<(SyntaxKind, Vec<GreenElement>)>,
+    checkpoints: Vec<(usize, usize)>,
+    cache: HashMap<(SyntaxKind, &'a str), Arc<GreenToken>>,
+}
+
+impl<'a> Builder<'a> {
+    fn new(source: &'a str, tokens: &[Token]) -> Builder<'a> {
+        let index = LineIndex::new(source);
+        let tokens: Vec<(TokenType, Range<usize>)> = tokens
+            .iter()
+            .map(|t| (t.token_type, index.range(source, t.span())))
+            .collect();
+        let significant = (0..tokens.len())
+            .filter(|&i| tokens[i].0 != TokenType::Comment)
+            .collect();
+
+        Builder {
+            source,
+            tokens,
+            significant,
+            next: 0,
+            offset: 0,
+            stack: vec![(SyntaxKind::Root, vec![])],
+            checkpoints: vec![],
+            cache: HashMap::new(),
+        }
+    }
+
+    fn build(mut self, events: Vec<Event>) -> Arc<GreenNode> {
+        for event in events {
+            match event {
+                Event::Start { kind, token } => {
+                    self.add_before(token);
+                    self.stack.push((kind, vec![]));
+                }
+                Event::StartAt {
+                    kind,
+                    checkpoint,
+                    token,
+                } => {
+                    self.add_through(token);
+                    let (depth, len) = self.checkpoints[checkpoint];
+                    assert_eq!(depth, self.stack.len(), "checkpoint in another node");
+                    let children = self.stack.last_mut().unwrap().1.split_off(len);
+                    self.stack.push((kind, children));
+                }
+                Event::Checkpoint { token } => {
+                    self.add_before(token);
+                    let len = self.stack.last().unwrap().1.len();
+                    self.checkpoints.push((self.stack.len(), len));
+                }
+                Event::Finish { token } => {
+                    self.add_through(token);
+                    let (kind, children) = self.stack.pop().unwrap();
+                    self.push(GreenElement::Node(Arc::new(GreenNode::new(kind, children))));
+                }
+            }
+        }
+
+        self.add_before(self.significant.len());
+        assert_eq!(self.stack.len(), 1, "unfinished syntax node");
+        let (kind, children) = self.stack.pop().unwrap();
+        Arc::new(GreenNode::new(kind, children))
+    }
+
+    fn push(&mut self, element: GreenElement) {
+        self.stack.last_mut().unwrap().1.push(element);
+    }
+
+    // add_through adds every token the parser consumed before `token` but
+    // not the trivia after the last one, which belongs to whatever is next.
+    fn add_through(&mut self, token: usize) {
+        if token > 0 {
+            self.add_tokens(self.significant[token - 1] + 1);
+        }
+    }
+
+    // add_before adds everything up to the start of `token`, trivia included.
+    fn add_before(&mut self, token: usize) {
+        let (index, offset) = match self.significant.get(token) {
+            Some(&index) => (index, self.tokens[index].1.start),
+            None => (self.tokens.len(), self.source.len()),
+        };
+        self.add_tokens(index);
+        self.add_whitespace(offset);
+    }
+
+    fn add_tokens(&mut self, until: usize) {
+        while self.next < until {
+            let (token_type, range) = self.tokens[self.next].clone();
+            self.add_whitespace(range.start);
+            self.add_token(SyntaxKind::Token(token_type), range.end);
+            self.next += 1;
+        }
+    }
+
+    fn add_whitespace(&mut self, until: usize) {
+        if until > self.offset {
+            self.add_token(SyntaxKind::Whitespace, until);
+        }
+    }
+
+    fn add_token(&mut self, kind: SyntaxKind, until: usize) {
+        let until = until.max(self.offset);
+        let text = &self.source[self.offset..until];
+        let token = self
+            .cache
+            .entry((kind, text))
+            .or_insert_with(|| Arc::new(GreenToken::new(kind, text)))
+            .clone();
+        self.push(GreenElement::Token(token));
+        self.offset = until;
+    }
+}
+
+/// AstNode is a typed view of a syntax node of a particular kind.
+pub trait AstNode: Sized {
+    fn cast(node: SyntaxNode) -> Option<Self>;
+    fn syntax(&self) -> &SyntaxNode;
+}
+
+macro_rules! ast_node {
+    ($(#[$meta:meta])* $name:ident, $kind:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name(SyntaxNode);
+
+        impl AstNode for $name {
+            fn cast(node: SyntaxNode) -> Option<$name> {
+                if node.kind() == SyntaxKind::$kind {
+                    Some($name(node))
+                } else {
+                    None
+                }
+            }
+
+            fn syntax(&self) -> &SyntaxNode {
+                &self.0
+            }
+        }
+    };
+}
+
+fn child<N: AstNode>(node: &SyntaxNode) -> Option<N> {
+    node.children().find_map(N::cast)
+}
+
+fn children<N: AstNode>(node: &SyntaxNode) -> impl Iterator<Item = N> {
+    node.children().filter_map(N::cast)
+}
+
+fn token(node: &SyntaxNode, token_type: TokenType) -> Option<SyntaxToken> {
+    node.tokens()
+        .find(|t| t.kind() == SyntaxKind::Token(token_type))
+}
+
+ast_node!(
+    /// SourceFile is the root of a syntax tree.
+    SourceFile,
+    Root
+);
+
+impl SourceFile {
+    pub fn functions(&self) -> impl Iterator<Item = FunctionDef> {
+        children(&self.0)
+    }
+
+    pub fn declarations(&self) -> impl Iterator<Item = Declaration> {
+        children(&self.0)
+    }
+
+    pub fn enums(&self) -> impl Iterator<Item = EnumDef> {
+        children(&self.0)
+    }
+}
+
+ast_node!(FunctionDef, Function);
+
+impl FunctionDef {
+    /// name returns the function's name, an operator function has none.
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, TokenType::Symbol)
+    }
+
+    pub fn operator(&self) -> Option<SyntaxToken> {
+        let operator = token(&self.0, TokenType::Operator)?;
+        self.0
+            .tokens()
+            .filter(|t| !t.kind().is_trivia())
+            .find(|t| t.text_range().start > operator.text_range().start)
+    }
+
+    pub fn tag(&self) -> Option<Tag> {
+        child(&self.0)
+    }
+
+    pub fn params(&self) -> impl Iterator<Item = Param> {
+        child::<ParamList>(&self.0)
+            .into_iter()
+            .flat_map(|list| list.params())
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+
+    pub fn is_native(&self) -> bool {
+        token(&self.0, TokenType::Native).is_some()
+    }
+
+    pub fn is_forward(&self) -> bool {
+        token(&self.0, TokenType::Forward).is_some()
+    }
+}
+
+ast_node!(ParamList, ParameterList);
+
+impl ParamList {
+    pub fn params(&self) -> impl Iterator<Item = Param> {
+        children(&self.0)
+    }
+}
+
+ast_node!(Param, Parameter);
+
+impl Param {
+    /// name returns the parameter's name, a variadic parameter has none.
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, TokenType::Symbol)
+    }
+
+    pub fn tag(&self) -> Option<Tag> {
+        child(&self.0)
+    }
+
+    pub fn is_const(&self) -> bool {
+        token(&self.0, TokenType::Const).is_some()
+    }
+
+    pub fn is_reference(&self) -> bool {
+        token(&self.0, TokenType::BitAnd).is_some()
+    }
+
+    pub fn is_variadic(&self) -> bool {
+        token(&self.0, TokenType::Elipsis).is_some()
+    }
+}
+
+ast_node!(
+    /// Tag is a `tag:` prefix or a tag list such as `{Float,_}:`.
+    Tag,
+    Tag
+);
+
+impl Tag {
+    pub fn names(&self) -> impl Iterator<Item = SyntaxToken> {
+        self.0
+            .tokens()
+            .filter(|t| t.kind() == SyntaxKind::Token(TokenType::Symbol))
+    }
+}
+
+ast_node!(Declaration, Declaration);
+
+impl Declaration {
+    pub fn variables(&self) -> impl Iterator<Item = VariableDef> {
+        children(&self.0)
+    }
+}
+
+ast_node!(VariableDef, Variable);
+
+impl VariableDef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, TokenType::Symbol)
+    }
+
+    pub fn tag(&self) -> Option<Tag> {
+        child(&self.0)
+    }
+
+    /// initialiser returns the expression after the `=`, if there is one.
+    pub fn initialiser(&self) -> Option<SyntaxNode> {
+        self.0
+            .children_with_tokens()
+            .skip_while(|e| e.kind() != SyntaxKind::Token(TokenType::Assign))
+            .find_map(SyntaxElement::into_node)
+    }
+}
+
+ast_node!(EnumDef, Enum);
+
+impl EnumDef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, TokenType::Symbol)
+    }
+
+    pub fn members(&self) -> impl Iterator<Item = EnumMemberDef> {
+        children(&self.0)
+    }
+}
+
+ast_node!(EnumMemberDef, EnumMember);
+
+impl EnumMemberDef {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        token(&self.0, TokenType::Symbol)
+    }
+}
+
+ast_node!(Block, Block);
+
+impl Block {
+    pub fn statements(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.0
+            .children()
+            .filter(|n| n.kind() != SyntaxKind::Directive)
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+use std::fs;
+#[cfg(test)]
+use std::path::Path;
+#[cfg(test)]
+use std::sync::Arc;
+
+#[cfg(test)]
+use crate::cst::{parse, AstNode, SyntaxKind};
+#[cfg(test)]
+use crate::token::TokenType;
+
+#[test]
+fn test_lossless() {
+    let sources = [
+        "",
+        "  \n",
+        "// comment only",
+        "#include <a_samp>\n\nmain()\n{\n\tprint(\"hello\"); // greet\n}\n",
+        "new a = 1, /* two */ b[2] = {1, 2};\r\n",
+        "stock Float:Add(Float:a, Float:b) return a + b;",
+        "new café = 1;\nmain() { café++; }",
+        "enum E_DATA { E_A[10], Float:E_B }\nnew data[E_DATA];",
+        "main() { if (a) { b(); } else c = d ? e : f; }",
+        "new n = sizeof a[ ] + sizeof(b[0][]);",
+    ];
+    for source in sources.iter() {
+        let parse = parse(source);
+        assert_eq!(parse.syntax().text(), *source);
+        assert_eq!(parse.syntax().text_range(), 0..source.len());
+    }
+}
+
+#[test]
+fn test_lossless_recovery() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/recovery");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|e| e != "pwn") {
+            continue;
+        }
+        let source = fs::read_to_string(&path).unwrap();
+        let parse = parse(&source);
+        assert!(!parse.diagnostics.is_empty());
+        assert_eq!(parse.syntax().text(), source, "{}", path.display());
+    }
+}
+
+#[test]
+fn test_function_params() {
+    let parse = parse("stock Float:Add(Float:a, &b, const c[] = \"x\", ...) { return a; }");
+    let function = parse.source_file().functions().next().unwrap();
+
+    assert_eq!(function.name().unwrap().text(), "Add");
+    let tag = function.tag().unwrap();
+    let tags: Vec<_> = tag.names().map(|t| t.text().to_string()).collect();
+    assert_eq!(tags, vec!["Float"]);
+
+    let params: Vec<_> = function.params().collect();
+    assert_eq!(params.len(), 4);
+    let names: Vec<_> = params
+        .iter()
+        .map(|p| p.name().map(|t| t.text().to_string()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            Some(String::from("a")),
+            Some(String::from("b")),
+            Some(String::from("c")),
+            None
+        ]
+    );
+    assert!(params[1].is_reference());
+    assert!(params[2].is_const());
+    assert!(params[3].is_variadic());
+    assert_eq!(params[2].syntax().text(), "const c[] = \"x\"");
+
+    let body = function.body().unwrap();
+    let statements: Vec<_> = body.statements().map(|s| s.kind()).collect();
+    assert_eq!(statements, vec![SyntaxKind::Return]);
+}
+
+#[test]
+fn test_declarations() {
+    let parse = parse("new a = 1 + 2, Float:b;\nenum { A, B = 5 }");
+    let file = parse.source_file();
+
+    let declaration = file.declarations().next().unwrap();
+    let variables: Vec<_> = declaration.variables().collect();
+    assert_eq!(variables.len(), 2);
+    assert_eq!(variables[0].name().unwrap().text(), "a");
+    let initialiser = variables[0].initialiser().unwrap();
+    assert_eq!(initialiser.kind(), SyntaxKind::BinaryOperation);
+    assert_eq!(initialiser.text(), "1 + 2");
+    assert_eq!(variables[1].name().unwrap().text(), "b");
+    assert!(variables[1].tag().is_some());
+    assert!(variables[1].initialiser().is_none());
+
+    let members: Vec<_> = file
+        .enums()
+        .next()
+        .unwrap()
+        .members()
+        .map(|m| m.name().unwrap().text().to_string())
+        .collect();
+    assert_eq!(members, vec!["A", "B"]);
+}
+
+#[test]
+fn test_parents_and_ranges() {
+    let source = "main() {\n    x = a * (b + c);\n}";
+    let parse = parse(source);
+
+    let b = parse
+        .syntax()
+        .descendants()
+        .find(|n| n.kind() == SyntaxKind::Name && n.text() == "b")
+        .unwrap();
+    assert_eq!(&source[b.text_range()], "b");
+
+    let kinds: Vec<_> = b.ancestors().map(|n| n.kind()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            SyntaxKind::Name,
+            SyntaxKind::BinaryOperation,
+            SyntaxKind::Paren,
+            SyntaxKind::BinaryOperation,
+            SyntaxKind::BinaryOperation,
+            SyntaxKind::ExpressionStatement,
+            SyntaxKind::Block,
+            SyntaxKind::Function,
+            SyntaxKind::Root,
+        ]
+    );
+    assert_eq!(b.next_sibling().unwrap().text(), "c");
+    assert_eq!(b.parent().unwrap().text(), "b + c");
+}
+
+#[test]
+fn test_trivia() {
+    let parse = parse("// header\nnew a; // trailing\n");
+    let root = parse.syntax();
+
+    let kinds: Vec<_> = root.children_with_tokens().map(|e| e.kind()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            SyntaxKind::Token(TokenType::Comment),
+            SyntaxKind::Declaration,
+            SyntaxKind::Whitespace,
+            SyntaxKind::Token(TokenType::Comment),
+        ]
+    );
+    let declaration = root.children().next().unwrap();
+    assert_eq!(declaration.text(), "new a;");
+}
+
+#[test]
+fn test_error_nodes() {
+    let parse = parse("new a = ;\nnew b;");
+    let kinds: Vec<_> = parse.syntax().children().map(|n| n.kind()).collect();
+    assert_eq!(kinds, vec![SyntaxKind::Error, SyntaxKind::Declaration]);
+    assert_eq!(
+        parse.syntax().children().next().unwrap().text(),
+        "new a = ;"
+    );
+    assert_eq!(parse.diagnostics.len(), 1);
+}
+
+#[test]
+fn test_shared_tokens() {
+    let parse = parse("main() { a = a; }");
+    let names: Vec<_> = parse
+        .syntax()
+        .descendants()
+        .filter(|n| n.kind() == SyntaxKind::Name)
+        .map(|n| n.tokens().next().unwrap())
+        .collect();
+    assert_eq!(names.len(), 2);
+    assert!(Arc::ptr_eq(names[0].green(), names[1].green()));
+    assert_ne!(names[0], names[1]);
+}
@@ -0,0 +1,255 @@
+use std::fmt;
+
+use crate::token::Span;
+use crate::token::TokenType;
+
+/// ParseError is the kind of error found while parsing, or while checking the
+/// tree afterwards such as in name resolution. Each kind has a stable code
+/// which matches the pawncc error number where there is one, the rest are
+/// numbered from 900 upwards. As in pawncc, codes from 200 to 299 are
+/// warnings. A `found` of `None` is the end of the file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// 001: a specific token, or one of a set of tokens, was required.
+    ExpectedToken {
+        expected: Vec<TokenType>,
+        found: Option<TokenType>,
+    },
+    /// 008: an expression whose value is needed while compiling, such as an
+    /// array size, which uses something only known when the script runs.
+    NotConstant,
+    /// 009: an array size of zero or less.
+    InvalidArraySize,
+    /// 010: something other than a declaration at the top level.
+    ExpectedDeclaration { found: Option<TokenType> },
+    /// 016: a second `default` case in the same switch.
+    MultipleDefaults,
+    /// 017: a name used where no declaration of it is in scope.
+    UndefinedSymbol { name: String },
+    /// 021: a name declared a second time in the same scope.
+    Redefinition { name: String },
+    /// 029: something other than an expression where one was required.
+    ExpectedExpression { found: Option<TokenType> },
+    /// 030: the file ended before a block was closed.
+    UnclosedBlock,
+    /// 213: a value with a tag other than the one, or one of the ones,
+    /// expected. This is a warning.
+    TagMismatch {
+        expected: Vec<String>,
+        found: String,
+    },
+    /// 901: an operator that can not be overloaded after `operator`.
+    InvalidOperator { found: Option<TokenType> },
+    /// 902: input left over after a fragment, such as a second expression
+    /// after the one being parsed.
+    TrailingInput { found: Option<TokenType> },
+    /// 903: expressions or statements nested deeper than the parser allows,
+    /// see `Options::max_depth`.
+    NestingTooDeep { limit: usize },
+    /// 904: a constant expression dividing by zero.
+    DivisionByZero,
+}
+
+impl ParseError {
+    pub fn code(&self) -> u16 {
+        match self {
+            ParseError::ExpectedToken { .. } => 1,
+            ParseError::NotConstant => 8,
+            ParseError::InvalidArraySize => 9,
+            ParseError::ExpectedDeclaration { .. } => 10,
+            ParseError::MultipleDefaults => 16,
+            ParseError::UndefinedSymbol { .. } => 17,
+            ParseError::Redefinition { .. } => 21,
+            ParseError::ExpectedExpression { .. } => 29,
+            ParseError::UnclosedBlock => 30,
+            ParseError::TagMismatch { .. } => 213,
+            ParseError::InvalidOperator { .. } => 901,
+            ParseError::TrailingInput { .. } => 902,
+            ParseError::NestingTooDeep { .. } => 903,
+            ParseError::DivisionByZero => 904,
+        }
+    }
+
+    /// is_warning reports whether this is a warning rather than an error.
+    pub fn is_warning(&self) -> bool {
+        (200..300).contains(&self.code())
+    }
+
+    /// expected returns the set of tokens that would have been accepted.
+    pub fn expected(&self) -> &[TokenType] {
+        match self {
+            ParseError::ExpectedToken { expected, .. } => expected,
+            _ => &[],
+        }
+    }
+
+    pub fn found(&self) -> Option<TokenType> {
+        match self {
+            ParseError::ExpectedToken { found, .. }
+            | ParseError::ExpectedDeclaration { found }
+            | ParseError::ExpectedExpression { found }
+            | ParseError::InvalidOperator { found }
+            | ParseError::TrailingInput { found } => *found,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::ExpectedToken { expected, found } => {
+                let expected: Vec<String> = expected.iter().map(|t| describe(*t)).collect();
+                if expected.len() == 1 {
+                    write!(f, "expected token: {}", expected[0])?;
+                } else {
+                    write!(f, "expected one of: {}", expected.join(", "))?;
+                }
+                write!(f, ", but found {}", Found(*found))
+            }
+            ParseError::NotConstant => write!(f, "must be a constant expression"),
+            ParseError::InvalidArraySize => {
+                write!(f, "invalid array size (negative, zero or out of bounds)")
+            }
+            ParseError::ExpectedDeclaration { found } => {
+                write!(
+                    f,
+                    "invalid function or declaration, found {}",
+                    Found(*found)
+                )
+            }
+            ParseError::MultipleDefaults => write!(f, "multiple defaults in \"switch\""),
+            ParseError::UndefinedSymbol { name } => write!(f, "undefined symbol \"{}\"", name),
+            ParseError::Redefinition { name } => {
+                write!(f, "symbol already defined: \"{}\"", name)
+            }
+            ParseError::ExpectedExpression { found } => {
+                write!(f, "invalid expression, found {}", Found(*found))
+            }
+            ParseError::UnclosedBlock => {
+                write!(f, "compound statement not closed at the end of file")
+            }
+            ParseError::TagMismatch { expected, found } => {
+                let expected: Vec<String> = expected.iter().map(|t| describe_tag(t)).collect();
+                write!(
+                    f,
+                    "tag mismatch: expected tag{} {}, but found {}",
+                    if expected.len() == 1 { "" } else { "s" },
+                    expected.join(" or "),
+                    describe_tag(found)
+                )
+            }
+            ParseError::InvalidOperator { found } => {
+                write!(f, "operator can not be redefined, found {}", Found(*found))
+            }
+            ParseError::TrailingInput { found } => {
+                write!(f, "expected end of input, but found {}", Found(*found))
+            }
+            ParseError::NestingTooDeep { limit } => {
+                write!(f, "nested too deeply, the limit is {} levels", limit)
+            }
+            ParseError::DivisionByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+// describe names a token the way pawncc does in its messages.
+fn describe(token_type: TokenType) -> String {
+    match token_type {
+        TokenType::Symbol => String::from("-identifier-"),
+        TokenType::Integer => String::from("-integer value-"),
+        TokenType::Float => String::from("-rational number-"),
+        TokenType::Literal => String::from("-string-"),
+        TokenType::Character => String::from("-character-"),
+        t => format!("\"{}\"", t),
+    }
+}
+
+// describe_tag names a tag the way pawncc does in its messages.
+fn describe_tag(tag: &str) -> String {
+    match tag {
+        "_" => String::from("none (\"_\")"),
+        tag => format!("\"{}\"", tag),
+    }
+}
+
+struct Found(Option<TokenType>);
+
+impl fmt::Display for Found {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(t) => f.write_str(&describe(t)),
+            None => f.write_str("end of file"),
+        }
+    }
+}
+
+/// Diagnostic is a ParseError along with where it happened, any related
+/// places in the source and notes on how to fix it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub error: ParseError,
+    pub span: Span,
+    pub secondary: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+/// Label is a secondary span with a message explaining why it is relevant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(error: ParseError, span: Span) -> Diagnostic {
+        Diagnostic {
+            error,
+            span,
+            secondary: vec![],
+            notes: vec![],
+        }
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Diagnostic {
+        self.secondary.push(Label {
+            span,
+            message: String::from(message),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(String::from(note));
+        self
+    }
+
+    pub fn code(&self) -> u16 {
+        self.error.code()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = if self.error.is_warning() {
+            "warning"
+        } else {
+            "error"
+        };
+        write!(
+            f,
+            "{}: {} {:03}: {}",
+            self.span,
+            severity,
+            self.code(),
+            self.error
+        )?;
+        for label in &self.secondary {
+            write!(f, "\n  {}: {}", label.span, label.message)?;
+        }
+        for note in &self.notes {
+            write!(f, "\n  note: {}", note)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,435 @@
+use crate::ast::Argument;
+use crate::ast::Array;
+use crate::ast::BinaryOperation;
+use crate::ast::Call;
+use crate::ast::Case;
+use crate::ast::CaseLabel;
+use crate::ast::DoWhile;
+use crate::ast::Enum;
+use crate::ast::EnumIncrement;
+use crate::ast::EnumMember;
+use crate::ast::Expression;
+use crate::ast::For;
+use crate::ast::Function;
+use crate::ast::If;
+use crate::ast::Index;
+use crate::ast::Literal;
+use crate::ast::Node;
+use crate::ast::Parameter;
+use crate::ast::Spanned;
+use crate::ast::StateTransition;
+use crate::ast::Statement;
+use crate::ast::Switch;
+use crate::ast::TagOverride;
+use crate::ast::TernaryOperation;
+use crate::ast::UnaryOperation;
+use crate::ast::Variable;
+use crate::ast::While;
+
+use std::mem;
+
+/// Fold rewrites a syntax tree by taking each node by value and returning its
+/// replacement. Like `Visitor`, every method defaults to the matching `walk_`
+/// function which folds the children and rebuilds the node, so a rewrite
+/// only overrides the nodes it changes. Spans are carried over as they are.
+pub trait Fold {
+    fn fold_node(&mut self, node: Node) -> Node {
+        walk_node(self, node)
+    }
+
+    fn fold_expression(&mut self, expr: Spanned<Expression>) -> Spanned<Expression> {
+        Spanned::new(walk_expression(self, expr.node), expr.span)
+    }
+
+    fn fold_statement(&mut self, statement: Spanned<Statement>) -> Spanned<Statement> {
+        Spanned::new(walk_statement(self, statement.node), statement.span)
+    }
+
+    fn fold_variable(&mut self, variable: Variable) -> Variable {
+        walk_variable(self, variable)
+    }
+
+    fn fold_function(&mut self, function: Function) -> Function {
+        walk_function(self, function)
+    }
+
+    fn fold_parameter(&mut self, parameter: Spanned<Parameter>) -> Spanned<Parameter> {
+        Spanned::new(walk_parameter(self, parameter.node), parameter.span)
+    }
+
+    fn fold_enum(&mut self, e: Enum) -> Enum {
+        walk_enum(self, e)
+    }
+
+    fn fold_enum_member(&mut self, member: Spanned<EnumMember>) -> Spanned<EnumMember> {
+        Spanned::new(walk_enum_member(self, member.node), member.span)
+    }
+
+    fn fold_binary_operation(&mut self, op: BinaryOperation) -> BinaryOperation {
+        walk_binary_operation(self, op)
+    }
+
+    fn fold_unary_operation(&mut self, op: UnaryOperation) -> UnaryOperation {
+        walk_unary_operation(self, op)
+    }
+
+    fn fold_ternary_operation(&mut self, op: TernaryOperation) -> TernaryOperation {
+        walk_ternary_operation(self, op)
+    }
+
+    fn fold_tag_override(&mut self, tag: TagOverride) -> TagOverride {
+        walk_tag_override(self, tag)
+    }
+
+    fn fold_call(&mut self, call: Call) -> Call {
+        walk_call(self, call)
+    }
+
+    fn fold_argument(&mut self, argument: Spanned<Argument>) -> Spanned<Argument> {
+        Spanned::new(walk_argument(self, argument.node), argument.span)
+    }
+
+    fn fold_index(&mut self, index: Index) -> Index {
+        walk_index(self, index)
+    }
+
+    fn fold_literal(&mut self, literal: Literal) -> Literal {
+        walk_literal(self, literal)
+    }
+
+    fn fold_array(&mut self, array: Array) -> Array {
+        walk_array(self, array)
+    }
+
+    /// fold_symbol is called for every symbol used in an expression, names
+    /// being declared are part of the declaring node instead.
+    fn fold_symbol(&mut self, name: String) -> String {
+        name
+    }
+
+    fn fold_if(&mut self, s: If) -> If {
+        walk_if(self, s)
+    }
+
+    fn fold_while(&mut self, s: While) -> While {
+        walk_while(self, s)
+    }
+
+    fn fold_do_while(&mut self, s: DoWhile) -> DoWhile {
+        walk_do_while(self, s)
+    }
+
+    fn fold_for(&mut self, s: For) -> For {
+        walk_for(self, s)
+    }
+
+    fn fold_switch(&mut self, s: Switch) -> Switch {
+        walk_switch(self, s)
+    }
+
+    fn fold_case(&mut self, case: Spanned<Case>) -> Spanned<Case> {
+        Spanned::new(walk_case(self, case.node), case.span)
+    }
+
+    fn fold_case_label(&mut self, label: CaseLabel) -> CaseLabel {
+        walk_case_label(self, label)
+    }
+
+    fn fold_state_transition(&mut self, s: StateTransition) -> StateTransition {
+        walk_state_transition(self, s)
+    }
+}
+
+// fold_boxed folds the expression held by a box, keeping the allocation.
+fn fold_boxed<F: Fold + ?Sized>(
+    f: &mut F,
+    mut expr: Box<Spanned<Expression>>,
+) -> Box<Spanned<Expression>> {
+    fold_in_place(f, &mut expr);
+    expr
+}
+
+// fold_in_place folds an expression where it is. The links of a chain, such
+// as a binary operation, take apart the chain below them when dropped, so
+// what they hold can not be moved out and is folded in place instead.
+fn fold_in_place<F: Fold + ?Sized>(f: &mut F, expr: &mut Spanned<Expression>) {
+    let inner = mem::replace(expr, Expression::Error.into());
+    *expr = f.fold_expression(inner);
+}
+
+// fold_dimensions folds the dimensions of a variable or parameter.
+fn fold_dimensions<F: Fold + ?Sized>(
+    f: &mut F,
+    dimensions: Vec<Option<Spanned<Expression>>>,
+) -> Vec<Option<Spanned<Expression>>> {
+    dimensions
+        .into_iter()
+        .map(|d| d.map(|d| f.fold_expression(d)))
+        .collect()
+}
+
+pub fn walk_node<F: Fold + ?Sized>(f: &mut F, node: Node) -> Node {
+    Node {
+        expr: walk_expression(f, node.expr),
+        children: node.children.into_iter().map(|c| f.fold_node(c)).collect(),
+        ..node
+    }
+}
+
+/// walk_expression dispatches an expression to the fold method for its kind.
+/// It takes the bare expression since the expressions held by a `Node` have
+/// no span of their own.
+pub fn walk_expression<F: Fold + ?Sized>(f: &mut F, expr: Expression) -> Expression {
+    match expr {
+        Expression::Variable(v) => Expression::Variable(Box::new(f.fold_variable(*v))),
+        Expression::Function(function) => {
+            Expression::Function(Box::new(f.fold_function(*function)))
+        }
+        Expression::Enum(e) => Expression::Enum(Box::new(f.fold_enum(*e))),
+        Expression::BinaryOperation(op) => {
+            Expression::BinaryOperation(Box::new(f.fold_binary_operation(*op)))
+        }
+        Expression::UnaryOperation(op) => {
+            Expression::UnaryOperation(Box::new(f.fold_unary_operation(*op)))
+        }
+        Expression::TernaryOperation(op) => {
+            Expression::TernaryOperation(Box::new(f.fold_ternary_operation(*op)))
+        }
+        Expression::TagOverride(tag) => {
+            Expression::TagOverride(Box::new(f.fold_tag_override(*tag)))
+        }
+        Expression::Call(call) => Expression::Call(Box::new(f.fold_call(*call))),
+        Expression::Index(index) => Expression::Index(Box::new(f.fold_index(*index))),
+        Expression::Selector(array) => Expression::Selector(fold_boxed(f, array)),
+        Expression::Literal(literal) => Expression::Literal(f.fold_literal(literal)),
+        Expression::Symbol(name) => Expression::Symbol(f.fold_symbol(name)),
+        expr @ Expression::Tag(_) | expr @ Expression::GlobalScope | expr @ Expression::Error => {
+            expr
+        }
+    }
+}
+
+pub fn walk_statement<F: Fold + ?Sized>(f: &mut F, statement: Statement) -> Statement {
+    match statement {
+        Statement::Block(statements) => Statement::Block(
+            statements
+                .into_iter()
+                .map(|s| f.fold_statement(s))
+                .collect(),
+        ),
+        Statement::Declaration(nodes) => {
+            Statement::Declaration(nodes.into_iter().map(|n| f.fold_node(n)).collect())
+        }
+        Statement::Expression(expr) => Statement::Expression(f.fold_expression(expr)),
+        Statement::If(s) => Statement::If(Box::new(f.fold_if(*s))),
+        Statement::While(s) => Statement::While(Box::new(f.fold_while(*s))),
+        Statement::DoWhile(s) => Statement::DoWhile(Box::new(f.fold_do_while(*s))),
+        Statement::For(s) => Statement::For(Box::new(f.fold_for(*s))),
+        Statement::Switch(s) => Statement::Switch(Box::new(f.fold_switch(*s))),
+        Statement::State(s) => Statement::State(Box::new(f.fold_state_transition(*s))),
+        Statement::Return(expr) => Statement::Return(expr.map(|e| f.fold_expression(e))),
+        statement @ Statement::Label(_)
+        | statement @ Statement::Goto(_)
+        | statement @ Statement::Break
+        | statement @ Statement::Continue
+        | statement @ Statement::Empty
+        | statement @ Statement::Error => statement,
+    }
+}
+
+pub fn walk_variable<F: Fold + ?Sized>(f: &mut F, variable: Variable) -> Variable {
+    Variable {
+        dimensions: fold_dimensions(f, variable.dimensions),
+        initialiser: variable.initialiser.map(|i| f.fold_expression(i)),
+        ..variable
+    }
+}
+
+pub fn walk_function<F: Fold + ?Sized>(f: &mut F, function: Function) -> Function {
+    Function {
+        parameters: function
+            .parameters
+            .into_iter()
+            .map(|p| f.fold_parameter(p))
+            .collect(),
+        body: function
+            .body
+            .map(|body| body.into_iter().map(|s| f.fold_statement(s)).collect()),
+        ..function
+    }
+}
+
+pub fn walk_parameter<F: Fold + ?Sized>(f: &mut F, parameter: Parameter) -> Parameter {
+    Parameter {
+        dimensions: fold_dimensions(f, parameter.dimensions),
+        default: parameter.default.map(|d| f.fold_expression(d)),
+        ..parameter
+    }
+}
+
+pub fn walk_enum<F: Fold + ?Sized>(f: &mut F, e: Enum) -> Enum {
+    Enum {
+        increment: e.increment.map(|i| EnumIncrement {
+            operator: i.operator,
+            amount: f.fold_expression(i.amount),
+        }),
+        members: e
+            .members
+            .into_iter()
+            .map(|m| f.fold_enum_member(m))
+            .collect(),
+        ..e
+    }
+}
+
+pub fn walk_enum_member<F: Fold + ?Sized>(f: &mut F, member: EnumMember) -> EnumMember {
+    EnumMember {
+        size: member.size.map(|s| f.fold_expression(s)),
+        value: member.value.map(|v| f.fold_expression(v)),
+        ..member
+    }
+}
+
+pub fn walk_binary_operation<F: Fold + ?Sized>(
+    f: &mut F,
+    mut op: BinaryOperation,
+) -> BinaryOperation {
+    fold_in_place(f, &mut op.left);
+    fold_in_place(f, &mut op.right);
+    op
+}
+
+pub fn walk_unary_operation<F: Fold + ?Sized>(f: &mut F, mut op: UnaryOperation) -> UnaryOperation {
+    fold_in_place(f, &mut op.operand);
+    op
+}
+
+pub fn walk_ternary_operation<F: Fold + ?Sized>(
+    f: &mut F,
+    op: TernaryOperation,
+) -> TernaryOperation {
+    TernaryOperation {
+        condition: fold_boxed(f, op.condition),
+        left: fold_boxed(f, op.left),
+        right: fold_boxed(f, op.right),
+    }
+}
+
+pub fn walk_tag_override<F: Fold + ?Sized>(f: &mut F, tag: TagOverride) -> TagOverride {
+    TagOverride {
+        operand: fold_boxed(f, tag.operand),
+        ..tag
+    }
+}
+
+pub fn walk_call<F: Fold + ?Sized>(f: &mut F, mut call: Call) -> Call {
+    fold_in_place(f, &mut call.function);
+    call.arguments = mem::take(&mut call.arguments)
+        .into_iter()
+        .map(|a| f.fold_argument(a))
+        .collect();
+    call
+}
+
+pub fn walk_argument<F: Fold + ?Sized>(f: &mut F, argument: Argument) -> Argument {
+    match argument {
+        Argument::Positional(expr) => Argument::Positional(f.fold_expression(expr)),
+        Argument::Named { name, value } => Argument::Named {
+            name,
+            value: f.fold_expression(value),
+        },
+        Argument::Skipped => Argument::Skipped,
+    }
+}
+
+pub fn walk_index<F: Fold + ?Sized>(f: &mut F, mut index: Index) -> Index {
+    fold_in_place(f, &mut index.array);
+    fold_in_place(f, &mut index.index);
+    index
+}
+
+pub fn walk_literal<F: Fold + ?Sized>(f: &mut F, literal: Literal) -> Literal {
+    match literal {
+        Literal::Array(array) => Literal::Array(Box::new(f.fold_array(*array))),
+        literal => literal,
+    }
+}
+
+pub fn walk_array<F: Fold + ?Sized>(f: &mut F, array: Array) -> Array {
+    Array {
+        elements: array
+            .elements
+            .into_iter()
+            .map(|e| f.fold_expression(e))
+            .collect(),
+        ..array
+    }
+}
+
+pub fn walk_if<F: Fold + ?Sized>(f: &mut F, mut s: If) -> If {
+    fold_in_place(f, &mut s.condition);
+    let body = mem::replace(&mut s.body, Statement::Error.into());
+    s.body = f.fold_statement(body);
+    s.otherwise = s.otherwise.take().map(|o| f.fold_statement(o));
+    s
+}
+
+pub fn walk_while<F: Fold + ?Sized>(f: &mut F, s: While) -> While {
+    While {
+        condition: f.fold_expression(s.condition),
+        body: f.fold_statement(s.body),
+    }
+}
+
+pub fn walk_do_while<F: Fold + ?Sized>(f: &mut F, s: DoWhile) -> DoWhile {
+    let body = f.fold_statement(s.body);
+    DoWhile {
+        body,
+        condition: f.fold_expression(s.condition),
+    }
+}
+
+pub fn walk_for<F: Fold + ?Sized>(f: &mut F, s: For) -> For {
+    For {
+        initialiser: s.initialiser.map(|i| f.fold_statement(i)),
+        condition: s.condition.map(|c| f.fold_expression(c)),
+        increment: s.increment.map(|i| f.fold_expression(i)),
+        body: f.fold_statement(s.body),
+    }
+}
+
+pub fn walk_switch<F: Fold + ?Sized>(f: &mut F, s: Switch) -> Switch {
+    Switch {
+        subject: f.fold_expression(s.subject),
+        cases: s.cases.into_iter().map(|c| f.fold_case(c)).collect(),
+        default: s.default.map(|d| f.fold_statement(d)),
+    }
+}
+
+pub fn walk_case<F: Fold + ?Sized>(f: &mut F, case: Case) -> Case {
+    Case {
+        labels: case
+            .labels
+            .into_iter()
+            .map(|l| f.fold_case_label(l))
+            .collect(),
+        body: f.fold_statement(case.body),
+    }
+}
+
+pub fn walk_case_label<F: Fold + ?Sized>(f: &mut F, label: CaseLabel) -> CaseLabel {
+    match label {
+        CaseLabel::Value(value) => CaseLabel::Value(f.fold_expression(value)),
+        CaseLabel::Range(from, to) => {
+            let from = f.fold_expression(from);
+            CaseLabel::Range(from, f.fold_expression(to))
+        }
+    }
+}
+
+pub fn walk_state_transition<F: Fold + ?Sized>(f: &mut F, s: StateTransition) -> StateTransition {
+    StateTransition {
+        condition: s.condition.map(|c| f.fold_expression(c)),
+        ..s
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Symbol is an interned identifier, it is only meaningful with the
+/// `Interner` that returned it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Interner maps identifiers to small numbers and back. Symbols are numbered
+/// in the order names are first interned, so a workspace gives each file an
+/// interner of its own while it is parsed, then merges them in the order the
+/// files were added, to keep the numbering the same whichever thread parsed
+/// which file. Once filled it is only read, which any number of threads can
+/// do at once.
+#[derive(Debug, Default)]
+pub struct Interner {
+    symbols: HashMap<Arc<str>, Symbol>,
+    names: Vec<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /// intern returns the symbol for `name`, adding it if it is new.
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(name) {
+            return symbol;
+        }
+        let symbol = Symbol(self.names.len() as u32);
+        let name: Arc<str> = Arc::from(name);
+        self.names.push(name.clone());
+        self.symbols.insert(name, symbol);
+        symbol
+    }
+
+    /// get returns the symbol for `name` without adding it.
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    /// resolve returns the name of a symbol. It panics if the symbol came
+    /// from another interner.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.index()]
+    }
+
+    /// merge interns every name of another interner in the order they were
+    /// added to it, returning the symbol here of each symbol there, by its
+    /// index.
+    pub fn merge(&mut self, other: &Interner) -> Vec<Symbol> {
+        other.names.iter().map(|name| self.intern(name)).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(test)]
+use crate::intern::Interner;
+
+#[cfg(test)]
+use std::sync::Arc;
+#[cfg(test)]
+use std::thread;
+
+#[test]
+fn test_interner() {
+    let mut interner = Interner::new();
+    let a = interner.intern("a");
+    let b = interner.intern("b");
+
+    assert_ne!(a, b);
+    assert_eq!(interner.intern("a"), a);
+    assert_eq!(interner.get("b"), Some(b));
+    assert_eq!(interner.get("c"), None);
+    assert_eq!(interner.resolve(b), "b");
+    assert_eq!(interner.len(), 2);
+}
+
+#[test]
+fn test_interner_threads() {
+    let mut interner = Interner::new();
+    let symbols: Vec<_> = (0..100)
+        .map(|i| interner.intern(&format!("name{}", i)))
+        .collect();
+
+    // once filled, any number of threads can read it
+    let interner = Arc::new(interner);
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let interner = interner.clone();
+            thread::spawn(move || {
+                (0..100)
+                    .map(|i| interner.get(&format!("name{}", i)).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), symbols);
+    }
+    assert_eq!(interner.len(), 100);
+}
+
+#[test]
+fn test_interner_merge() {
+    let mut interner = Interner::new();
+    interner.intern("a");
+    interner.intern("b");
+
+    let mut other = Interner::new();
+    let c = other.intern("c");
+    let a = other.intern("a");
+
+    let symbols = interner.merge(&other);
+    assert_eq!(symbols.len(), 2);
+    assert_eq!(interner.resolve(symbols[c.index()]), "c");
+    assert_eq!(symbols[a.index()], interner.get("a").unwrap());
+    assert_eq!(symbols[c.index()].index(), 2);
+    assert_eq!(interner.len(), 3);
+}
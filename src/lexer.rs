@@ -16,7 +16,7 @@ pub struct Lexer<'a> {
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(input: &str) -> Lexer {
+    pub fn new(input: &str) -> Lexer<'_> {
         Lexer {
             input: input.chars().peekable(),
             initial_line: 1,
@@ -50,10 +50,7 @@ impl<'a> Lexer<'a> {
     }
 
     fn read_char(&mut self) -> Option<char> {
-        let next = match self.input.next() {
-            Some(v) => v,
-            None => return None,
-        };
+        let next = self.input.next()?;
         if next == '\n' {
             self.current_line += 1;
             self.current_column = 1;
@@ -67,6 +64,12 @@ impl<'a> Lexer<'a> {
         self.input.peek()
     }
 
+    fn peek_second_char(&self) -> Option<char> {
+        let mut input = self.input.clone();
+        input.next();
+        input.next()
+    }
+
     // fn peek_char_eq(&mut self, ch: char) -> bool {
     //     match self.peek_char() {
     //         Some(&peek_ch) => peek_ch == ch,
@@ -76,15 +79,11 @@ impl<'a> Lexer<'a> {
 
     fn peek_char_eq_consume(&mut self, ch: char) -> bool {
         match self.peek_char() {
-            Some(&peek_ch) => {
-                if peek_ch == ch {
-                    self.read_char();
-                    true
-                } else {
-                    false
-                }
+            Some(&peek_ch) if peek_ch == ch => {
+                self.read_char();
+                true
             }
-            None => false,
+            _ => false,
         }
     }
 
@@ -97,9 +96,9 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn peek_is_letter(&mut self) -> bool {
+    fn peek_is_symbol_char(&mut self) -> bool {
         match self.peek_char() {
-            Some(&ch) => is_letter(ch),
+            Some(&ch) => is_letter(ch) || ch.is_numeric() || ch == '@',
             None => false,
         }
     }
@@ -111,7 +110,7 @@ impl<'a> Lexer<'a> {
 
         while let Some(c) = self.read_char() {
             recent_chars.insert(c as u8);
-            if String::from(until) == String::from_utf8(recent_chars.unroll()).unwrap() {
+            if until.as_bytes() == recent_chars.unroll().as_slice() {
                 result.truncate(result.len() - until_len);
                 break;
             }
@@ -131,17 +130,36 @@ impl<'a> Lexer<'a> {
         line
     }
 
+    // read_string reads a string or character literal up to the closing
+    // quote, escape sequences are kept as they are written in the source.
+    fn read_string(&mut self, quote: char) -> String {
+        let mut result = String::new();
+        while let Some(&c) = self.peek_char() {
+            if c == quote || c == '\n' {
+                break;
+            }
+            result.push(self.read_char().unwrap());
+            if c == '\\' {
+                if let Some(escaped) = self.read_char() {
+                    result.push(escaped);
+                }
+            }
+        }
+        self.peek_char_eq_consume(quote);
+        result
+    }
+
     fn read_symbol(&mut self, first: char) -> Token {
         let mut ident = String::new();
         ident.push(first);
 
-        while self.peek_is_letter() {
+        while self.peek_is_symbol_char() {
             ident.push(self.read_char().unwrap());
         }
 
         let (kw, v) = match token::lookup_keyword(&ident) {
             Some(v) => (v, None),
-            None => (TokenType::Symbol, Some(TokenValue::String(ident.into()))),
+            None => (TokenType::Symbol, Some(TokenValue::String(ident))),
         };
 
         Token {
@@ -154,36 +172,92 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    // read_number reads an integer, in decimal, hexadecimal such as `0x1F` or
+    // binary such as `0b101`, or a rational number such as `1.5` or `1.0e-5`.
+    // Digits may be separated by `_`. An integer too large for a cell wraps
+    // around as it does in pawncc, so `4294967295` and `0xFFFFFFFF` are -1.
     fn read_number(&mut self, first: char) -> Token {
-        let mut number = String::new();
-        number.push(first);
+        let radix = match (first, self.peek_char()) {
+            ('0', Some('x')) => 16,
+            ('0', Some('b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 && self.peek_second_char().is_some_and(|c| c.is_digit(radix)) {
+            self.read_char();
+            let digits = self.read_digits(radix);
+            return self.integer(&digits, radix);
+        }
+
+        let mut number = first.to_string();
+        number.push_str(&self.read_digits(10));
+
+        // `1..5` is a range rather than a decimal point
+        if self.peek_char() == Some(&'.')
+            && self.peek_second_char().is_some_and(|c| c.is_ascii_digit())
+        {
+            self.read_char();
+            number.push('.');
+            number.push_str(&self.read_digits(10));
+            if let Some(exponent) = self.read_exponent() {
+                number.push_str(&exponent);
+            }
+            // every character was checked as it was read, and a number too
+            // large for a float is infinite rather than an error
+            let value = number.parse().unwrap_or_default();
+            return self.gen_token(TokenType::Float, Some(TokenValue::Float(value)));
+        }
+
+        self.integer(&number, 10)
+    }
 
-        let mut has_decimal = false;
+    // read_digits reads the digits of a number in the given radix, leaving out
+    // the `_` used to separate them.
+    fn read_digits(&mut self, radix: u32) -> String {
+        let mut digits = String::new();
         while let Some(&c) = self.peek_char() {
-            if !c.is_numeric() {
-                if c == '.' {
-                    has_decimal = true
-                } else if c == '_' {
-                    continue;
-                } else {
-                    break;
-                }
+            if c.is_digit(radix) {
+                digits.push(c);
+            } else if c != '_' {
+                break;
             }
+            self.read_char();
+        }
+        digits
+    }
 
-            number.push(self.read_char().unwrap());
+    // read_exponent reads the exponent of a rational number, such as `e5` or
+    // `e-5`, if there is one.
+    fn read_exponent(&mut self) -> Option<String> {
+        if self.peek_char() != Some(&'e') {
+            return None;
+        }
+        let mut input = self.input.clone();
+        input.next();
+        let sign = input.next_if(|c| *c == '-' || *c == '+');
+        if !input.peek().is_some_and(|c| c.is_ascii_digit()) {
+            return None;
         }
 
-        if has_decimal {
-            self.gen_token(
-                TokenType::Float,
-                Some(TokenValue::Float(number.parse().unwrap())),
-            )
-        } else {
-            self.gen_token(
-                TokenType::Integer,
-                Some(TokenValue::Integer(number.parse().unwrap())),
-            )
+        let mut exponent = String::from("e");
+        self.read_char();
+        if let Some(sign) = sign {
+            self.read_char();
+            exponent.push(sign);
         }
+        exponent.push_str(&self.read_digits(10));
+        Some(exponent)
+    }
+
+    // integer returns an integer token for digits in the given radix, which
+    // wraps around at the size of a cell.
+    fn integer(&self, digits: &str, radix: u32) -> Token {
+        let value = digits
+            .chars()
+            .filter_map(|c| c.to_digit(radix))
+            .fold(0u32, |value, digit| {
+                value.wrapping_mul(radix).wrapping_add(digit)
+            });
+        self.gen_token(TokenType::Integer, Some(TokenValue::Integer(value as i32)))
     }
 
     pub fn next_token(&mut self) -> Token {
@@ -295,6 +369,12 @@ impl<'a> Lexer<'a> {
                 } else if self.peek_char_eq_consume('>') {
                     if self.peek_char_eq_consume('=') {
                         self.gen_token(TokenType::BitRightAssign, None)
+                    } else if self.peek_char_eq_consume('>') {
+                        if self.peek_char_eq_consume('=') {
+                            self.gen_token(TokenType::BitRightLogicalAssign, None)
+                        } else {
+                            self.gen_token(TokenType::BitRightLogical, None)
+                        }
                     } else {
                         self.gen_token(TokenType::BitRight, None)
                     }
@@ -309,6 +389,8 @@ impl<'a> Lexer<'a> {
                     self.gen_token(TokenType::Bang, None)
                 }
             }
+            Some('~') => self.gen_token(TokenType::Tilde, None),
+            Some('?') => self.gen_token(TokenType::Question, None),
             Some(';') => self.gen_token(TokenType::Semicolon, None),
             Some(':') => self.gen_token(TokenType::Colon, None),
             Some(',') => self.gen_token(TokenType::Comma, None),
@@ -326,15 +408,29 @@ impl<'a> Lexer<'a> {
                         self.gen_token(TokenType::Range, None)
                     }
                 } else {
-                    self.gen_token(TokenType::Illegal, None)
+                    self.gen_token(TokenType::Dot, None)
+                }
+            }
+            Some('"') => {
+                let value = self.read_string('"');
+                self.gen_token(TokenType::Literal, Some(TokenValue::String(value)))
+            }
+            Some('\'') => {
+                let value = unescape(&self.read_string('\''));
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => {
+                        self.gen_token(TokenType::Character, Some(TokenValue::Integer(c as i32)))
+                    }
+                    _ => self.gen_token(TokenType::Illegal, None),
                 }
             }
             Some('#') => self.gen_token(TokenType::Directive, None),
 
-            Some(ch @ _) => {
+            Some(ch) => {
                 if is_letter(ch) {
                     self.read_symbol(ch)
-                } else if ch.is_numeric() {
+                } else if ch.is_ascii_digit() {
                     self.read_number(ch)
                 } else {
                     self.gen_token(TokenType::Illegal, None)
@@ -349,3 +445,45 @@ impl<'a> Lexer<'a> {
 fn is_letter(ch: char) -> bool {
     ch.is_alphabetic() || ch == '_'
 }
+
+/// unescape returns the text a string or character literal stands for, given
+/// the literal as written without its quotes. Numeric escapes such as `\65;`
+/// and `\x41;` may leave out the closing semicolon. An unknown escape stands
+/// for the character after the backslash.
+pub fn unescape(text: &str) -> String {
+    let mut result = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        let escaped = match chars.next() {
+            Some('a') => '\u{7}',
+            Some('b') => '\u{8}',
+            Some('e') => '\u{1b}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('v') => '\u{b}',
+            Some(first) if first == 'x' || first.is_ascii_digit() => {
+                let radix = if first == 'x' { 16 } else { 10 };
+                let mut value = first.to_digit(10).unwrap_or(0);
+                while let Some(digit) = chars.peek().and_then(|d| d.to_digit(radix)) {
+                    value = value.wrapping_mul(radix).wrapping_add(digit);
+                    chars.next();
+                }
+                if chars.peek() == Some(&';') {
+                    chars.next();
+                }
+                std::char::from_u32(value).unwrap_or(std::char::REPLACEMENT_CHARACTER)
+            }
+            Some(other) => other,
+            None => '\\',
+        };
+        result.push(escaped);
+    }
+    result
+}
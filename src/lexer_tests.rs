@@ -1,5 +1,5 @@
 #[cfg(test)]
-use crate::lexer::Lexer;
+use crate::lexer::{unescape, Lexer};
 #[cfg(test)]
 use crate::token::{Token, TokenType, TokenValue};
 
@@ -781,3 +781,102 @@ main() {
         ]
     );
 }
+
+#[test]
+fn lex_string_literal() {
+    assert_eq!(
+        Lexer::new(r#""say \"hi\"""#).lex(),
+        vec![Token {
+            token_type: TokenType::Literal,
+            value: Some(TokenValue::String(r#"say \"hi\""#.into())),
+            line_start: 1,
+            line_end: 1,
+            column_start: 1,
+            column_end: 13,
+        }],
+    );
+}
+
+#[test]
+fn lex_character_literal() {
+    assert_eq!(
+        Lexer::new(r"'a' '\n' '\'' '\x41;' '\65'")
+            .lex()
+            .into_iter()
+            .map(|t| (t.token_type, t.value))
+            .collect::<Vec<_>>(),
+        vec![
+            (TokenType::Character, Some(TokenValue::Integer(97))),
+            (TokenType::Character, Some(TokenValue::Integer(10))),
+            (TokenType::Character, Some(TokenValue::Integer(39))),
+            (TokenType::Character, Some(TokenValue::Integer(65))),
+            (TokenType::Character, Some(TokenValue::Integer(65))),
+        ]
+    );
+    assert_eq!(Lexer::new("'ab'").lex()[0].token_type, TokenType::Illegal);
+}
+
+#[test]
+fn lex_number_forms() {
+    assert_eq!(
+        Lexer::new("0x1f 0xFFFFFFFF 0b1010 1_000 1.5 1.0e5 2.5e-3 4.0e+1 1..5 0x 1.e")
+            .lex()
+            .into_iter()
+            .map(|t| (t.token_type, t.value))
+            .collect::<Vec<_>>(),
+        vec![
+            (TokenType::Integer, Some(TokenValue::Integer(31))),
+            (TokenType::Integer, Some(TokenValue::Integer(-1))),
+            (TokenType::Integer, Some(TokenValue::Integer(10))),
+            (TokenType::Integer, Some(TokenValue::Integer(1000))),
+            (TokenType::Float, Some(TokenValue::Float(1.5))),
+            (TokenType::Float, Some(TokenValue::Float(100000.0))),
+            (TokenType::Float, Some(TokenValue::Float(0.0025))),
+            (TokenType::Float, Some(TokenValue::Float(40.0))),
+            (TokenType::Integer, Some(TokenValue::Integer(1))),
+            (TokenType::Range, None),
+            (TokenType::Integer, Some(TokenValue::Integer(5))),
+            // a prefix without digits is a zero followed by a name
+            (TokenType::Integer, Some(TokenValue::Integer(0))),
+            (TokenType::Symbol, Some(TokenValue::String("x".into()))),
+            (TokenType::Integer, Some(TokenValue::Integer(1))),
+            (TokenType::Dot, None),
+            (TokenType::Symbol, Some(TokenValue::String("e".into()))),
+        ]
+    );
+}
+
+#[test]
+fn lex_number_overflow() {
+    // integers too large for a cell wrap around as they do in pawncc
+    let cases = [
+        ("2147483647", i32::MAX),
+        ("2147483648", i32::MIN),
+        ("4294967295", -1),
+        ("4294967296", 0),
+        ("99999999999", 1215752191),
+        ("0x80000000", i32::MIN),
+        ("0x100000001", 1),
+        ("0b11111111111111111111111111111111", -1),
+    ];
+    for (source, expected) in cases.iter() {
+        assert_eq!(
+            Lexer::new(source).lex()[0].value,
+            Some(TokenValue::Integer(*expected)),
+            "{}",
+            source
+        );
+    }
+    assert_eq!(
+        Lexer::new("1.0e99").lex()[0].value,
+        Some(TokenValue::Float(f32::INFINITY))
+    );
+}
+
+#[test]
+fn lex_unescape() {
+    assert_eq!(unescape(r#"say \"hi\"\n"#), "say \"hi\"\n");
+    assert_eq!(unescape(r"\x41;\x42!"), "AB!");
+    assert_eq!(unescape(r"\65;6\66"), "A6B");
+    assert_eq!(unescape(r"100\%\q\"), "100%q\\");
+}
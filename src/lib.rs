@@ -1,6 +1,35 @@
+pub mod arena;
+mod arena_tests;
 pub mod ast;
 mod ast_tests;
+pub mod const_eval;
+mod const_eval_tests;
+pub mod cst;
+mod cst_tests;
+pub mod error;
+pub mod fold;
+pub mod intern;
+mod intern_tests;
 pub mod lexer;
 mod lexer_tests;
+pub mod lookup;
+mod lookup_tests;
+pub mod parse;
+mod parse_tests;
+pub mod printer;
+mod printer_tests;
+mod recovery_tests;
+pub mod resolve;
+mod resolve_tests;
 mod ring;
+pub mod schema;
+mod schema_tests;
+pub mod tags;
+mod tags_tests;
+mod test_utils;
 pub mod token;
+pub mod visit;
+pub mod visit_mut;
+mod visit_tests;
+pub mod workspace;
+mod workspace_tests;
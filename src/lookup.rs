@@ -0,0 +1,186 @@
+use crate::ast::{
+    Argument, Case, CaseLabel, EnumMember, Expression, Literal, Node, Parameter, Spanned, Statement,
+};
+use crate::token::{Position, Span};
+
+/// AstRef is a reference to any node of the tree that carries a span, it is
+/// what position lookups return since a chain of nodes mixes declarations,
+/// statements and expressions.
+#[derive(Debug, Clone, Copy)]
+pub enum AstRef<'a> {
+    Node(&'a Node),
+    Expression(&'a Spanned<Expression>),
+    Statement(&'a Spanned<Statement>),
+    Parameter(&'a Spanned<Parameter>),
+    EnumMember(&'a Spanned<EnumMember>),
+    Case(&'a Spanned<Case>),
+    Argument(&'a Spanned<Argument>),
+}
+
+impl<'a> AstRef<'a> {
+    pub fn span(&self) -> Span {
+        match self {
+            AstRef::Node(n) => n.span,
+            AstRef::Expression(e) => e.span,
+            AstRef::Statement(s) => s.span,
+            AstRef::Parameter(p) => p.span,
+            AstRef::EnumMember(m) => m.span,
+            AstRef::Case(c) => c.span,
+            AstRef::Argument(a) => a.span,
+        }
+    }
+
+    /// children returns the direct children of the node in source order.
+    pub fn children(&self) -> Vec<AstRef<'a>> {
+        let mut out = vec![];
+        match *self {
+            AstRef::Node(n) => {
+                expression_children(&n.expr, &mut out);
+                out.extend(n.children.iter().map(AstRef::Node));
+            }
+            AstRef::Expression(e) => expression_children(&e.node, &mut out),
+            AstRef::Statement(s) => statement_children(&s.node, &mut out),
+            AstRef::Parameter(p) => {
+                out.extend(p.dimensions.iter().flatten().map(AstRef::Expression));
+                out.extend(p.default.iter().map(AstRef::Expression));
+            }
+            AstRef::EnumMember(m) => {
+                out.extend(m.size.iter().map(AstRef::Expression));
+                out.extend(m.value.iter().map(AstRef::Expression));
+            }
+            AstRef::Case(c) => {
+                for label in &c.labels {
+                    match label {
+                        CaseLabel::Value(v) => out.push(AstRef::Expression(v)),
+                        CaseLabel::Range(from, to) => {
+                            out.push(AstRef::Expression(from));
+                            out.push(AstRef::Expression(to));
+                        }
+                    }
+                }
+                out.push(AstRef::Statement(&c.body));
+            }
+            AstRef::Argument(a) => match &a.node {
+                Argument::Positional(e) => out.push(AstRef::Expression(e)),
+                Argument::Named { value, .. } => out.push(AstRef::Expression(value)),
+                Argument::Skipped => {}
+            },
+        }
+        out
+    }
+}
+
+// expression_children collects the spanned children of an expression, which
+// may not carry a span itself when it is held by a `Node`.
+fn expression_children<'a>(expr: &'a Expression, out: &mut Vec<AstRef<'a>>) {
+    match expr {
+        Expression::Variable(v) => {
+            out.extend(v.dimensions.iter().flatten().map(AstRef::Expression));
+            out.extend(v.initialiser.iter().map(AstRef::Expression));
+        }
+        Expression::Function(f) => {
+            out.extend(f.parameters.iter().map(AstRef::Parameter));
+            out.extend(f.body.iter().flatten().map(AstRef::Statement));
+        }
+        Expression::Enum(e) => {
+            out.extend(e.increment.iter().map(|i| AstRef::Expression(&i.amount)));
+            out.extend(e.members.iter().map(AstRef::EnumMember));
+        }
+        Expression::BinaryOperation(op) => {
+            out.push(AstRef::Expression(&op.left));
+            out.push(AstRef::Expression(&op.right));
+        }
+        Expression::UnaryOperation(op) => out.push(AstRef::Expression(&op.operand)),
+        Expression::TernaryOperation(op) => {
+            out.push(AstRef::Expression(&op.condition));
+            out.push(AstRef::Expression(&op.left));
+            out.push(AstRef::Expression(&op.right));
+        }
+        Expression::TagOverride(tag) => out.push(AstRef::Expression(&tag.operand)),
+        Expression::Call(call) => {
+            out.push(AstRef::Expression(&call.function));
+            out.extend(call.arguments.iter().map(AstRef::Argument));
+        }
+        Expression::Index(index) => {
+            out.push(AstRef::Expression(&index.array));
+            out.push(AstRef::Expression(&index.index));
+        }
+        Expression::Selector(array) => out.push(AstRef::Expression(array)),
+        Expression::Literal(Literal::Array(array)) => {
+            out.extend(array.elements.iter().map(AstRef::Expression));
+        }
+        Expression::GlobalScope
+        | Expression::Symbol(_)
+        | Expression::Tag(_)
+        | Expression::Literal(_)
+        | Expression::Error => {}
+    }
+}
+
+// statement_children collects the spanned children of a statement.
+fn statement_children<'a>(statement: &'a Statement, out: &mut Vec<AstRef<'a>>) {
+    match statement {
+        Statement::Block(statements) => out.extend(statements.iter().map(AstRef::Statement)),
+        Statement::Declaration(nodes) => out.extend(nodes.iter().map(AstRef::Node)),
+        Statement::Expression(e) => out.push(AstRef::Expression(e)),
+        Statement::If(s) => {
+            out.push(AstRef::Expression(&s.condition));
+            out.push(AstRef::Statement(&s.body));
+            out.extend(s.otherwise.iter().map(AstRef::Statement));
+        }
+        Statement::While(s) => {
+            out.push(AstRef::Expression(&s.condition));
+            out.push(AstRef::Statement(&s.body));
+        }
+        Statement::DoWhile(s) => {
+            out.push(AstRef::Statement(&s.body));
+            out.push(AstRef::Expression(&s.condition));
+        }
+        Statement::For(s) => {
+            out.extend(s.initialiser.iter().map(AstRef::Statement));
+            out.extend(s.condition.iter().map(AstRef::Expression));
+            out.extend(s.increment.iter().map(AstRef::Expression));
+            out.push(AstRef::Statement(&s.body));
+        }
+        Statement::Switch(s) => {
+            out.push(AstRef::Expression(&s.subject));
+            out.extend(s.cases.iter().map(AstRef::Case));
+            out.extend(s.default.iter().map(AstRef::Statement));
+        }
+        Statement::State(s) => out.extend(s.condition.iter().map(AstRef::Expression)),
+        Statement::Return(e) => out.extend(e.iter().map(AstRef::Expression)),
+        Statement::Label(_)
+        | Statement::Goto(_)
+        | Statement::Break
+        | Statement::Continue
+        | Statement::Empty
+        | Statement::Error => {}
+    }
+}
+
+impl Node {
+    /// node_at returns the chain of nodes containing `position`, from this
+    /// node down to the innermost one, which is last. The chain is empty when
+    /// the position is outside of this node. Use `LineIndex::position` to look
+    /// up a byte offset.
+    pub fn node_at(&self, position: Position) -> Vec<AstRef<'_>> {
+        self.chain(|span| span.contains(position))
+    }
+
+    /// nodes_covering returns the chain of nodes that cover all of `span`,
+    /// from this node down to the innermost one, which is last.
+    pub fn nodes_covering(&self, span: Span) -> Vec<AstRef<'_>> {
+        self.chain(|s| s.covers(span))
+    }
+
+    // chain descends into the first child matching `f` until none does.
+    fn chain(&self, f: impl Fn(Span) -> bool) -> Vec<AstRef<'_>> {
+        let mut chain = vec![];
+        let mut next = Some(AstRef::Node(self)).filter(|n| f(n.span()));
+        while let Some(node) = next {
+            next = node.children().into_iter().find(|c| f(c.span()));
+            chain.push(node);
+        }
+        chain
+    }
+}
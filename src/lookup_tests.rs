@@ -0,0 +1,139 @@
+#[cfg(test)]
+use crate::ast::{Expression, Literal, Statement};
+#[cfg(test)]
+use crate::lookup::AstRef;
+#[cfg(test)]
+use crate::test_utils::parse;
+#[cfg(test)]
+use crate::token::{LineIndex, Position, Span};
+
+// describe names each node of a chain so they can be compared easily.
+#[cfg(test)]
+fn describe(chain: &[AstRef]) -> Vec<String> {
+    chain
+        .iter()
+        .map(|n| match n {
+            AstRef::Node(n) => match &n.expr {
+                Expression::GlobalScope => "root".into(),
+                Expression::Function(f) => format!("function {}", f.name),
+                Expression::Variable(v) => format!("variable {}", v.name),
+                e => format!("node {:?}", e),
+            },
+            AstRef::Expression(e) => match &e.node {
+                Expression::Symbol(s) => format!("symbol {}", s),
+                Expression::Literal(Literal::Integer(i)) => format!("integer {}", i),
+                Expression::BinaryOperation(op) => format!("binary {}", op.operator.token_type),
+                Expression::Call(_) => "call".into(),
+                Expression::Literal(Literal::Array(_)) => "array".into(),
+                e => format!("expression {:?}", e),
+            },
+            AstRef::Statement(s) => match &s.node {
+                Statement::Block(_) => "block".into(),
+                Statement::If(_) => "if".into(),
+                Statement::Expression(_) => "expression statement".into(),
+                Statement::Return(_) => "return".into(),
+                s => format!("statement {:?}", s),
+            },
+            AstRef::Parameter(p) => format!("parameter {:?}", p.name),
+            AstRef::EnumMember(m) => format!("member {}", m.name),
+            AstRef::Case(_) => "case".into(),
+            AstRef::Argument(_) => "argument".into(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_spans() {
+    let root = parse("new a = 1;\nmain() {\n    return a + 2;\n}");
+
+    let spans: Vec<Span> = root.children.iter().map(|n| n.span).collect();
+    assert_eq!(
+        spans,
+        vec![
+            Span {
+                line_start: 1,
+                line_end: 1,
+                column_start: 5,
+                column_end: 10,
+            },
+            Span {
+                line_start: 2,
+                line_end: 4,
+                column_start: 1,
+                column_end: 2,
+            },
+        ]
+    );
+
+    let function = match &root.children[1].expr {
+        Expression::Function(f) => f,
+        e => panic!("expected function, got {:?}", e),
+    };
+    let statement = &function.body.as_ref().unwrap()[0];
+    assert_eq!(
+        statement.span,
+        Span {
+            line_start: 3,
+            line_end: 3,
+            column_start: 5,
+            column_end: 18,
+        }
+    );
+}
+
+#[test]
+fn test_node_at() {
+    let source = "main() {\n    if (a) {\n        Func(b + 10);\n    }\n}";
+    let root = parse(source);
+
+    // the cursor on the `1` of `10`
+    let position = Position::new(3, 18);
+    assert_eq!(
+        describe(&root.node_at(position)),
+        vec![
+            "root",
+            "function main",
+            "if",
+            "block",
+            "expression statement",
+            "call",
+            "argument",
+            "binary +",
+            "integer 10",
+        ]
+    );
+
+    let offset = source.find("10").unwrap();
+    assert_eq!(LineIndex::new(source).position(source, offset), position);
+
+    // between statements only the enclosing nodes match
+    assert_eq!(
+        describe(&root.node_at(Position::new(3, 3))),
+        vec!["root", "function main", "if", "block"]
+    );
+    assert!(root.node_at(Position::new(9, 1)).is_empty());
+}
+
+#[test]
+fn test_nodes_covering() {
+    let root = parse("new a = 1, b[2] = {3, 4};\nenum E { A, B = 5 }");
+
+    let range = Span {
+        line_start: 1,
+        line_end: 1,
+        column_start: 20,
+        column_end: 24,
+    };
+    let chain = root.nodes_covering(range);
+    assert_eq!(describe(&chain), vec!["root", "variable b", "array"]);
+
+    let range = Span {
+        line_start: 2,
+        line_end: 2,
+        column_start: 13,
+        column_end: 18,
+    };
+    let chain = root.nodes_covering(range);
+    assert_eq!(describe(&chain)[2], "member B");
+    assert_eq!(chain.last().unwrap().span(), range);
+}
@@ -0,0 +1,69 @@
+use crate::ast::Expression;
+use crate::ast::Node;
+use crate::ast::Parser;
+use crate::ast::Spanned;
+use crate::ast::Statement;
+use crate::error::Diagnostic;
+use crate::lexer::Lexer;
+
+/// Parsed is the result of one of the parse functions. There is always a
+/// node, where the input could not be parsed at all it is an `Error` node
+/// covering the input. The node is only complete when there are no
+/// diagnostics.
+#[derive(Debug)]
+pub struct Parsed<T> {
+    pub node: T,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl<T> Parsed<T> {
+    /// is_ok reports whether the input parsed without any errors.
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// parse_expression_str parses a single expression, such as the body of a
+/// `#define` or a watch expression. The comma operator is allowed, so
+/// `a, b` is one expression.
+pub fn parse_expression_str(source: &str) -> Parsed<Spanned<Expression>> {
+    let mut parser = Parser::new(Lexer::new(source).lex());
+    let (node, diagnostics) = parser.parse_fragment(Parser::parse_comma_expression, |span| {
+        Spanned::new(Expression::Error, span)
+    });
+    Parsed { node, diagnostics }
+}
+
+/// parse_statement_str parses a single statement as found in a function
+/// body, including its `;`. Use a block to parse more than one.
+pub fn parse_statement_str(source: &str) -> Parsed<Spanned<Statement>> {
+    let mut parser = Parser::new(Lexer::new(source).lex());
+    let (node, diagnostics) = parser.parse_fragment(Parser::parse_statement, |span| {
+        Spanned::new(Statement::Error, span)
+    });
+    Parsed { node, diagnostics }
+}
+
+/// parse_declaration_str parses a single top-level declaration, which is a
+/// function, an enum or a variable declaration. A variable declaration gives
+/// one node per declared variable, so `new a, b;` gives two.
+pub fn parse_declaration_str(source: &str) -> Parsed<Vec<Node>> {
+    let mut parser = Parser::new(Lexer::new(source).lex());
+    let (node, diagnostics) = parser.parse_fragment(Parser::parse_top_level, |span| {
+        let mut node = Node::new(Expression::Error);
+        node.span = span;
+        vec![node]
+    });
+    Parsed { node, diagnostics }
+}
+
+/// parse_file parses a whole file, the node is the global scope. Errors are
+/// recovered from the same way as `Parser::parse`.
+pub fn parse_file(source: &str) -> Parsed<Node> {
+    let mut parser = Parser::new(Lexer::new(source).lex());
+    let diagnostics = parser.parse().err().unwrap_or_default();
+    Parsed {
+        node: parser.root,
+        diagnostics,
+    }
+}
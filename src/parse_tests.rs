@@ -0,0 +1,119 @@
+#[cfg(test)]
+use crate::ast::Expression;
+#[cfg(test)]
+use crate::ast::Statement;
+#[cfg(test)]
+use crate::error::ParseError;
+#[cfg(test)]
+use crate::parse::{parse_declaration_str, parse_expression_str, parse_file, parse_statement_str};
+#[cfg(test)]
+use crate::token::TokenType;
+
+#[test]
+fn test_parse_expression_str() {
+    let parsed = parse_expression_str("a + b * 2");
+    assert!(parsed.is_ok());
+    assert!(matches!(parsed.node.node, Expression::BinaryOperation(_)));
+    assert_eq!(parsed.node.span.column_start, 1);
+    assert_eq!(parsed.node.span.column_end, 10);
+
+    let parsed = parse_expression_str("f(a), b");
+    assert!(parsed.is_ok());
+
+    // A `#define` body is lexed on its own, so directives before it are
+    // skipped.
+    let parsed = parse_expression_str("#define X\nMAX_PLAYERS - 1");
+    assert!(parsed.is_ok());
+    // a number too large for a cell wraps around rather than failing
+    let parsed = parse_expression_str("4294967295 + 0xFFFFFFFF + 1.0e5");
+    assert!(parsed.is_ok(), "{:?}", parsed.diagnostics);
+}
+
+#[test]
+fn test_parse_expression_str_errors() {
+    let parsed = parse_expression_str("a b");
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(
+        parsed.diagnostics[0].error,
+        ParseError::TrailingInput {
+            found: Some(TokenType::Symbol)
+        }
+    );
+    assert_eq!(parsed.diagnostics[0].code(), 902);
+    assert_eq!(parsed.diagnostics[0].span.column_start, 3);
+    assert_eq!(parsed.node.node, Expression::Symbol("a".into()));
+
+    let parsed = parse_expression_str("a +");
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.diagnostics[0].error.found(), None);
+    assert_eq!(parsed.node.node, Expression::Error);
+    assert_eq!(parsed.node.span.column_end, 4);
+
+    let parsed = parse_expression_str("");
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.node.node, Expression::Error);
+
+    // empty brackets only select a dimension in the operand of `sizeof`
+    for source in ["a[]", "sizeof a[b[]]", "sizeof f(a[])"].iter() {
+        let parsed = parse_expression_str(source);
+        assert_eq!(parsed.diagnostics[0].code(), 29, "{}", source);
+    }
+}
+
+#[test]
+fn test_parse_statement_str() {
+    let parsed = parse_statement_str("if (a) { b(); } else c = 1;");
+    assert!(parsed.is_ok());
+    assert!(matches!(parsed.node.node, Statement::If(_)));
+
+    let parsed = parse_statement_str("a = 1; b = 2;");
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.diagnostics[0].code(), 902);
+
+    let parsed = parse_statement_str("#pragma semicolon 1\na = 1");
+    assert_eq!(parsed.node.node, Statement::Error);
+    assert_eq!(
+        parsed.diagnostics[0].error.expected(),
+        &[TokenType::Semicolon][..]
+    );
+
+    // An error inside a block is recovered from and the block is kept.
+    let parsed = parse_statement_str("{ a = ; b(); }");
+    assert_eq!(parsed.diagnostics.len(), 1);
+    match parsed.node.node {
+        Statement::Block(body) => assert_eq!(body.len(), 2),
+        s => panic!("expected block, got {:?}", s),
+    }
+}
+
+#[test]
+fn test_parse_declaration_str() {
+    let parsed = parse_declaration_str("new a = 1, b[4];");
+    assert!(parsed.is_ok());
+    assert_eq!(parsed.node.len(), 2);
+
+    let parsed = parse_declaration_str("stock f(a) { return a; }");
+    assert!(parsed.is_ok());
+    assert!(matches!(parsed.node[0].expr, Expression::Function(_)));
+
+    let parsed = parse_declaration_str("new a; new b;");
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.diagnostics[0].code(), 902);
+
+    let parsed = parse_declaration_str("a = 1;");
+    assert_eq!(parsed.node.len(), 1);
+    assert_eq!(parsed.node[0].expr, Expression::Error);
+}
+
+#[test]
+fn test_parse_file() {
+    let parsed = parse_file("new a;\nmain() {}\n");
+    assert!(parsed.is_ok());
+    assert_eq!(parsed.node.expr, Expression::GlobalScope);
+    assert_eq!(parsed.node.children.len(), 2);
+
+    let parsed = parse_file("new a = ;\nmain() {}\n");
+    assert_eq!(parsed.diagnostics.len(), 1);
+    assert_eq!(parsed.node.children.len(), 2);
+    assert_eq!(parsed.node.children[0].expr, Expression::Error);
+}
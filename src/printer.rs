@@ -0,0 +1,708 @@
+use crate::ast::binary_precedence;
+use crate::ast::is_assignment;
+use crate::ast::Argument;
+use crate::ast::CaseLabel;
+use crate::ast::Enum;
+use crate::ast::Expression;
+use crate::ast::Function;
+use crate::ast::FunctionKind;
+use crate::ast::Literal;
+use crate::ast::NativeAlias;
+use crate::ast::Node;
+use crate::ast::Parameter;
+use crate::ast::Spanned;
+use crate::ast::Statement;
+use crate::ast::StorageClass;
+use crate::ast::Variable;
+use crate::token::TokenType;
+
+// precedence levels of expressions, higher binds tighter. Binary operators
+// take the levels between TERNARY and UNARY, see `expression_precedence`.
+const COMMA: u8 = 0;
+const ASSIGNMENT: u8 = 1;
+const TERNARY: u8 = 2;
+const BINARY: u8 = 3;
+const UNARY: u8 = 13;
+const POSTFIX: u8 = 14;
+const PRIMARY: u8 = 15;
+
+/// print returns the source of a whole file from the root node of a parse.
+pub fn print(root: &Node) -> String {
+    let mut p = Printer::new();
+    p.file(root);
+    p.out
+}
+
+/// print_expression returns the source of a single expression, with only the
+/// brackets that precedence requires.
+pub fn print_expression(expr: &Expression) -> String {
+    let mut p = Printer::new();
+    p.expression(expr, COMMA);
+    p.out
+}
+
+/// print_statement returns the source of a single statement, nested blocks
+/// are indented by four spaces.
+pub fn print_statement(statement: &Statement) -> String {
+    let mut p = Printer::new();
+    p.statement(statement);
+    p.out
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+    // whether a tag override can be written without brackets, this mirrors
+    // `allow_tags` in the parser.
+    allow_tags: bool,
+    // whether nothing has been written since the start of an expression
+    // statement, where `tag:` would be read as a label and `{` as a block.
+    statement_start: bool,
+}
+
+impl Printer {
+    fn new() -> Printer {
+        Printer {
+            out: String::new(),
+            indent: 0,
+            allow_tags: true,
+            statement_start: false,
+        }
+    }
+
+    fn write(&mut self, s: &str) {
+        self.out.push_str(s);
+        self.statement_start = false;
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str("    ");
+        }
+    }
+
+    fn with_tags(&mut self, allow: bool, f: impl FnOnce(&mut Printer)) {
+        let previous = self.allow_tags;
+        self.allow_tags = allow;
+        f(self);
+        self.allow_tags = previous;
+    }
+
+    fn file(&mut self, root: &Node) {
+        let mut previous: Option<&Expression> = None;
+        for node in &root.children {
+            if matches!(node.expr, Expression::Error) {
+                continue;
+            }
+            // functions and enums are kept apart by a blank line
+            if let Some(previous) = previous {
+                if is_item(previous) || is_item(&node.expr) {
+                    self.out.push('\n');
+                }
+            }
+            match &node.expr {
+                Expression::Variable(_) => self.declaration(std::slice::from_ref(node)),
+                Expression::Function(function) => self.function(function),
+                Expression::Enum(e) => self.enumeration(e),
+                expr => self.expression(expr, COMMA),
+            }
+            self.out.push('\n');
+            previous = Some(&node.expr);
+        }
+    }
+
+    // declaration writes variables that share a storage class, such as
+    // `new a = 1, b[2];`.
+    fn declaration(&mut self, nodes: &[Node]) {
+        for (i, node) in nodes.iter().enumerate() {
+            let variable = match &node.expr {
+                Expression::Variable(v) => v,
+                _ => continue,
+            };
+            if i == 0 {
+                self.write(storage_class(variable));
+                if variable.is_const && variable.storage != StorageClass::Const {
+                    self.write(" const");
+                }
+                self.write(" ");
+            } else {
+                self.write(", ");
+            }
+            self.variable(variable);
+            if let Some(initialiser) = &variable.initialiser {
+                self.write(" = ");
+                self.expression(&initialiser.node, ASSIGNMENT);
+            }
+        }
+        self.write(";");
+    }
+
+    fn variable(&mut self, variable: &Variable) {
+        self.tag(&variable.tag);
+        self.write(&variable.name);
+        self.dimensions(&variable.dimensions);
+    }
+
+    fn dimensions(&mut self, dimensions: &[Option<Spanned<Expression>>]) {
+        for dimension in dimensions {
+            self.write("[");
+            if let Some(size) = dimension {
+                self.with_tags(true, |p| p.expression(size, ASSIGNMENT));
+            }
+            self.write("]");
+        }
+    }
+
+    fn tag(&mut self, tag: &str) {
+        if tag != "_" {
+            self.write(tag);
+            self.write(":");
+        }
+    }
+
+    fn function(&mut self, function: &Function) {
+        match function.kind {
+            FunctionKind::Forward => self.write("forward "),
+            FunctionKind::Native => self.write("native "),
+            FunctionKind::Definition => {}
+        }
+        if function.is_public {
+            self.write("public ");
+        }
+        if function.is_static {
+            self.write("static ");
+        }
+        if function.is_stock {
+            self.write("stock ");
+        }
+        self.tag(&function.tag);
+        self.write(&function.name);
+
+        self.write("(");
+        for (i, parameter) in function.parameters.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            self.parameter(parameter);
+        }
+        self.write(")");
+
+        if let Some(states) = &function.states {
+            self.write(" <");
+            if let Some(automaton) = &states.automaton {
+                self.write(automaton);
+                self.write(":");
+            }
+            self.write(&states.states.join(", "));
+            self.write(">");
+        }
+
+        match (&function.native_alias, &function.body) {
+            (Some(NativeAlias::Name(name)), _) => {
+                self.write(" = ");
+                self.write(name);
+                self.write(";");
+            }
+            (Some(NativeAlias::Index(index)), _) => self.write(&format!(" = {};", index)),
+            (None, Some(body)) => {
+                self.write(" ");
+                self.block(body);
+            }
+            (None, None) => self.write(";"),
+        }
+    }
+
+    fn parameter(&mut self, parameter: &Parameter) {
+        if parameter.is_const {
+            self.write("const ");
+        }
+        if parameter.is_reference {
+            self.write("&");
+        }
+        match parameter.tags.as_slice() {
+            [tag] => self.tag(tag),
+            tags => {
+                self.write("{");
+                self.write(&tags.join(", "));
+                self.write("}:");
+            }
+        }
+        if parameter.is_variadic {
+            self.write("...");
+            return;
+        }
+        if let Some(name) = &parameter.name {
+            self.write(name);
+        }
+        self.dimensions(&parameter.dimensions);
+        if let Some(default) = &parameter.default {
+            self.write(" = ");
+            self.expression(default, ASSIGNMENT);
+        }
+    }
+
+    fn enumeration(&mut self, e: &Enum) {
+        self.write("enum ");
+        if let Some(tag) = &e.tag {
+            self.write(tag);
+            self.write(":");
+        }
+        if let Some(name) = &e.name {
+            self.write(name);
+            self.write(" ");
+        }
+        if let Some(increment) = &e.increment {
+            self.write("(");
+            self.write(&increment.operator.token_type.to_string());
+            self.write(" ");
+            self.expression(&increment.amount, ASSIGNMENT);
+            self.write(") ");
+        }
+        self.write("{");
+        self.indent += 1;
+        for member in &e.members {
+            self.newline();
+            self.tag(&member.tag);
+            self.write(&member.name);
+            if let Some(size) = &member.size {
+                self.write("[");
+                self.expression(size, ASSIGNMENT);
+                self.write("]");
+            }
+            if let Some(value) = &member.value {
+                self.write(" = ");
+                self.expression(value, ASSIGNMENT);
+            }
+            self.write(",");
+        }
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+    }
+
+    fn block(&mut self, statements: &[Spanned<Statement>]) {
+        if statements.is_empty() {
+            self.write("{}");
+            return;
+        }
+        self.write("{");
+        self.indent += 1;
+        for statement in statements {
+            self.newline();
+            self.statement(statement);
+        }
+        self.indent -= 1;
+        self.newline();
+        self.write("}");
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Block(statements) => self.block(statements),
+            Statement::Declaration(nodes) => self.declaration(nodes),
+            Statement::Expression(expr) => {
+                self.statement_start = true;
+                self.expression(expr, COMMA);
+                self.write(";");
+            }
+            Statement::If(s) => {
+                self.write("if ");
+                self.condition(&s.condition);
+                self.write(" ");
+                match &s.otherwise {
+                    // without braces the else would belong to the inner if
+                    Some(_) if has_open_if(&s.body) => {
+                        self.block(std::slice::from_ref(&s.body));
+                    }
+                    _ => self.statement(&s.body),
+                }
+                if let Some(otherwise) = &s.otherwise {
+                    self.after_body(&s.body);
+                    self.write("else ");
+                    self.statement(otherwise);
+                }
+            }
+            Statement::While(s) => {
+                self.write("while ");
+                self.condition(&s.condition);
+                self.write(" ");
+                self.statement(&s.body);
+            }
+            Statement::DoWhile(s) => {
+                self.write("do ");
+                self.statement(&s.body);
+                self.after_body(&s.body);
+                self.write("while ");
+                self.condition(&s.condition);
+                self.write(";");
+            }
+            Statement::For(s) => {
+                self.write("for (");
+                self.with_tags(true, |p| {
+                    match s.initialiser.as_deref() {
+                        Some(initialiser) => p.statement(initialiser),
+                        None => p.write(";"),
+                    }
+                    if let Some(condition) = &s.condition {
+                        p.write(" ");
+                        p.expression(condition, COMMA);
+                    }
+                    p.write(";");
+                    if let Some(increment) = &s.increment {
+                        p.write(" ");
+                        p.expression(increment, COMMA);
+                    }
+                });
+                self.write(") ");
+                self.statement(&s.body);
+            }
+            Statement::Switch(s) => {
+                self.write("switch ");
+                self.condition(&s.subject);
+                self.write(" {");
+                self.indent += 1;
+                for case in &s.cases {
+                    self.newline();
+                    self.write("case ");
+                    self.with_tags(false, |p| {
+                        for (i, label) in case.labels.iter().enumerate() {
+                            if i > 0 {
+                                p.write(", ");
+                            }
+                            match label {
+                                CaseLabel::Value(value) => p.expression(value, BINARY),
+                                CaseLabel::Range(from, to) => {
+                                    p.expression(from, BINARY);
+                                    p.write("..");
+                                    p.expression(to, BINARY);
+                                }
+                            }
+                        }
+                    });
+                    self.write(": ");
+                    self.statement(&case.body);
+                }
+                if let Some(default) = &s.default {
+                    self.newline();
+                    self.write("default: ");
+                    self.statement(default);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.write("}");
+            }
+            Statement::State(s) => {
+                self.write("state ");
+                if let Some(condition) = &s.condition {
+                    self.condition(condition);
+                    self.write(" ");
+                }
+                if let Some(automaton) = &s.automaton {
+                    self.write(automaton);
+                    self.write(":");
+                }
+                self.write(&s.state);
+                self.write(";");
+            }
+            Statement::Label(label) => {
+                self.write(label);
+                self.write(":");
+            }
+            Statement::Goto(label) => {
+                self.write("goto ");
+                self.write(label);
+                self.write(";");
+            }
+            Statement::Return(value) => {
+                self.write("return");
+                if let Some(value) = value {
+                    self.write(" ");
+                    self.expression(value, ASSIGNMENT);
+                }
+                self.write(";");
+            }
+            Statement::Break => self.write("break;"),
+            Statement::Continue => self.write("continue;"),
+            Statement::Empty => self.write(";"),
+            Statement::Error => {}
+        }
+    }
+
+    // after_body separates the body of an if or do from the keyword that
+    // follows it, on the same line as a closing brace or else on the next.
+    fn after_body(&mut self, body: &Statement) {
+        if matches!(body, Statement::Block(_)) {
+            self.write(" ");
+        } else {
+            self.newline();
+        }
+    }
+
+    fn condition(&mut self, condition: &Expression) {
+        self.write("(");
+        self.with_tags(true, |p| p.expression(condition, COMMA));
+        self.write(")");
+    }
+
+    // expression writes an expression, bracketed if it binds looser than
+    // `min` or would otherwise be read as something else.
+    fn expression(&mut self, expr: &Expression, min: u8) {
+        let brackets = expression_precedence(expr) < min
+            || match expr {
+                Expression::TagOverride(_) => !self.allow_tags || self.statement_start,
+                Expression::Literal(Literal::Array(_)) => self.statement_start,
+                _ => false,
+            };
+        if brackets {
+            self.write("(");
+            self.with_tags(true, |p| p.expression(expr, COMMA));
+            self.write(")");
+            return;
+        }
+
+        match expr {
+            Expression::BinaryOperation(op) => {
+                let operator = op.operator.token_type;
+                let precedence = expression_precedence(expr);
+                // assignment is right associative, everything else is left
+                let (left, right) = match precedence {
+                    ASSIGNMENT => (TERNARY, ASSIGNMENT),
+                    COMMA => (COMMA, ASSIGNMENT),
+                    p => (p, p + 1),
+                };
+                self.expression(&op.left, left);
+                if operator == TokenType::Comma {
+                    self.write(", ");
+                } else {
+                    self.write(&format!(" {} ", operator));
+                }
+                self.expression(&op.right, right);
+            }
+            Expression::UnaryOperation(op) if op.postfix => {
+                self.expression(&op.operand, POSTFIX);
+                if op.operator.token_type == TokenType::Char {
+                    self.write(" ");
+                }
+                self.write(&op.operator.token_type.to_string());
+            }
+            // `tagof` is always written with brackets, which a tag on its own
+            // needs, and `sizeof` and `defined` the same way
+            Expression::UnaryOperation(op)
+                if matches!(
+                    op.operator.token_type,
+                    TokenType::Tagof | TokenType::Sizeof | TokenType::Defined
+                ) =>
+            {
+                self.write(&op.operator.token_type.to_string());
+                self.write("(");
+                self.with_tags(true, |p| p.expression(&op.operand, COMMA));
+                self.write(")");
+            }
+            Expression::UnaryOperation(op) => {
+                self.write(&op.operator.token_type.to_string());
+                // `- -a` must not become `--a`, nor `! "a"` a packed string
+                if starts_with_minus(&op.operand)
+                    || (op.operator.token_type == TokenType::Bang
+                        && starts_with_string(&op.operand))
+                {
+                    self.write(" ");
+                }
+                self.expression(&op.operand, UNARY);
+            }
+            Expression::TernaryOperation(op) => {
+                self.expression(&op.condition, BINARY);
+                self.write(" ? ");
+                self.with_tags(false, |p| p.expression(&op.left, TERNARY));
+                self.write(" : ");
+                self.expression(&op.right, TERNARY);
+            }
+            Expression::TagOverride(tag) => {
+                self.write(&tag.tag);
+                self.write(":");
+                self.expression(&tag.operand, UNARY);
+            }
+            Expression::Call(call) => {
+                self.expression(&call.function, POSTFIX);
+                self.write("(");
+                self.with_tags(true, |p| {
+                    for (i, argument) in call.arguments.iter().enumerate() {
+                        if i > 0 {
+                            p.write(", ");
+                        }
+                        match &argument.node {
+                            Argument::Positional(value) => p.expression(value, ASSIGNMENT),
+                            Argument::Named { name, value } => {
+                                p.write(".");
+                                p.write(name);
+                                p.write(" = ");
+                                p.expression(value, ASSIGNMENT);
+                            }
+                            Argument::Skipped => p.write("_"),
+                        }
+                    }
+                });
+                self.write(")");
+            }
+            Expression::Index(index) => {
+                self.expression(&index.array, POSTFIX);
+                self.write("[");
+                self.with_tags(true, |p| p.expression(&index.index, ASSIGNMENT));
+                self.write("]");
+            }
+            Expression::Selector(array) => {
+                self.expression(array, POSTFIX);
+                self.write("[]");
+            }
+            Expression::Symbol(name) => self.write(name),
+            Expression::Tag(tag) => {
+                self.write(tag);
+                self.write(":");
+            }
+            Expression::Literal(literal) => self.literal(literal),
+            Expression::Variable(variable) => self.variable(variable),
+            Expression::Function(function) => self.function(function),
+            Expression::Enum(e) => self.enumeration(e),
+            Expression::GlobalScope | Expression::Error => {}
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Integer(value) => self.write(&value.to_string()),
+            Literal::Float(value) => self.write(&float(*value)),
+            Literal::Bool(value) => self.write(if *value { "true" } else { "false" }),
+            Literal::Char(c) => self.write(&character(*c)),
+            Literal::String(string) => {
+                if string.packed {
+                    self.write("!");
+                }
+                for (i, part) in string.parts.iter().enumerate() {
+                    if i > 0 {
+                        self.write(" ");
+                    }
+                    self.write("\"");
+                    self.write(part);
+                    self.write("\"");
+                }
+            }
+            Literal::Array(array) => {
+                self.write("{");
+                self.with_tags(true, |p| {
+                    for (i, element) in array.elements.iter().enumerate() {
+                        if i > 0 {
+                            p.write(", ");
+                        }
+                        p.expression(element, ASSIGNMENT);
+                    }
+                });
+                if array.ellipsis {
+                    self.write(if array.elements.is_empty() {
+                        "..."
+                    } else {
+                        ", ..."
+                    });
+                }
+                self.write("}");
+            }
+        }
+    }
+}
+
+// expression_precedence returns how tightly an expression binds, which
+// decides whether it needs brackets as the operand of another.
+fn expression_precedence(expr: &Expression) -> u8 {
+    match expr {
+        Expression::BinaryOperation(op) => match op.operator.token_type {
+            TokenType::Comma => COMMA,
+            t if is_assignment(t) => ASSIGNMENT,
+            t => binary_precedence(t).map_or(BINARY, |p| BINARY + p - 1),
+        },
+        Expression::TernaryOperation(_) => TERNARY,
+        Expression::UnaryOperation(op) if op.postfix => POSTFIX,
+        Expression::UnaryOperation(_) | Expression::TagOverride(_) => UNARY,
+        Expression::Call(_) | Expression::Index(_) | Expression::Selector(_) => POSTFIX,
+        _ => PRIMARY,
+    }
+}
+
+fn starts_with_minus(expr: &Expression) -> bool {
+    match expr {
+        Expression::UnaryOperation(op) if !op.postfix => matches!(
+            op.operator.token_type,
+            TokenType::Minus | TokenType::MinusMinus
+        ),
+        Expression::Literal(Literal::Integer(value)) => *value < 0,
+        Expression::Literal(Literal::Float(value)) => value.is_sign_negative(),
+        _ => false,
+    }
+}
+
+// starts_with_string reports whether an expression is written starting with
+// an unpacked string, such as `"abc"[0]`.
+fn starts_with_string(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal(Literal::String(string)) => !string.packed,
+        Expression::UnaryOperation(op) if op.postfix => starts_with_string(&op.operand),
+        Expression::Call(call) => starts_with_string(&call.function),
+        Expression::Index(index) => starts_with_string(&index.array),
+        _ => false,
+    }
+}
+
+// character writes a character literal, escaping the characters that can not
+// be written as they are.
+fn character(c: char) -> String {
+    match c {
+        '\'' => String::from("'\\''"),
+        '\\' => String::from("'\\\\'"),
+        '\n' => String::from("'\\n'"),
+        '\r' => String::from("'\\r'"),
+        '\t' => String::from("'\\t'"),
+        c if c.is_control() => format!("'\\x{:x};'", c as u32),
+        c => format!("'{}'", c),
+    }
+}
+
+// has_open_if reports whether a statement ends with an if that has no else,
+// which would take an else written after it.
+fn has_open_if(statement: &Statement) -> bool {
+    match statement {
+        Statement::If(s) => match &s.otherwise {
+            Some(otherwise) => has_open_if(otherwise),
+            None => true,
+        },
+        Statement::While(s) => has_open_if(&s.body),
+        Statement::For(s) => has_open_if(&s.body),
+        _ => false,
+    }
+}
+
+fn is_item(expr: &Expression) -> bool {
+    matches!(expr, Expression::Function(_) | Expression::Enum(_))
+}
+
+// storage_class returns the keywords a variable is declared with before any
+// `const`, a stock variable needs no `new`.
+fn storage_class(variable: &Variable) -> &'static str {
+    match (variable.storage, variable.is_stock) {
+        (StorageClass::New, false) => "new",
+        (StorageClass::New, true) => "stock",
+        (StorageClass::Static, false) => "static",
+        (StorageClass::Static, true) => "static stock",
+        (StorageClass::Public, false) => "public",
+        (StorageClass::Public, true) => "public stock",
+        (StorageClass::Const, _) => "const",
+    }
+}
+
+// float writes a float so that it is lexed as a float again, `1` would be
+// read as an integer.
+fn float(value: f32) -> String {
+    let s = value.to_string();
+    if s.contains('.') {
+        s
+    } else {
+        format!("{}.0", s)
+    }
+}
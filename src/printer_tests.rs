@@ -0,0 +1,554 @@
+#[cfg(test)]
+use proptest::prelude::*;
+
+#[cfg(test)]
+use crate::ast::{
+    Argument, Array, BinaryOperation, Call, Case, CaseLabel, DoWhile, Enum, Expression, For,
+    Function, FunctionKind, If, Index, Literal, Node, Spanned, Statement, StorageClass,
+    StringLiteral, Switch, TagOverride, TernaryOperation, UnaryOperation, Variable, While,
+};
+#[cfg(test)]
+use crate::fold::{self, Fold};
+#[cfg(test)]
+use crate::printer::{print, print_expression, print_statement};
+#[cfg(test)]
+use crate::test_utils;
+#[cfg(test)]
+use crate::token::{Token, TokenType};
+
+// Normalise clears the positions held by operator tokens, spans are already
+// ignored when comparing trees.
+#[cfg(test)]
+struct Normalise;
+
+#[cfg(test)]
+fn token(token_type: TokenType) -> Token {
+    Token {
+        token_type,
+        value: None,
+        line_start: 0,
+        line_end: 0,
+        column_start: 0,
+        column_end: 0,
+    }
+}
+
+#[cfg(test)]
+impl Fold for Normalise {
+    fn fold_binary_operation(&mut self, op: BinaryOperation) -> BinaryOperation {
+        let mut op = fold::walk_binary_operation(self, op);
+        op.operator = token(op.operator.token_type);
+        op
+    }
+
+    fn fold_unary_operation(&mut self, op: UnaryOperation) -> UnaryOperation {
+        let mut op = fold::walk_unary_operation(self, op);
+        op.operator = token(op.operator.token_type);
+        op
+    }
+
+    fn fold_function(&mut self, function: Function) -> Function {
+        let function = fold::walk_function(self, function);
+        Function {
+            operator: function.operator.map(|o| token(o.token_type)),
+            ..function
+        }
+    }
+
+    fn fold_enum(&mut self, e: Enum) -> Enum {
+        let mut e = fold::walk_enum(self, e);
+        if let Some(increment) = &mut e.increment {
+            increment.operator = token(increment.operator.token_type);
+        }
+        e
+    }
+}
+
+// parse parses a file and normalises it, so that it compares equal to the
+// tree of its printed form.
+#[cfg(test)]
+fn parse(source: &str) -> Node {
+    Normalise.fold_node(test_utils::parse(source))
+}
+
+// reprint parses an initialiser and prints it again.
+#[cfg(test)]
+fn reprint(expr: &str) -> String {
+    let root = parse(&format!("new x = {};", expr));
+    match &root.children[0].expr {
+        Expression::Variable(v) => print_expression(&v.initialiser.as_ref().unwrap().node),
+        e => panic!("expected variable, got {:?}", e),
+    }
+}
+
+#[test]
+fn test_minimal_brackets() {
+    let cases = [
+        ("a + b * c", "a + b * c"),
+        ("(a + b) * c", "(a + b) * c"),
+        ("((a * b)) + c", "a * b + c"),
+        ("a - (b - c)", "a - (b - c)"),
+        ("(a - b) - c", "a - b - c"),
+        ("a = b = c", "a = b = c"),
+        ("(a = b) + c", "(a = b) + c"),
+        ("a ? b : c ? d : e", "a ? b : c ? d : e"),
+        ("(a ? b : c) ? d : e", "(a ? b : c) ? d : e"),
+        ("-(a + b)", "-(a + b)"),
+        ("-(-a)", "- -a"),
+        ("(-a)++", "(-a)++"),
+        ("-a++", "-a++"),
+        ("(a || b) && c", "(a || b) && c"),
+        ("a | b ^ c & d", "a | b ^ c & d"),
+        ("(a & b) == c", "a & b == c"),
+        ("a & (b == c)", "a & (b == c)"),
+        ("(a << 2) + 1", "(a << 2) + 1"),
+        ("Float:(a + b)", "Float:(a + b)"),
+        ("c ? (bool:x) : false", "c ? (bool:x) : false"),
+        ("c ? f(bool:x) : Float:y", "c ? f(bool:x) : Float:y"),
+        ("(f)(a, _, .b = (c))", "f(a, _, .b = c)"),
+        ("a[(b)][c + 1]", "a[b][c + 1]"),
+        ("{1, {2, 3}, ...}", "{1, {2, 3}, ...}"),
+        ("10 char", "10 char"),
+        ("1.0 + 2.5", "1.0 + 2.5"),
+        ("!\"abc\" ... \"d\"", "!\"abc\" \"d\""),
+        ("!(\"abc\")", "! \"abc\""),
+        ("!!\"abc\"[0]", "!!\"abc\"[0]"),
+        ("'\\'' + '\\x1;'", "'\\'' + '\\x1;'"),
+        ("true || false", "true || false"),
+        ("tagof(Float:) + tagof a", "tagof(Float:) + tagof(a)"),
+        ("tagof(Float:a)", "tagof(Float:a)"),
+        ("sizeof a + sizeof(b[0])", "sizeof(a) + sizeof(b[0])"),
+        (
+            "sizeof a[] + sizeof(b[0][])",
+            "sizeof(a[]) + sizeof(b[0][])",
+        ),
+        ("defined a || !defined(b)", "defined(a) || !defined(b)"),
+    ];
+    for (source, expected) in cases.iter() {
+        assert_eq!(reprint(source), *expected, "{}", source);
+    }
+}
+
+#[test]
+fn test_statement_brackets() {
+    let root = parse("main() { (_:a) = 5; ({1, 2}); if (a) { if (b) c(); } else d(); }");
+    let body = match &root.children[0].expr {
+        Expression::Function(f) => f.body.as_ref().unwrap(),
+        e => panic!("expected function, got {:?}", e),
+    };
+    let printed: Vec<String> = body.iter().map(|s| print_statement(s)).collect();
+    assert_eq!(
+        printed,
+        vec![
+            "(_:a) = 5;",
+            "({1, 2});",
+            "if (a) {\n    if (b) c();\n} else d();",
+        ]
+    );
+}
+
+#[test]
+fn test_print_file() {
+    let source = "\
+#include <a_samp>
+
+enum E_PLAYER {
+    E_NAME[MAX_PLAYER_NAME],
+    Float:E_HEALTH = 100
+}
+
+new gPlayers[MAX_PLAYERS][E_PLAYER];
+static const Float:gScale = 2.0;
+stock static gCache[4];
+new stock const gOther;
+
+native Float:operator*(Float:oper1, Float:oper2) = floatmul;
+forward OnTick();
+
+public OnTick() <auto:idle, busy> {
+    for (new i = 0, j = 10; i < j; i++, j--) {
+        if (i % 2 == 0) continue;
+        Update(i, .force = true, _);
+    }
+    switch (gState) {
+        case 1, 2, 5..10: gState = 0;
+        case IDLE: {
+            state (IsReady()) auto:busy;
+        }
+        default: return;
+    }
+retry:
+    do {
+        gState++;
+    } while (gState < 3);
+    goto retry;
+}
+
+stock Half(Float:value, &{Float, _}:out, const name[] = \"x\", ...) return value / 2;
+";
+    let root = parse(source);
+    let printed = print(&root);
+    assert_eq!(parse(&printed), root, "{}", printed);
+
+    // printing is stable once the layout has been normalised
+    assert_eq!(print(&parse(&printed)), printed);
+    assert!(printed.starts_with("enum E_PLAYER {\n    E_NAME[MAX_PLAYER_NAME],\n"));
+    assert!(printed.contains("\nstatic stock gCache[4];\nstock const gOther;\n"));
+    assert!(printed.contains("\nstock Half(Float:value, &{Float, _}:out, const name[] = \"x\", ...) {\n    return value / 2;\n}\n"));
+}
+
+// strategies for generating trees that the parser can produce, so that the
+// printed source must parse back to exactly the same tree.
+
+#[cfg(test)]
+fn spanned<T>(node: T) -> Spanned<T> {
+    Spanned::from(node)
+}
+
+#[cfg(test)]
+fn symbol() -> impl Strategy<Value = String> {
+    prop::sample::select(vec!["a", "b", "foo", "Bar_1", "x2"]).prop_map(String::from)
+}
+
+#[cfg(test)]
+fn tag() -> impl Strategy<Value = String> {
+    prop::sample::select(vec!["Float", "bool", "_"]).prop_map(String::from)
+}
+
+#[cfg(test)]
+fn leaf() -> impl Strategy<Value = Expression> {
+    prop_oneof![
+        symbol().prop_map(Expression::Symbol),
+        (0..1000i32).prop_map(|v| Expression::Literal(Literal::Integer(v))),
+        prop::sample::select(vec![0.5f32, 1.0, 2.25, 100.0])
+            .prop_map(|v| Expression::Literal(Literal::Float(v))),
+        any::<bool>().prop_map(|v| Expression::Literal(Literal::Bool(v))),
+        prop::sample::select(vec!['a', ' ', '\'', '\\', '\n', '\u{1}', 'é'])
+            .prop_map(|c| Expression::Literal(Literal::Char(c))),
+        string().prop_map(|s| Expression::Literal(Literal::String(s))),
+    ]
+}
+
+#[cfg(test)]
+fn string() -> impl Strategy<Value = StringLiteral> {
+    let part = prop::sample::select(vec!["", "hello", "a \\\"b\\\"", "\\x41;"]);
+    (prop::collection::vec(part, 1..3), any::<bool>()).prop_map(|(parts, packed)| StringLiteral {
+        packed,
+        parts: parts
+            .into_iter()
+            .map(|p| Spanned::from(String::from(p)))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+const BINARY_OPERATORS: [TokenType; 22] = [
+    TokenType::Or,
+    TokenType::And,
+    TokenType::BitOr,
+    TokenType::BitXor,
+    TokenType::BitAnd,
+    TokenType::Equal,
+    TokenType::NotEqual,
+    TokenType::LowerThan,
+    TokenType::LowerThanEqual,
+    TokenType::GreaterThan,
+    TokenType::GreaterThanEqual,
+    TokenType::BitLeft,
+    TokenType::BitRight,
+    TokenType::BitRightLogical,
+    TokenType::Plus,
+    TokenType::Minus,
+    TokenType::Asterisk,
+    TokenType::Slash,
+    TokenType::Percent,
+    TokenType::Assign,
+    TokenType::PlusAssign,
+    TokenType::BitRightLogicalAssign,
+];
+
+#[cfg(test)]
+fn boxed(expr: Expression) -> Box<Spanned<Expression>> {
+    Box::new(spanned(expr))
+}
+
+#[cfg(test)]
+fn expression() -> impl Strategy<Value = Expression> {
+    leaf().prop_recursive(5, 48, 4, |inner| {
+        prop_oneof![
+            (
+                prop::sample::select(BINARY_OPERATORS.to_vec()),
+                inner.clone(),
+                inner.clone()
+            )
+                .prop_map(|(operator, left, right)| {
+                    Expression::BinaryOperation(Box::new(BinaryOperation {
+                        operator: token(operator),
+                        left: boxed(left),
+                        right: boxed(right),
+                    }))
+                }),
+            (
+                prop::sample::select(vec![
+                    TokenType::Minus,
+                    TokenType::Bang,
+                    TokenType::Tilde,
+                    TokenType::PlusPlus,
+                    TokenType::MinusMinus,
+                ]),
+                inner.clone()
+            )
+                .prop_map(|(operator, operand)| {
+                    Expression::UnaryOperation(Box::new(UnaryOperation {
+                        operator: token(operator),
+                        operand: boxed(operand),
+                        postfix: false,
+                    }))
+                }),
+            (
+                prop::sample::select(vec![TokenType::PlusPlus, TokenType::MinusMinus]),
+                inner.clone()
+            )
+                .prop_map(|(operator, operand)| {
+                    Expression::UnaryOperation(Box::new(UnaryOperation {
+                        operator: token(operator),
+                        operand: boxed(operand),
+                        postfix: true,
+                    }))
+                }),
+            (inner.clone(), inner.clone(), inner.clone()).prop_map(|(condition, left, right)| {
+                Expression::TernaryOperation(Box::new(TernaryOperation {
+                    condition: boxed(condition),
+                    left: boxed(left),
+                    right: boxed(right),
+                }))
+            }),
+            (tag(), inner.clone()).prop_map(|(tag, operand)| {
+                Expression::TagOverride(Box::new(TagOverride {
+                    tag,
+                    operand: boxed(operand),
+                }))
+            }),
+            (
+                symbol(),
+                prop::collection::vec(
+                    prop_oneof![
+                        inner.clone().prop_map(|e| Argument::Positional(spanned(e))),
+                        (symbol(), inner.clone()).prop_map(|(name, value)| Argument::Named {
+                            name,
+                            value: spanned(value),
+                        }),
+                        Just(()).prop_map(|_| Argument::Skipped),
+                    ],
+                    0..3
+                )
+            )
+                .prop_map(|(function, arguments)| {
+                    Expression::Call(Box::new(Call {
+                        function: boxed(Expression::Symbol(function)),
+                        arguments: arguments.into_iter().map(spanned).collect(),
+                    }))
+                }),
+            (inner.clone(), inner.clone()).prop_map(|(array, index)| {
+                Expression::Index(Box::new(Index {
+                    array: boxed(array),
+                    index: boxed(index),
+                }))
+            }),
+            (prop::collection::vec(inner, 0..3), any::<bool>()).prop_map(|(elements, ellipsis)| {
+                Expression::Literal(Literal::Array(Box::new(Array {
+                    elements: elements.into_iter().map(spanned).collect(),
+                    ellipsis,
+                })))
+            }),
+        ]
+    })
+}
+
+// comma_expression is only generated where the parser allows the comma
+// operator, it can not be bracketed.
+#[cfg(test)]
+fn comma_expression() -> impl Strategy<Value = Expression> {
+    prop::collection::vec(expression(), 1..3).prop_map(|expressions| {
+        let mut expressions = expressions.into_iter();
+        let first = expressions.next().unwrap();
+        expressions.fold(first, |left, right| {
+            Expression::BinaryOperation(Box::new(BinaryOperation {
+                operator: token(TokenType::Comma),
+                left: boxed(left),
+                right: boxed(right),
+            }))
+        })
+    })
+}
+
+#[cfg(test)]
+fn declaration() -> impl Strategy<Value = Statement> {
+    prop::collection::vec((symbol(), tag(), prop::option::of(expression())), 1..3).prop_map(
+        |variables| {
+            Statement::Declaration(
+                variables
+                    .into_iter()
+                    .map(|(name, tag, initialiser)| {
+                        Node::new(Expression::Variable(Box::new(Variable {
+                            name,
+                            tag,
+                            dimensions: vec![],
+                            initialiser: initialiser.map(Spanned::from),
+                            storage: StorageClass::New,
+                            is_stock: false,
+                            is_const: false,
+                        })))
+                    })
+                    .collect(),
+            )
+        },
+    )
+}
+
+// has_open_if mirrors the printer, an if with an else needs a body that
+// does not end in an if without one.
+#[cfg(test)]
+fn has_open_if(statement: &Statement) -> bool {
+    match statement {
+        Statement::If(s) => s.otherwise.as_ref().is_none_or(|o| has_open_if(o)),
+        Statement::While(s) => has_open_if(&s.body),
+        Statement::For(s) => has_open_if(&s.body),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+fn statement() -> impl Strategy<Value = Statement> {
+    let leaf = prop_oneof![
+        comma_expression().prop_map(|e| Statement::Expression(spanned(e))),
+        prop::option::of(expression()).prop_map(|e| Statement::Return(e.map(spanned))),
+        declaration(),
+        symbol().prop_map(Statement::Goto),
+        symbol().prop_map(Statement::Label),
+        Just(()).prop_map(|_| Statement::Break),
+        Just(()).prop_map(|_| Statement::Continue),
+        Just(()).prop_map(|_| Statement::Empty),
+    ];
+    leaf.prop_recursive(3, 24, 3, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..3)
+                .prop_map(|body| Statement::Block(body.into_iter().map(spanned).collect())),
+            (
+                comma_expression(),
+                inner.clone(),
+                prop::option::of(inner.clone())
+            )
+                .prop_map(|(condition, body, otherwise)| {
+                    let body = if otherwise.is_some() && has_open_if(&body) {
+                        Statement::Block(vec![spanned(body)])
+                    } else {
+                        body
+                    };
+                    Statement::If(Box::new(If {
+                        condition: spanned(condition),
+                        body: spanned(body),
+                        otherwise: otherwise.map(spanned),
+                    }))
+                }),
+            (comma_expression(), inner.clone()).prop_map(|(condition, body)| {
+                Statement::While(Box::new(While {
+                    condition: spanned(condition),
+                    body: spanned(body),
+                }))
+            }),
+            (inner.clone(), comma_expression()).prop_map(|(body, condition)| {
+                Statement::DoWhile(Box::new(DoWhile {
+                    body: spanned(body),
+                    condition: spanned(condition),
+                }))
+            }),
+            (
+                prop::option::of(prop_oneof![
+                    declaration(),
+                    comma_expression().prop_map(|e| Statement::Expression(spanned(e))),
+                ]),
+                prop::option::of(comma_expression()),
+                prop::option::of(comma_expression()),
+                inner.clone()
+            )
+                .prop_map(|(initialiser, condition, increment, body)| {
+                    Statement::For(Box::new(For {
+                        initialiser: initialiser.map(spanned),
+                        condition: condition.map(spanned),
+                        increment: increment.map(spanned),
+                        body: spanned(body),
+                    }))
+                }),
+            (
+                comma_expression(),
+                prop::collection::vec(
+                    (
+                        prop::collection::vec(
+                            prop_oneof![
+                                expression().prop_map(|e| CaseLabel::Value(spanned(e))),
+                                (expression(), expression()).prop_map(|(from, to)| {
+                                    CaseLabel::Range(spanned(from), spanned(to))
+                                }),
+                            ],
+                            1..3
+                        ),
+                        inner.clone()
+                    ),
+                    0..3
+                ),
+                prop::option::of(inner)
+            )
+                .prop_map(|(subject, cases, default)| {
+                    Statement::Switch(Box::new(Switch {
+                        subject: spanned(subject),
+                        cases: cases
+                            .into_iter()
+                            .map(|(labels, body)| {
+                                spanned(Case {
+                                    labels,
+                                    body: spanned(body),
+                                })
+                            })
+                            .collect(),
+                        default: default.map(spanned),
+                    }))
+                }),
+        ]
+    })
+}
+
+#[cfg(test)]
+fn file(body: Vec<Statement>) -> Node {
+    let mut root = Node::new(Expression::GlobalScope);
+    root.children
+        .push(Node::new(Expression::Function(Box::new(Function {
+            kind: FunctionKind::Definition,
+            is_public: false,
+            is_stock: false,
+            is_static: false,
+            name: "main".into(),
+            tag: "_".into(),
+            parameters: vec![],
+            body: Some(body.into_iter().map(spanned).collect()),
+            native_alias: None,
+            states: None,
+            operator: None,
+        }))));
+    root
+}
+
+#[cfg(test)]
+proptest! {
+    #[test]
+    fn test_round_trip(body in prop::collection::vec(statement(), 0..4)) {
+        let generated = file(body);
+        let source = print(&generated);
+
+        let parsed = parse(&source);
+        prop_assert_eq!(&parsed, &generated, "{}", source);
+
+        let printed = print(&parsed);
+        prop_assert_eq!(parse(&printed), parsed, "{}", printed);
+    }
+}
@@ -0,0 +1,69 @@
+// Snapshot tests for error recovery. Each `.pwn` file in testdata/recovery is
+// parsed and the diagnostics along with the recovered top-level nodes are
+// compared against the `.snap` file next to it. Run the tests with
+// `UPDATE_SNAPSHOTS=1` to rewrite the snapshots after an intended change.
+
+#[cfg(test)]
+use std::fs;
+#[cfg(test)]
+use std::path::Path;
+
+#[cfg(test)]
+use crate::ast::{Expression, Node, Parser};
+#[cfg(test)]
+use crate::lexer::Lexer;
+
+#[cfg(test)]
+fn describe(node: &Node) -> String {
+    match &node.expr {
+        Expression::Variable(v) => format!("variable {}", v.name),
+        Expression::Function(f) => format!("function {}", f.name),
+        Expression::Enum(e) => format!("enum {}", e.name.as_deref().unwrap_or("_")),
+        Expression::Error => String::from("error"),
+        e => format!("{:?}", e),
+    }
+}
+
+#[cfg(test)]
+fn render(source: &str) -> String {
+    let mut p = Parser::new(Lexer::new(source).lex());
+    let diagnostics = p.parse().err().unwrap_or_default();
+
+    let mut out = String::from("diagnostics:\n");
+    for d in &diagnostics {
+        out.push_str(&format!("  {}\n", d));
+    }
+    out.push_str("nodes:\n");
+    for node in &p.root.children {
+        out.push_str(&format!("  {}\n", describe(node)));
+    }
+    out
+}
+
+#[test]
+fn recovery_snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/recovery");
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+
+    let mut sources: Vec<_> = fs::read_dir(&dir)
+        .expect("failed to read testdata/recovery")
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "pwn"))
+        .collect();
+    sources.sort();
+    assert!(!sources.is_empty());
+
+    for path in sources {
+        let actual = render(&fs::read_to_string(&path).unwrap());
+        let snapshot = path.with_extension("snap");
+
+        if update {
+            fs::write(&snapshot, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&snapshot)
+            .unwrap_or_else(|_| panic!("missing snapshot {}", snapshot.display()));
+        assert_eq!(actual, expected, "snapshot mismatch for {}", path.display());
+    }
+}
@@ -0,0 +1,855 @@
+use crate::arena::Ast;
+use crate::arena::Event;
+use crate::arena::NodeId;
+use crate::arena::NodeKind;
+use crate::arena::Walk;
+use crate::ast::FunctionKind;
+use crate::ast::StateList;
+use crate::ast::StorageClass;
+use crate::error::Diagnostic;
+use crate::error::ParseError;
+use crate::intern::Interner;
+use crate::intern::Symbol;
+use crate::token::Position;
+use crate::token::Span;
+use crate::token::TokenType;
+
+use std::collections::HashMap;
+use std::ops;
+
+/// PREDEFINED are the constants pawncc defines before reading any source.
+const PREDEFINED: &[&str] = &[
+    "cellbits", "cellmax", "cellmin", "charbits", "charmax", "charmin", "ucharmax", "__Pawn",
+    "__line", "debug",
+];
+
+/// SymbolId identifies a declaration in a `SymbolTable`, ids are handed out
+/// in the order names are declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(u32);
+
+impl SymbolId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// ScopeId identifies a scope in a `SymbolTable`, the global scope is always
+/// the first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ScopeId(u32);
+
+impl ScopeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// SymbolKind is what a name was declared as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// a constant the compiler defines such as `cellbits`, or a name given to
+    /// `Resolver::predefine`.
+    Predefined,
+    Variable,
+    /// a symbolic constant declared with `const`.
+    Constant,
+    Function,
+    Native,
+    Parameter,
+    /// the name of an enum, which is also a constant holding its size.
+    Enum,
+    EnumMember,
+    Label,
+    /// a state of an automaton, named in the state list of a function.
+    State,
+}
+
+/// Declaration is a declared name. Names have no node of their own so the
+/// node is what declares it, such as a variable along with its initialiser,
+/// in the file given by its position in the files resolved. A state is
+/// declared by the first function naming it. Predefined names are in no file
+/// and have no node. The tag is the one the name was declared with, `_` if it
+/// has none. A parameter with a list of tags has the first, and the members
+/// of an enum without a tag of their own have the tag of the enum. The name
+/// is a symbol of the interner given to `Resolver::resolve`, that of a state
+/// is its name as written, `automaton:state` or just `state`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: Symbol,
+    pub kind: SymbolKind,
+    pub tag: String,
+    pub file: Option<usize>,
+    pub node: Option<NodeId>,
+    pub span: Span,
+    pub scope: ScopeId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind {
+    /// Global holds the predefined constants, states and everything declared
+    /// at the top level of a file that is not `static`.
+    Global,
+    /// File holds the `static` declarations at the top level of one file.
+    File,
+    /// Function holds the parameters, labels and outermost locals of a
+    /// function.
+    Function,
+    /// Block holds the locals of a compound statement or a `for` loop.
+    Block,
+}
+
+/// Scope is a region of source in which declared names are visible. Labels
+/// are kept apart from other names since only `goto` refers to them.
+#[derive(Debug)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    pub span: Span,
+    names: HashMap<Symbol, SymbolId>,
+    labels: HashMap<Symbol, SymbolId>,
+}
+
+impl Scope {
+    fn new(kind: ScopeKind, parent: Option<ScopeId>, span: Span) -> Scope {
+        Scope {
+            kind,
+            parent,
+            span,
+            names: HashMap::new(),
+            labels: HashMap::new(),
+        }
+    }
+
+    /// get returns the name declared in this scope itself, not counting the
+    /// scopes around it.
+    pub fn get(&self, name: Symbol) -> Option<SymbolId> {
+        self.names.get(&name).copied()
+    }
+
+    pub fn label(&self, name: Symbol) -> Option<SymbolId> {
+        self.labels.get(&name).copied()
+    }
+}
+
+/// Reference is a use of a name along with the declaration it refers to and
+/// the scope it is used from. Like a declaration, its node is in the file
+/// given by its position in the files resolved. The node of a named argument
+/// is the `Argument`, and a state named again in the state list of a
+/// function refers to it from the `Function`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub name: Symbol,
+    pub file: usize,
+    pub node: NodeId,
+    pub span: Span,
+    pub symbol: SymbolId,
+    pub scope: ScopeId,
+}
+
+/// SymbolTable holds every scope and declaration found by a `Resolver`, and
+/// every use of a name that could be resolved.
+#[derive(Debug)]
+pub struct SymbolTable {
+    scopes: Vec<Scope>,
+    symbols: Vec<Declaration>,
+    references: Vec<Reference>,
+    files: Vec<ScopeId>,
+    // states are global but named by automaton, see state_key.
+    states: HashMap<String, SymbolId>,
+}
+
+impl SymbolTable {
+    fn new() -> SymbolTable {
+        SymbolTable {
+            scopes: vec![Scope::new(ScopeKind::Global, None, Span::default())],
+            symbols: vec![],
+            references: vec![],
+            files: vec![],
+            states: HashMap::new(),
+        }
+    }
+
+    pub fn global(&self) -> ScopeId {
+        ScopeId(0)
+    }
+
+    /// file returns the scope of a file, by its position in the files given
+    /// to `Resolver::resolve`.
+    pub fn file(&self, index: usize) -> ScopeId {
+        self.files[index]
+    }
+
+    /// file_of returns the position of the file a scope is in, `None` for the
+    /// global scope.
+    pub fn file_of(&self, scope: ScopeId) -> Option<usize> {
+        let mut scope = Some(scope);
+        while let Some(id) = scope {
+            if self.scope(id).kind == ScopeKind::File {
+                return self.files.iter().position(|&file| file == id);
+            }
+            scope = self.scope(id).parent;
+        }
+        None
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.index()]
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> &Declaration {
+        &self.symbols[id.index()]
+    }
+
+    /// symbols returns every declaration, the index of each is its id.
+    pub fn symbols(&self) -> &[Declaration] {
+        &self.symbols
+    }
+
+    /// declarations returns every declaration along with its id.
+    pub fn declarations(&self) -> impl Iterator<Item = (SymbolId, &Declaration)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .map(|(i, declaration)| (SymbolId(i as u32), declaration))
+    }
+
+    /// references returns every resolved use of a name in source order
+    /// within each file.
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    /// references_to returns the uses of a declaration.
+    pub fn references_to(&self, id: SymbolId) -> impl Iterator<Item = &Reference> {
+        self.references.iter().filter(move |r| r.symbol == id)
+    }
+
+    /// reference_at returns the use of a name under a cursor in a file, if
+    /// any, the file being its position in the files resolved.
+    pub fn reference_at(&self, file: usize, position: Position) -> Option<&Reference> {
+        self.references
+            .iter()
+            .find(|r| r.file == file && r.span.contains(position))
+    }
+
+    /// lookup finds the declaration a name refers to from within a scope,
+    /// searching outwards through the scopes around it.
+    pub fn lookup(&self, scope: ScopeId, name: Symbol) -> Option<SymbolId> {
+        let mut scope = Some(scope);
+        while let Some(id) = scope {
+            let current = self.scope(id);
+            if let Some(symbol) = current.get(name) {
+                return Some(symbol);
+            }
+            scope = current.parent;
+        }
+        None
+    }
+
+    /// state returns the declaration of a state, the automaton is `None` for
+    /// the default automaton.
+    pub fn state(&self, automaton: Option<&str>, name: &str) -> Option<SymbolId> {
+        self.states.get(&state_key(automaton, name)).copied()
+    }
+
+    fn add_scope(&mut self, kind: ScopeKind, parent: ScopeId, span: Span) -> ScopeId {
+        self.scopes.push(Scope::new(kind, Some(parent), span));
+        ScopeId(self.scopes.len() as u32 - 1)
+    }
+
+    fn add_symbol(&mut self, declaration: Declaration) -> SymbolId {
+        self.symbols.push(declaration);
+        SymbolId(self.symbols.len() as u32 - 1)
+    }
+}
+
+impl ops::Index<SymbolId> for SymbolTable {
+    type Output = Declaration;
+
+    fn index(&self, id: SymbolId) -> &Declaration {
+        self.symbol(id)
+    }
+}
+
+// state_key names a state the way it is written in source, `automaton:state`
+// or just `state` for the default automaton.
+fn state_key(automaton: Option<&str>, name: &str) -> String {
+    match automaton {
+        Some(automaton) => format!("{}:{}", automaton, name),
+        None => String::from(name),
+    }
+}
+
+/// Resolved is the result of name resolution, the table is complete even
+/// when there are diagnostics.
+#[derive(Debug)]
+pub struct Resolved {
+    pub table: SymbolTable,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolved {
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Resolver resolves every name used in a set of files to its declaration.
+///
+/// Like pawncc, declarations at the top level are visible throughout every
+/// file, `static` ones only throughout their own file, so functions and
+/// globals can be used before they are declared. Locals are visible from
+/// their declaration to the end of their block. Undefined names are reported
+/// as error 017 and names declared twice in one scope as error 021.
+///
+/// Directives are not part of the tree, so names that come from macros or
+/// from files that are not resolved along with the rest can be declared up
+/// front with `predefine`.
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    predefined: Vec<String>,
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Resolver {
+            predefined: PREDEFINED.iter().map(|name| String::from(*name)).collect(),
+        }
+    }
+
+    pub fn predefine(&mut self, name: &str) {
+        self.predefined.push(String::from(name));
+    }
+
+    /// resolve resolves a set of files, each given as the arena built from
+    /// the root returned by the parser with `interner`, which the predefined
+    /// names and states are added to. The declarations and references found
+    /// are in the file at the same position, and their nodes are ids in its
+    /// arena.
+    pub fn resolve(&self, files: &[&Ast], interner: &mut Interner) -> Resolved {
+        let mut pass = Pass::new(files, interner);
+        let global = pass.table.global();
+        for name in &self.predefined {
+            let name = pass.interner.intern(name);
+            pass.declare(global, name, SymbolKind::Predefined, "_", None);
+        }
+
+        for index in 0..files.len() {
+            pass.file = index;
+            pass.declare_file();
+        }
+        for index in 0..files.len() {
+            pass.file = index;
+            pass.scope = pass.table.file(index);
+            pass.walk_file();
+        }
+
+        Resolved {
+            table: pass.table,
+            diagnostics: pass.diagnostics,
+        }
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Resolver {
+        Resolver::new()
+    }
+}
+
+/// resolve resolves the names in a single file.
+pub fn resolve(ast: &Ast, interner: &mut Interner) -> Resolved {
+    Resolver::new().resolve(&[ast], interner)
+}
+
+// FunctionInfo is what is needed to tell a function being declared again
+// apart from one being defined twice.
+struct FunctionInfo {
+    // whether any declaration so far has a body, or is a native.
+    implemented: bool,
+    // whether every implementation so far belongs to a state.
+    has_states: bool,
+    // the scope holding the parameters, for named arguments.
+    parameters: ScopeId,
+}
+
+// Pass does the work of a Resolver. The top level of every file is declared
+// first, then each file is walked to declare locals and resolve names.
+struct Pass<'a> {
+    files: &'a [&'a Ast],
+    interner: &'a mut Interner,
+    table: SymbolTable,
+    diagnostics: Vec<Diagnostic>,
+    // the position of the file being declared or walked.
+    file: usize,
+    scope: ScopeId,
+    // the scopes around the current one, left for it by the nodes being
+    // walked that opened one.
+    outer: Vec<ScopeId>,
+    // the scope of each function at the top level, in the order they are
+    // declared and so also the order they are walked in.
+    function_scopes: Vec<ScopeId>,
+    next_function: usize,
+    functions: HashMap<SymbolId, FunctionInfo>,
+}
+
+impl<'a> Pass<'a> {
+    fn new(files: &'a [&'a Ast], interner: &'a mut Interner) -> Pass<'a> {
+        let table = SymbolTable::new();
+        let scope = table.global();
+        Pass {
+            files,
+            interner,
+            table,
+            diagnostics: vec![],
+            file: 0,
+            scope,
+            outer: vec![],
+            function_scopes: vec![],
+            next_function: 0,
+            functions: HashMap::new(),
+        }
+    }
+
+    // ast returns the file being declared or walked.
+    fn ast(&self) -> &'a Ast {
+        self.files[self.file]
+    }
+
+    // declare_file adds the scope of a file and declares its top level.
+    fn declare_file(&mut self) {
+        let ast = self.ast();
+        let global = self.table.global();
+        let file = self
+            .table
+            .add_scope(ScopeKind::File, global, ast.span(ast.root()));
+        self.table.files.push(file);
+
+        for &id in ast.children(ast.root()) {
+            match ast.kind(id) {
+                NodeKind::Variable {
+                    name, tag, storage, ..
+                } => {
+                    let (scope, kind) = match storage {
+                        StorageClass::Static => (file, SymbolKind::Variable),
+                        StorageClass::Const => (global, SymbolKind::Constant),
+                        _ => (global, SymbolKind::Variable),
+                    };
+                    self.declare(scope, *name, kind, tag, Some(id));
+                }
+                NodeKind::Function { is_static, .. } => {
+                    let scope = if *is_static { file } else { global };
+                    self.declare_function(scope, file, id);
+                }
+                NodeKind::Enum { name, .. } => {
+                    if let Some(name) = *name {
+                        let tag = enum_tag(ast, self.interner, id);
+                        self.declare(global, name, SymbolKind::Enum, &tag, Some(id));
+                    }
+                    for &member in ast.children(id) {
+                        if let NodeKind::EnumMember { name, .. } = ast.kind(member) {
+                            let tag = member_tag(ast, self.interner, member);
+                            self.declare(global, *name, SymbolKind::EnumMember, &tag, Some(member));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // declare_function declares a function along with a scope holding its
+    // parameters. A function may be declared any number of times, but only
+    // implemented once unless each implementation belongs to a state.
+    fn declare_function(&mut self, scope: ScopeId, file: ScopeId, id: NodeId) {
+        let ast = self.ast();
+        let (kind, name, tag, states, operator) = match ast.kind(id) {
+            NodeKind::Function {
+                kind,
+                name,
+                tag,
+                states,
+                operator,
+                ..
+            } => (kind, name, tag, states, operator),
+            _ => return,
+        };
+        let parameters = self
+            .table
+            .add_scope(ScopeKind::Function, file, ast.span(id));
+        self.function_scopes.push(parameters);
+        let mut implemented = *kind == FunctionKind::Native;
+        for &child in ast.children(id) {
+            match ast.kind(child) {
+                NodeKind::Parameter {
+                    name: Some(name),
+                    tags,
+                    ..
+                } => {
+                    let tag = tags.first().map_or("_", |tag| tag.as_str());
+                    self.declare(parameters, *name, SymbolKind::Parameter, tag, Some(child));
+                }
+                NodeKind::Block => implemented = true,
+                _ => {}
+            }
+        }
+        if let Some(states) = states {
+            self.declare_states(states, id);
+        }
+
+        // operators are found by the tags of their operands, not by name
+        if operator.is_some() {
+            return;
+        }
+
+        let has_states = states.is_some();
+        let existing = self.table.scope(scope).get(*name);
+        if let Some(info) = existing.and_then(|id| self.functions.get_mut(&id)) {
+            if !(implemented && info.implemented && !(has_states && info.has_states)) {
+                if implemented {
+                    if !info.implemented {
+                        info.has_states = has_states;
+                    }
+                    info.implemented = true;
+                    info.parameters = parameters;
+                }
+                return;
+            }
+        }
+
+        let kind = match kind {
+            FunctionKind::Native => SymbolKind::Native,
+            _ => SymbolKind::Function,
+        };
+        if let Some(id) = self.declare(scope, *name, kind, tag, Some(id)) {
+            let info = FunctionInfo {
+                implemented,
+                has_states,
+                parameters,
+            };
+            self.functions.insert(id, info);
+        }
+    }
+
+    // declare_states declares the states of a function that have not been
+    // named before, the rest are references to the first function naming
+    // them.
+    fn declare_states(&mut self, states: &StateList, function: NodeId) {
+        let global = self.table.global();
+        let span = self.ast().span(function);
+        for state in &states.states {
+            let key = state_key(states.automaton.as_deref(), state);
+            let name = self.interner.intern(&key);
+            match self.table.states.get(&key) {
+                Some(&symbol) => self.table.references.push(Reference {
+                    name,
+                    file: self.file,
+                    node: function,
+                    span,
+                    symbol,
+                    scope: global,
+                }),
+                None => {
+                    let id = self.table.add_symbol(Declaration {
+                        name,
+                        kind: SymbolKind::State,
+                        tag: String::from("_"),
+                        file: Some(self.file),
+                        node: Some(function),
+                        span,
+                        scope: global,
+                    });
+                    self.table.states.insert(key, id);
+                }
+            }
+        }
+    }
+
+    // declare adds a name to a scope, reporting it if the scope already has
+    // it. Only predefined names have no node.
+    fn declare(
+        &mut self,
+        scope: ScopeId,
+        name: Symbol,
+        kind: SymbolKind,
+        tag: &str,
+        node: Option<NodeId>,
+    ) -> Option<SymbolId> {
+        let span = node.map_or_else(Span::default, |id| self.ast().span(id));
+        let existing = match kind {
+            SymbolKind::Label => self.table.scope(scope).label(name),
+            _ => self.table.scope(scope).get(name),
+        };
+        if let Some(existing) = existing {
+            let previous = self.table.symbol(existing);
+            let mut diagnostic = Diagnostic::new(
+                ParseError::Redefinition {
+                    name: String::from(self.interner.resolve(name)),
+                },
+                span,
+            );
+            if previous.kind != SymbolKind::Predefined {
+                diagnostic = diagnostic.with_label(previous.span, "first defined here");
+            }
+            self.diagnostics.push(diagnostic);
+            return None;
+        }
+
+        let id = self.table.add_symbol(Declaration {
+            name,
+            kind,
+            tag: String::from(tag),
+            file: node.map(|_| self.file),
+            node,
+            span,
+            scope,
+        });
+        let scope = &mut self.table.scopes[scope.index()];
+        match kind {
+            SymbolKind::Label => scope.labels.insert(name, id),
+            _ => scope.names.insert(name, id),
+        };
+        Some(id)
+    }
+
+    // refer records a use of a name, or reports it if nothing declares it.
+    fn refer(&mut self, name: Symbol, node: NodeId, symbol: Option<SymbolId>) {
+        let span = self.ast().span(node);
+        match symbol {
+            Some(symbol) => self.table.references.push(Reference {
+                name,
+                file: self.file,
+                node,
+                span,
+                symbol,
+                scope: self.scope,
+            }),
+            None => self.diagnostics.push(Diagnostic::new(
+                ParseError::UndefinedSymbol {
+                    name: String::from(self.interner.resolve(name)),
+                },
+                span,
+            )),
+        }
+    }
+
+    fn refer_symbol(&mut self, name: Symbol, node: NodeId) {
+        let symbol = self.table.lookup(self.scope, name);
+        self.refer(name, node, symbol);
+    }
+
+    // is_local reports whether the current scope is inside a function, where
+    // declarations are made as they are walked rather than up front.
+    fn is_local(&self) -> bool {
+        matches!(
+            self.table.scope(self.scope).kind,
+            ScopeKind::Function | ScopeKind::Block
+        )
+    }
+
+    // function_scope returns the scope of the function being walked.
+    fn function_scope(&self) -> Option<ScopeId> {
+        let mut scope = Some(self.scope);
+        while let Some(id) = scope {
+            if self.table.scope(id).kind == ScopeKind::Function {
+                return Some(id);
+            }
+            scope = self.table.scope(id).parent;
+        }
+        None
+    }
+
+    // open makes a scope the current one until the node that opened it is
+    // left.
+    fn open(&mut self, scope: ScopeId) {
+        let outer = std::mem::replace(&mut self.scope, scope);
+        self.outer.push(outer);
+    }
+
+    fn open_block(&mut self, id: NodeId) {
+        let scope = self
+            .table
+            .add_scope(ScopeKind::Block, self.scope, self.ast().span(id));
+        self.open(scope);
+    }
+
+    fn close(&mut self) {
+        if let Some(outer) = self.outer.pop() {
+            self.scope = outer;
+        }
+    }
+
+    // walk_file walks the file being resolved, declaring locals as they are
+    // left and resolving names as they are entered. The walk keeps its place
+    // on a stack of its own, so a long chain of operators takes no more
+    // stack here than a short one.
+    fn walk_file(&mut self) {
+        let ast = self.ast();
+        let mut walk = ast.walk(ast.root());
+        while let Some(event) = walk.next() {
+            match event {
+                Event::Enter(id) => self.enter(id, &mut walk),
+                Event::Leave(id) => self.leave(id),
+            }
+        }
+    }
+
+    fn enter(&mut self, id: NodeId, walk: &mut Walk) {
+        let ast = self.ast();
+        match ast.kind(id) {
+            NodeKind::Function { .. } => {
+                let scope = self.function_scopes[self.next_function];
+                self.next_function += 1;
+                self.open(scope);
+
+                // a label can be jumped to from before it
+                for label in ast.descendants(id) {
+                    if let NodeKind::Label(name) = ast.kind(label) {
+                        self.declare(scope, *name, SymbolKind::Label, "_", Some(label));
+                    }
+                }
+            }
+            NodeKind::Block if !is_body(ast, id) => self.open_block(id),
+            NodeKind::For { .. } => self.open_block(id),
+            NodeKind::Enum {
+                increment: None, ..
+            } => self.declare_enum(id),
+            NodeKind::Symbol(name) => self.refer_symbol(*name, id),
+            // `defined` asks whether a name is known, so one that is not is
+            // no error
+            NodeKind::UnaryOperation { operator, .. }
+                if operator.token_type == TokenType::Defined =>
+            {
+                walk.skip_children();
+                let operand = ast.children(id)[0];
+                if let NodeKind::Symbol(name) = *ast.kind(operand) {
+                    if let Some(symbol) = self.table.lookup(self.scope, name) {
+                        self.refer(name, operand, Some(symbol));
+                    }
+                }
+            }
+            NodeKind::Goto(name) => {
+                let symbol = self
+                    .function_scope()
+                    .and_then(|scope| self.table.scope(scope).label(*name));
+                self.refer(*name, id, symbol);
+            }
+            _ => {}
+        }
+    }
+
+    fn leave(&mut self, id: NodeId) {
+        let ast = self.ast();
+        match ast.kind(id) {
+            NodeKind::Function { .. } | NodeKind::For { .. } => self.close(),
+            NodeKind::Block if !is_body(ast, id) => self.close(),
+            NodeKind::Variable {
+                name, tag, storage, ..
+            } if self.is_local() => {
+                let kind = match storage {
+                    StorageClass::Const => SymbolKind::Constant,
+                    _ => SymbolKind::Variable,
+                };
+                self.declare(self.scope, *name, kind, tag, Some(id));
+            }
+            NodeKind::EnumMember { name, .. } if self.is_local() => {
+                let tag = member_tag(ast, self.interner, id);
+                self.declare(self.scope, *name, SymbolKind::EnumMember, &tag, Some(id));
+            }
+            NodeKind::Call => self.named_arguments(id),
+            NodeKind::StateTransition { automaton, state } => {
+                let automaton = automaton.as_deref();
+                let symbol = self.table.state(automaton, state);
+                let name = self.interner.intern(&state_key(automaton, state));
+                self.refer(name, id, symbol);
+            }
+            _ => {}
+        }
+
+        // the name of an enum is declared after its increment
+        if let Some(parent) = ast.parent(id) {
+            if let NodeKind::Enum {
+                increment: Some(_), ..
+            } = ast.kind(parent)
+            {
+                if ast.children(parent)[0] == id {
+                    self.declare_enum(parent);
+                }
+            }
+        }
+    }
+
+    // declare_enum declares the name of an enum inside a function, those at
+    // the top level are declared up front along with their members.
+    fn declare_enum(&mut self, id: NodeId) {
+        let ast = self.ast();
+        if let (
+            true,
+            NodeKind::Enum {
+                name: Some(name), ..
+            },
+        ) = (self.is_local(), ast.kind(id))
+        {
+            let tag = enum_tag(ast, self.interner, id);
+            self.declare(self.scope, *name, SymbolKind::Enum, &tag, Some(id));
+        }
+    }
+
+    // named_arguments resolves the named arguments of a call, which refer
+    // to the parameters of the function called.
+    fn named_arguments(&mut self, call: NodeId) {
+        let ast = self.ast();
+        let (function, arguments) = match ast.children(call).split_first() {
+            Some(children) => children,
+            None => return,
+        };
+        let parameters = match ast.kind(*function) {
+            NodeKind::Symbol(name) => self
+                .table
+                .lookup(self.scope, *name)
+                .and_then(|id| self.functions.get(&id))
+                .map(|info| info.parameters),
+            _ => None,
+        };
+        if let Some(parameters) = parameters {
+            for &argument in arguments {
+                if let NodeKind::Argument { name: Some(name) } = *ast.kind(argument) {
+                    let symbol = self.table.scope(parameters).get(name);
+                    self.refer(name, argument, symbol);
+                }
+            }
+        }
+    }
+}
+
+// is_body reports whether a block is the body of a function, whose locals
+// are in the scope of the function itself.
+fn is_body(ast: &Ast, id: NodeId) -> bool {
+    matches!(
+        ast.parent(id).map(|parent| ast.kind(parent)),
+        Some(NodeKind::Function { .. })
+    )
+}
+
+// enum_tag returns the tag of an enum, which is its name unless it is given
+// one explicitly.
+fn enum_tag(ast: &Ast, interner: &Interner, id: NodeId) -> String {
+    let tag = match ast.kind(id) {
+        NodeKind::Enum { name, tag, .. } => tag
+            .as_deref()
+            .or_else(|| name.map(|name| interner.resolve(name)))
+            .unwrap_or("_"),
+        _ => "_",
+    };
+    String::from(tag)
+}
+
+fn member_tag(ast: &Ast, interner: &Interner, id: NodeId) -> String {
+    match ast.kind(id) {
+        NodeKind::EnumMember { tag, .. } if tag != "_" => tag.clone(),
+        _ => ast
+            .parent(id)
+            .map_or_else(|| String::from("_"), |e| enum_tag(ast, interner, e)),
+    }
+}
@@ -0,0 +1,303 @@
+#[cfg(test)]
+use crate::arena::NodeKind;
+#[cfg(test)]
+use crate::error::ParseError;
+#[cfg(test)]
+use crate::intern::Interner;
+#[cfg(test)]
+use crate::resolve::{resolve, Resolved, Resolver, ScopeKind, SymbolKind};
+#[cfg(test)]
+use crate::test_utils::arena;
+#[cfg(test)]
+use crate::token::Position;
+
+// resolved describes every reference as its name, the line it is on and the
+// kind and line of the declaration it resolves to.
+#[cfg(test)]
+fn resolved(resolved: &Resolved, interner: &Interner) -> Vec<(String, i32, SymbolKind, i32)> {
+    resolved
+        .table
+        .references()
+        .iter()
+        .map(|r| {
+            let declaration = resolved.table.symbol(r.symbol);
+            (
+                String::from(interner.resolve(r.name)),
+                r.span.line_start,
+                declaration.kind,
+                declaration.span.line_start,
+            )
+        })
+        .collect()
+}
+
+// errors lists the code, name and line of each diagnostic.
+#[cfg(test)]
+fn errors(resolved: &Resolved) -> Vec<(u16, String, i32)> {
+    resolved
+        .diagnostics
+        .iter()
+        .map(|d| {
+            let name = match &d.error {
+                ParseError::UndefinedSymbol { name } | ParseError::Redefinition { name } => {
+                    name.clone()
+                }
+                e => panic!("unexpected error {:?}", e),
+            };
+            (d.code(), name, d.span.line_start)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn reference(
+    name: &str,
+    line: i32,
+    kind: SymbolKind,
+    declared: i32,
+) -> (String, i32, SymbolKind, i32) {
+    (String::from(name), line, kind, declared)
+}
+
+#[test]
+fn test_resolve_scopes() {
+    let mut interner = Interner::new();
+    let ast = arena(
+        "new a = 1;\n\
+         const SIZE = 4;\n\
+         enum E { E_X, E_Y[SIZE] }\n\
+         native print(const s[]);\n\
+         main() {\n\
+         \x20   new b[E] = a;\n\
+         \x20   f(b[E_X]);\n\
+         \x20   {\n\
+         \x20       new a = cellbits;\n\
+         \x20       print(a);\n\
+         \x20   }\n\
+         \x20   return a;\n\
+         }\n\
+         f(x) {\n\
+         \x20   for (new i = 0; i < x; i++) {}\n\
+         }",
+        &mut interner,
+    );
+    let result = resolve(&ast, &mut interner);
+    assert!(result.is_ok(), "{:?}", result.diagnostics);
+
+    assert_eq!(
+        resolved(&result, &interner),
+        vec![
+            reference("SIZE", 3, SymbolKind::Constant, 2),
+            reference("E", 6, SymbolKind::Enum, 3),
+            reference("a", 6, SymbolKind::Variable, 1),
+            // functions can be used before they are declared
+            reference("f", 7, SymbolKind::Function, 14),
+            reference("b", 7, SymbolKind::Variable, 6),
+            reference("E_X", 7, SymbolKind::EnumMember, 3),
+            reference("cellbits", 9, SymbolKind::Predefined, 0),
+            reference("print", 10, SymbolKind::Native, 4),
+            // the local shadows the global inside the block only
+            reference("a", 10, SymbolKind::Variable, 9),
+            reference("a", 12, SymbolKind::Variable, 1),
+            reference("i", 15, SymbolKind::Variable, 15),
+            reference("x", 15, SymbolKind::Parameter, 14),
+            reference("i", 15, SymbolKind::Variable, 15),
+        ]
+    );
+
+    let table = &result.table;
+    let inner = table.reference_at(0, Position::new(10, 15)).unwrap();
+    assert_eq!(table.scope(inner.scope).kind, ScopeKind::Block);
+    assert_eq!(table.symbol(inner.symbol).scope, inner.scope);
+    let global = table
+        .lookup(table.global(), interner.get("a").unwrap())
+        .unwrap();
+    assert_eq!(table.references_to(global).count(), 2);
+    assert_eq!(interner.resolve(table[global].name), "a");
+}
+
+#[test]
+fn test_resolve_undefined() {
+    let mut interner = Interner::new();
+    let ast = arena(
+        "main() {\n\
+         \x20   a = 1;\n\
+         \x20   new a;\n\
+         \x20   {\n\
+         \x20       new b;\n\
+         \x20   }\n\
+         \x20   b = missing(a);\n\
+         \x20   goto nowhere;\n\
+         \x20   state unknown;\n\
+         \x20   new c = defined nothing + defined(a);\n\
+         }",
+        &mut interner,
+    );
+    let result = resolve(&ast, &mut interner);
+
+    assert_eq!(
+        errors(&result),
+        vec![
+            // a local is only visible after its declaration
+            (17, String::from("a"), 2),
+            (17, String::from("b"), 7),
+            (17, String::from("missing"), 7),
+            (17, String::from("nowhere"), 8),
+            (17, String::from("unknown"), 9),
+        ]
+    );
+    assert_eq!(
+        result.diagnostics[2].to_string(),
+        "7:9: error 017: undefined symbol \"missing\""
+    );
+    // `defined` only refers to names that are known
+    assert_eq!(
+        resolved(&result, &interner).last(),
+        Some(&reference("a", 10, SymbolKind::Variable, 3))
+    );
+}
+
+#[test]
+fn test_resolve_redefinition() {
+    let mut interner = Interner::new();
+    let ast = arena(
+        "new a;\n\
+         new a;\n\
+         forward f(x);\n\
+         f(x) {\n\
+         \x20   new x;\n\
+         l:\n\
+         l:\n\
+         \x20   new y;\n\
+         \x20   {\n\
+         \x20       new y;\n\
+         \x20   }\n\
+         }\n\
+         f(x) {}\n\
+         g() <on> {}\n\
+         g() <off> {}\n\
+         g() <> {}\n\
+         new cellmax;",
+        &mut interner,
+    );
+    let result = resolve(&ast, &mut interner);
+
+    assert_eq!(
+        errors(&result),
+        vec![
+            (21, String::from("a"), 2),
+            // a forward declaration is not a definition, a second body is
+            (21, String::from("f"), 13),
+            (21, String::from("cellmax"), 17),
+            // labels are declared before the body of a function is walked
+            (21, String::from("l"), 7),
+            // the body of a function shares a scope with its parameters
+            (21, String::from("x"), 5),
+        ]
+    );
+
+    let diagnostic = &result.diagnostics[0];
+    assert_eq!(diagnostic.secondary.len(), 1);
+    assert_eq!(diagnostic.secondary[0].span.line_start, 1);
+    assert_eq!(diagnostic.secondary[0].message, "first defined here");
+    assert!(result.diagnostics[2].secondary.is_empty());
+}
+
+#[test]
+fn test_resolve_labels_states_and_arguments() {
+    let mut interner = Interner::new();
+    let ast = arena(
+        "f(a, b = 1) {}\n\
+         g() <idle> {\n\
+         \x20   goto done;\n\
+         \x20   f(.b = 2, .a = 1);\n\
+         \x20   f(.c = 3);\n\
+         done:\n\
+         \x20   state busy;\n\
+         }\n\
+         g() <busy> {\n\
+         \x20   state idle;\n\
+         }",
+        &mut interner,
+    );
+    let result = resolve(&ast, &mut interner);
+
+    assert_eq!(errors(&result), vec![(17, String::from("c"), 5)]);
+    let references: Vec<_> = resolved(&result, &interner)
+        .into_iter()
+        .filter(|r| r.2 != SymbolKind::Function)
+        .collect();
+    assert_eq!(
+        references,
+        vec![
+            reference("done", 3, SymbolKind::Label, 6),
+            reference("b", 4, SymbolKind::Parameter, 1),
+            reference("a", 4, SymbolKind::Parameter, 1),
+            reference("busy", 7, SymbolKind::State, 9),
+            reference("idle", 10, SymbolKind::State, 2),
+        ]
+    );
+}
+
+#[test]
+fn test_resolve_files() {
+    let mut interner = Interner::new();
+    let first = arena(
+        "static counter;\nnew shared;\nstatic helper() {}\nmain() { helper(); }",
+        &mut interner,
+    );
+    let second = arena(
+        "static counter;\nuse() {\n    shared = counter;\n    helper();\n}",
+        &mut interner,
+    );
+
+    let mut resolver = Resolver::new();
+    resolver.predefine("MAX_PLAYERS");
+    let result = resolver.resolve(&[&first, &second], &mut interner);
+
+    // each file has its own statics, the global is shared
+    assert_eq!(errors(&result), vec![(17, String::from("helper"), 4)]);
+    let table = &result.table;
+    let shared = table
+        .lookup(table.file(1), interner.get("shared").unwrap())
+        .unwrap();
+    assert_eq!(table[shared].scope, table.global());
+    let counter = table
+        .lookup(table.file(1), interner.get("counter").unwrap())
+        .unwrap();
+    assert_eq!(table[counter].scope, table.file(1));
+    assert_ne!(
+        table.lookup(table.file(0), interner.get("counter").unwrap()),
+        Some(counter)
+    );
+    assert!(table
+        .lookup(table.file(0), interner.get("MAX_PLAYERS").unwrap())
+        .is_some());
+
+    // declarations and references know the file they are in, so a position
+    // is only looked up in the file it is given for
+    assert_eq!(table[shared].file, Some(0));
+    assert_eq!(table[counter].file, Some(1));
+    let predefined = table
+        .lookup(table.global(), interner.get("MAX_PLAYERS").unwrap())
+        .unwrap();
+    assert_eq!(table[predefined].file, None);
+    let helper = table.reference_at(0, Position::new(4, 10)).unwrap();
+    assert_eq!((interner.resolve(helper.name), helper.file), ("helper", 0));
+    assert!(table.reference_at(1, Position::new(4, 10)).is_none());
+    let used = table.reference_at(1, Position::new(3, 5)).unwrap();
+    assert_eq!((interner.resolve(used.name), used.file), ("shared", 1));
+    assert!(table.reference_at(0, Position::new(3, 5)).is_none());
+
+    // and the node of each, in the arena of that file
+    assert_eq!(
+        second.kind(used.node),
+        &NodeKind::Symbol(interner.get("shared").unwrap())
+    );
+    let declared = table[counter].node.unwrap();
+    assert!(
+        matches!(second.kind(declared), NodeKind::Variable { name, .. } if interner.resolve(*name) == "counter")
+    );
+    assert_eq!(second.parent(declared), Some(second.root()));
+    assert_eq!(table[predefined].node, None);
+}
@@ -8,7 +8,7 @@ impl<T: Copy> Ring<T> {
     pub fn new(capacity: usize) -> Ring<T> {
         Ring {
             buffer: Vec::with_capacity(capacity),
-            capacity: capacity,
+            capacity,
             playhead: 0,
         }
     }
@@ -30,11 +30,11 @@ impl<T: Copy> Ring<T> {
         let playhead = self.playhead;
 
         self.buffer[playhead..]
-            .into_iter()
-            .for_each(|i: &T| result.push(i.clone()));
+            .iter()
+            .for_each(|i: &T| result.push(*i));
         self.buffer[..playhead]
-            .into_iter()
-            .for_each(|i: &T| result.push(i.clone()));
+            .iter()
+            .for_each(|i: &T| result.push(*i));
 
         result
     }
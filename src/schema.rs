@@ -0,0 +1,36 @@
+use crate::ast::Node;
+use crate::token::Token;
+
+/// SCHEMA_VERSION is the version of the serialised form of tokens and syntax
+/// trees described in `docs/schema.md`. It is bumped whenever a change to the
+/// AST types would change that form, so consumers can tell what they are
+/// reading.
+pub const SCHEMA_VERSION: u32 = 7;
+
+/// Document is the top-level object written when exporting a parse with the
+/// `serde` feature. The tokens may be left empty when only the tree is
+/// needed.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Document {
+    pub version: u32,
+    pub tokens: Vec<Token>,
+    pub root: Node,
+}
+
+impl Document {
+    /// new returns a document of the current schema version.
+    pub fn new(tokens: Vec<Token>, root: Node) -> Document {
+        Document {
+            version: SCHEMA_VERSION,
+            tokens,
+            root,
+        }
+    }
+
+    /// is_supported reports whether a deserialised document was written with
+    /// the schema version this build reads.
+    pub fn is_supported(&self) -> bool {
+        self.version == SCHEMA_VERSION
+    }
+}
@@ -0,0 +1,130 @@
+#[cfg(all(test, feature = "serde"))]
+use serde_json::json;
+
+#[cfg(all(test, feature = "serde"))]
+use crate::ast::Parser;
+#[cfg(all(test, feature = "serde"))]
+use crate::lexer::Lexer;
+#[cfg(all(test, feature = "serde"))]
+use crate::schema::{Document, SCHEMA_VERSION};
+
+#[cfg(all(test, feature = "serde"))]
+fn document(source: &str) -> Document {
+    let tokens = Lexer::new(source).lex();
+    let mut p = Parser::new(tokens.clone());
+    p.parse().expect("failed to parse");
+    Document::new(tokens, p.root)
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[test]
+fn test_round_trip() {
+    let document = document(
+        "
+enum E_DATA { E_NAME[24], Float:E_HEALTH = 100 }
+native Print(const string[], ...) = -5;
+stock Float:Half(Float:a) return a / 2.0;
+main() <idle> {
+    new data[E_DATA] = {\"name\", 1.5};
+    switch (data[E_HEALTH]) { case 1..5, X: state ready; default: {} }
+    for (;;) data[E_NAME]++;
+}
+",
+    );
+
+    let json = serde_json::to_string(&document).unwrap();
+    let read: Document = serde_json::from_str(&json).unwrap();
+    assert_eq!(read, document);
+    assert_eq!(read.tokens, document.tokens);
+    assert_eq!(read.root.children[3].span, document.root.children[3].span);
+    assert!(read.is_supported());
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[test]
+fn test_schema_shape() {
+    let document = document("new a = -b;");
+    let value = serde_json::to_value(&document).unwrap();
+
+    assert_eq!(value["version"], json!(SCHEMA_VERSION));
+    assert_eq!(
+        value["tokens"][1],
+        json!({
+            "token_type": "Symbol",
+            "value": {"String": "a"},
+            "line_start": 1,
+            "line_end": 1,
+            "column_start": 5,
+            "column_end": 6,
+        })
+    );
+    assert_eq!(
+        value["root"]["children"][0]["expr"],
+        json!({
+            "Variable": {
+                "name": "a",
+                "tag": "_",
+                "dimensions": [],
+                "initialiser": {
+                    "node": {"UnaryOperation": {
+                        "operator": {
+                            "token_type": "Minus",
+                            "value": null,
+                            "line_start": 1,
+                            "line_end": 1,
+                            "column_start": 9,
+                            "column_end": 10,
+                        },
+                        "operand": {
+                            "node": {"Symbol": "b"},
+                            "span": {"line_start": 1, "line_end": 1, "column_start": 10, "column_end": 11},
+                        },
+                        "postfix": false,
+                    }},
+                    "span": {"line_start": 1, "line_end": 1, "column_start": 9, "column_end": 11},
+                },
+                "storage": "New",
+                "is_stock": false,
+                "is_const": false,
+            }
+        })
+    );
+    assert_eq!(value["root"]["children"][0]["children"], json!([]));
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[test]
+fn test_literal_shape() {
+    let document = document("new a[] = !\"x\" \"y\";");
+    let value = serde_json::to_value(&document).unwrap();
+
+    assert_eq!(
+        value["root"]["children"][0]["expr"]["Variable"]["initialiser"]["node"],
+        json!({
+            "Literal": {
+                "String": {
+                    "packed": true,
+                    "parts": [
+                        {
+                            "node": "x",
+                            "span": {"line_start": 1, "line_end": 1, "column_start": 12, "column_end": 15},
+                        },
+                        {
+                            "node": "y",
+                            "span": {"line_start": 1, "line_end": 1, "column_start": 16, "column_end": 19},
+                        },
+                    ],
+                }
+            }
+        })
+    );
+}
+
+#[cfg(all(test, feature = "serde"))]
+#[test]
+fn test_unsupported_version() {
+    let mut value = serde_json::to_value(document("new a;")).unwrap();
+    value["version"] = json!(SCHEMA_VERSION + 1);
+    let read: Document = serde_json::from_value(value).unwrap();
+    assert!(!read.is_supported());
+}
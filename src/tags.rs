@@ -0,0 +1,572 @@
+use crate::arena::Ast;
+use crate::arena::Event;
+use crate::arena::NodeId;
+use crate::arena::NodeKind;
+use crate::ast::compound_operator;
+use crate::ast::is_assignment;
+use crate::ast::Node;
+use crate::error::Diagnostic;
+use crate::error::ParseError;
+use crate::intern::Interner;
+use crate::intern::Symbol;
+use crate::resolve::resolve;
+use crate::resolve::Resolved;
+use crate::resolve::SymbolId;
+use crate::resolve::SymbolKind;
+use crate::resolve::SymbolTable;
+use crate::token::Span;
+use crate::token::TokenType;
+
+use std::collections::HashMap;
+
+/// check checks the tags used in a single file, resolving its names first.
+/// Only tag mismatches are returned, undefined names are left to `resolve`.
+pub fn check(root: &Node) -> Vec<Diagnostic> {
+    let mut interner = Interner::new();
+    let ast = Ast::new(root, &mut interner);
+    check_files(&[&ast], &resolve(&ast, &mut interner))
+}
+
+/// check_files checks the tags used in a set of files, which must be the
+/// files the names were resolved from and in the same order. Each value whose
+/// tag does not match the one expected of it is reported as warning 213.
+///
+/// The rules are those of pawncc. Tags starting with an upper case letter,
+/// such as `Float:`, are strong and the rest, such as `bool:`, are weak. A
+/// value can be given where an untagged one is expected if its tag is weak,
+/// but an untagged value is never taken for a tagged one. The operands of a
+/// binary operator must match the same way in either direction, unless a
+/// user-defined operator takes their tags, in which case the result has the
+/// tag the operator returns. Operators are found in the files checked, so
+/// the include that defines them, such as `float.inc`, should be among them.
+pub fn check_files(files: &[&Ast], resolved: &Resolved) -> Vec<Diagnostic> {
+    let table = &resolved.table;
+    let mut functions = HashMap::new();
+    let mut operators = vec![];
+    for (index, ast) in files.iter().enumerate() {
+        for &id in ast.children(ast.root()) {
+            let (name, tag, operator) = match ast.kind(id) {
+                NodeKind::Function {
+                    name,
+                    tag,
+                    operator,
+                    ..
+                } => (name, tag, operator),
+                _ => continue,
+            };
+            let parameters = parameters(ast, id);
+            match operator {
+                Some(operator) => operators.push(UserOperator {
+                    operator: operator.token_type,
+                    operands: parameters.iter().map(|p| first_tag(p.tags)).collect(),
+                    tag,
+                }),
+                None => {
+                    if let Some(id) = table.lookup(table.file(index), *name) {
+                        functions.entry(id).or_insert(parameters);
+                    }
+                }
+            }
+        }
+    }
+
+    let references: Vec<HashMap<NodeId, SymbolId>> = (0..files.len())
+        .map(|index| {
+            table
+                .references()
+                .iter()
+                .filter(|r| r.file == index)
+                .map(|r| (r.node, r.symbol))
+                .collect()
+        })
+        .collect();
+
+    let mut layouts = Layouts {
+        arrays: HashMap::new(),
+        members: HashMap::new(),
+    };
+    for (index, ast) in files.iter().enumerate() {
+        layouts.find(ast, index, table, &references[index]);
+    }
+
+    let mut diagnostics = vec![];
+    for (index, ast) in files.iter().enumerate() {
+        let mut checker = Checker {
+            ast,
+            table,
+            functions: &functions,
+            operators: &operators,
+            layouts: &layouts,
+            references: &references[index],
+            tags: vec!["_"; ast.len()],
+            indices: HashMap::new(),
+            function_tag: "_",
+            diagnostics: &mut diagnostics,
+        };
+        checker.check();
+    }
+    diagnostics
+}
+
+// UserOperator is an operator defined for some tags, such as
+// `Float:operator*(Float:a, Float:b)`.
+struct UserOperator<'a> {
+    operator: TokenType,
+    operands: Vec<&'a str>,
+    tag: &'a str,
+}
+
+// Parameter is what the arguments given for a parameter are checked against.
+struct Parameter<'a> {
+    name: Option<Symbol>,
+    tags: &'a [String],
+    is_variadic: bool,
+}
+
+// parameters returns the parameters of a function.
+fn parameters(ast: &Ast, function: NodeId) -> Vec<Parameter<'_>> {
+    ast.children(function)
+        .iter()
+        .filter_map(|&id| match ast.kind(id) {
+            NodeKind::Parameter {
+                name,
+                tags,
+                is_variadic,
+                ..
+            } => Some(Parameter {
+                name: *name,
+                tags,
+                is_variadic: *is_variadic,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+// Layouts holds the arrays with a dimension sized by an enum, such as
+// `new data[10][E_PLAYER]`, and the enum each member belongs to, for indexing
+// those dimensions by member.
+struct Layouts {
+    // the enum sizing each dimension of an array, if any.
+    arrays: HashMap<SymbolId, Vec<Option<SymbolId>>>,
+    // the enum each member belongs to.
+    members: HashMap<SymbolId, SymbolId>,
+}
+
+impl Layouts {
+    // find finds the layouts declared in a file.
+    fn find(
+        &mut self,
+        ast: &Ast,
+        index: usize,
+        table: &SymbolTable,
+        references: &HashMap<NodeId, SymbolId>,
+    ) {
+        let declared: HashMap<NodeId, SymbolId> = table
+            .declarations()
+            .filter(|(_, d)| d.file == Some(index))
+            .filter_map(|(id, d)| Some((d.node?, id)))
+            .collect();
+
+        for id in ast.ids() {
+            let symbol = match declared.get(&id) {
+                Some(&symbol) => symbol,
+                None => continue,
+            };
+            match ast.kind(id) {
+                NodeKind::Variable { .. } | NodeKind::Parameter { .. } => {
+                    let enums: Vec<_> = ast
+                        .children(id)
+                        .iter()
+                        .filter(|&&child| ast.kind(child) == &NodeKind::Dimension)
+                        .map(|&dimension| {
+                            let size = *ast.children(dimension).first()?;
+                            references
+                                .get(&size)
+                                .copied()
+                                .filter(|&e| table[e].kind == SymbolKind::Enum)
+                        })
+                        .collect();
+                    if enums.iter().any(Option::is_some) {
+                        self.arrays.insert(symbol, enums);
+                    }
+                }
+                NodeKind::Enum { .. } => {
+                    for member in ast.children(id) {
+                        if let Some(&member) = declared.get(member) {
+                            self.members.insert(member, symbol);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Checker checks a single file. It walks the file leaving each expression
+// after the expressions inside it, so it has the tags of its operands by
+// their ids when working out its own.
+struct Checker<'a> {
+    ast: &'a Ast,
+    table: &'a SymbolTable,
+    functions: &'a HashMap<SymbolId, Vec<Parameter<'a>>>,
+    operators: &'a [UserOperator<'a>],
+    layouts: &'a Layouts,
+    references: &'a HashMap<NodeId, SymbolId>,
+    // the tag of each expression left so far.
+    tags: Vec<&'a str>,
+    // the enums sizing the dimensions of the array each index is into, and
+    // which of them it indexes.
+    indices: HashMap<NodeId, (&'a [Option<SymbolId>], usize)>,
+    // the tag of the function being checked, for its return statements.
+    function_tag: &'a str,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn check(&mut self) {
+        let ast = self.ast;
+        let mut walk = ast.walk(ast.root());
+        while let Some(event) = walk.next() {
+            match event {
+                Event::Enter(id) => match ast.kind(id) {
+                    NodeKind::Function { tag, .. } => self.function_tag = tag,
+                    // the operands of tagof, sizeof and defined are never
+                    // evaluated
+                    NodeKind::UnaryOperation { operator, .. }
+                        if is_unevaluated(operator.token_type) =>
+                    {
+                        walk.skip_children()
+                    }
+                    _ => {}
+                },
+                Event::Leave(id) => self.leave(id),
+            }
+        }
+    }
+
+    fn leave(&mut self, id: NodeId) {
+        let ast = self.ast;
+        let tag = self.infer(id);
+        self.tags[id.index()] = tag;
+
+        let children = ast.children(id);
+        match ast.kind(id) {
+            NodeKind::Argument { name } => {
+                if let Some(&value) = children.first() {
+                    self.argument(id, *name, value);
+                }
+            }
+            // a function without a tag may return anything, pawncc takes its
+            // tag from what it returns
+            NodeKind::Return => {
+                if let (Some(&value), false) = (children.first(), self.function_tag == "_") {
+                    self.expect(&[self.function_tag], self.tag(value), ast.span(value));
+                }
+            }
+            NodeKind::Parameter { tags, .. } => {
+                let default = children
+                    .last()
+                    .filter(|&&child| ast.kind(child) != &NodeKind::Dimension);
+                if let Some(&default) = default {
+                    if let NodeKind::String(_) = ast.kind(default) {
+                        return;
+                    }
+                    self.expect(tags, self.tag(default), ast.span(default));
+                }
+            }
+            // the elements of an array initialiser are each checked on their
+            // own, and strings are arrays of characters
+            NodeKind::Array { .. } | NodeKind::String(_) | NodeKind::Dimension => {}
+            _ => {
+                if let Some(expected) = self.initialised(id) {
+                    self.expect(&[expected], tag, ast.span(id));
+                }
+            }
+        }
+    }
+
+    fn tag(&self, id: NodeId) -> &'a str {
+        self.tags[id.index()]
+    }
+
+    // infer returns the tag of an expression being left, the expressions
+    // inside it having been checked already.
+    fn infer(&mut self, id: NodeId) -> &'a str {
+        let ast = self.ast;
+        let children = ast.children(id);
+        match ast.kind(id) {
+            NodeKind::Float(_) => "Float",
+            NodeKind::Bool(_) => "bool",
+            NodeKind::Symbol(_) => match self.references.get(&id) {
+                Some(&symbol) => &self.table.symbol(symbol).tag,
+                None => "_",
+            },
+            NodeKind::TagOverride(tag) => tag,
+            NodeKind::UnaryOperation { operator, .. } => {
+                self.unary(operator.token_type, children[0])
+            }
+            NodeKind::BinaryOperation(operator) => {
+                self.binary(operator.token_type, children[0], children[1])
+            }
+            NodeKind::TernaryOperation => {
+                let left = self.tag(children[1]);
+                let right = self.tag(children[2]);
+                if !compatible(left, right) {
+                    self.mismatch(&[left], right, ast.span(children[2]));
+                }
+                left
+            }
+            NodeKind::Call => self.tag(children[0]),
+            NodeKind::Index => self.index(id, children[0], children[1]),
+            _ => "_",
+        }
+    }
+
+    // index returns the tag of an element of an array, which is the tag of
+    // the array unless a dimension sized by an enum is indexed by a member
+    // with a tag of its own, such as `Float:E_HEALTH`. Other indices of such
+    // a dimension must have the tag of the enum.
+    fn index(&mut self, id: NodeId, array: NodeId, index: NodeId) -> &'a str {
+        let ast = self.ast;
+        let layouts = self.layouts;
+        let tag = self.tag(array);
+
+        // an index into an index is into the next dimension of the same array
+        let (enums, depth) = match ast.kind(array) {
+            NodeKind::Index => match self.indices.get(&array) {
+                Some(&(enums, depth)) => (enums, depth + 1),
+                None => return tag,
+            },
+            NodeKind::Symbol(_) => match self
+                .references
+                .get(&array)
+                .and_then(|symbol| layouts.arrays.get(symbol))
+            {
+                Some(enums) => (enums.as_slice(), 0),
+                None => return tag,
+            },
+            _ => return tag,
+        };
+        self.indices.insert(id, (enums, depth));
+
+        let e = match enums.get(depth) {
+            Some(&Some(e)) => e,
+            _ => return tag,
+        };
+        let found = self.tag(index);
+        let is_member = match ast.kind(index) {
+            NodeKind::Symbol(_) => {
+                self.references
+                    .get(&index)
+                    .and_then(|member| layouts.members.get(member))
+                    == Some(&e)
+            }
+            _ => false,
+        };
+        let expected = self.table[e].tag.as_str();
+        if !is_member {
+            self.expect(&[expected], found, ast.span(index));
+            tag
+        } else if found != expected {
+            // a member without a tag of its own has the enum's
+            found
+        } else {
+            tag
+        }
+    }
+
+    fn unary(&mut self, operator: TokenType, operand: NodeId) -> &'a str {
+        match operator {
+            t if is_unevaluated(t) => "_",
+            TokenType::Char => "_",
+            TokenType::Bang => {
+                let operand = self.tag(operand);
+                self.user_operator(TokenType::Bang, &[operand])
+                    .unwrap_or("bool")
+            }
+            operator => {
+                let operand = self.tag(operand);
+                self.user_operator(operator, &[operand]).unwrap_or(operand)
+            }
+        }
+    }
+
+    fn binary(&mut self, operator: TokenType, left: NodeId, right: NodeId) -> &'a str {
+        let span = self.ast.span(right);
+        let (left, right) = (self.tag(left), self.tag(right));
+        match operator {
+            TokenType::Comma => right,
+            TokenType::And | TokenType::Or => "bool",
+            TokenType::Assign => {
+                // `operator=` converts the value assigned to the tag it returns
+                let converted = self.user_operator(TokenType::Assign, &[right]);
+                if converted != Some(left) {
+                    self.expect(&[left], right, span);
+                }
+                left
+            }
+            t if is_assignment(t) => {
+                self.operands(compound_operator(t), left, right, span);
+                left
+            }
+            t if is_comparison(t) => self.operands(t, left, right, span).unwrap_or("bool"),
+            t => self.operands(t, left, right, span).unwrap_or(left),
+        }
+    }
+
+    // operands checks the operands of a binary operator, returning the tag of
+    // the result if a user-defined operator takes them.
+    fn operands(
+        &mut self,
+        operator: TokenType,
+        left: &str,
+        right: &str,
+        span: Span,
+    ) -> Option<&'a str> {
+        let tag = self.user_operator(operator, &[left, right]);
+        if tag.is_none() && !compatible(left, right) {
+            self.mismatch(&[left], right, span);
+        }
+        tag
+    }
+
+    // argument checks an argument of a call against the parameter it is
+    // given for, if the function called is known.
+    fn argument(&mut self, argument: NodeId, name: Option<Symbol>, value: NodeId) {
+        let ast = self.ast;
+        let functions = self.functions;
+        let call = match ast.parent(argument) {
+            Some(call) => ast.children(call),
+            None => return,
+        };
+        let parameters = match ast.kind(call[0]) {
+            NodeKind::Symbol(_) => self
+                .references
+                .get(&call[0])
+                .and_then(|id| functions.get(id)),
+            _ => None,
+        };
+        let parameters = match parameters {
+            Some(parameters) => parameters,
+            None => return,
+        };
+        let parameter = match name {
+            Some(name) => parameters.iter().find(|p| p.name == Some(name)),
+            None => {
+                let i = call[1..].iter().position(|&a| a == argument).unwrap_or(0);
+                parameters
+                    .get(i)
+                    .or_else(|| parameters.last().filter(|p| p.is_variadic))
+            }
+        };
+        if let Some(parameter) = parameter {
+            self.expect(parameter.tags, self.tag(value), ast.span(value));
+        }
+    }
+
+    // initialised returns the tag of the variable an expression initialises,
+    // either on its own or as an element of an array.
+    fn initialised(&self, id: NodeId) -> Option<&'a str> {
+        let ast = self.ast;
+        let mut id = id;
+        loop {
+            let parent = ast.parent(id)?;
+            match ast.kind(parent) {
+                NodeKind::Array { .. } => id = parent,
+                NodeKind::Variable { tag, .. } => return Some(tag),
+                _ => return None,
+            }
+        }
+    }
+
+    // user_operator returns the tag of the user-defined operator that takes
+    // operands of the given tags, if there is one. As in pawncc, operators
+    // which do not care about the order of their operands are also found with
+    // them swapped, and untagged operands never use one.
+    fn user_operator(&self, operator: TokenType, operands: &[&str]) -> Option<&'a str> {
+        if operands.iter().all(|tag| *tag == "_") {
+            return None;
+        }
+        let operators = self.operators;
+        let find = |operands: &[&str]| {
+            operators
+                .iter()
+                .find(|o| o.operator == operator && o.operands == operands)
+                .map(|o| o.tag)
+        };
+        match operands {
+            [left, right] if is_commutative(operator) => {
+                find(operands).or_else(|| find(&[right, left]))
+            }
+            _ => find(operands),
+        }
+    }
+
+    // expect reports a value whose tag can not be taken for any of the tags
+    // expected.
+    fn expect<T: AsRef<str>>(&mut self, expected: &[T], found: &str, span: Span) {
+        if !expected.iter().any(|tag| assignable(tag.as_ref(), found)) {
+            self.mismatch(expected, found, span);
+        }
+    }
+
+    fn mismatch<T: AsRef<str>>(&mut self, expected: &[T], found: &str, span: Span) {
+        let error = ParseError::TagMismatch {
+            expected: expected
+                .iter()
+                .map(|tag| String::from(tag.as_ref()))
+                .collect(),
+            found: String::from(found),
+        };
+        self.diagnostics.push(Diagnostic::new(error, span));
+    }
+}
+
+// is_strong reports whether a tag is strong, which is when its name starts
+// with an upper case letter.
+fn is_strong(tag: &str) -> bool {
+    tag.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+// assignable reports whether a value of one tag can be given where a value
+// of another is expected.
+fn assignable(expected: &str, found: &str) -> bool {
+    expected == found || (expected == "_" && !is_strong(found))
+}
+
+// compatible reports whether two tags can be the operands of one operator.
+fn compatible(left: &str, right: &str) -> bool {
+    assignable(left, right) || assignable(right, left)
+}
+
+fn first_tag(tags: &[String]) -> &str {
+    tags.first().map_or("_", |tag| tag.as_str())
+}
+
+fn is_unevaluated(operator: TokenType) -> bool {
+    matches!(
+        operator,
+        TokenType::Tagof | TokenType::Sizeof | TokenType::Defined
+    )
+}
+
+fn is_comparison(operator: TokenType) -> bool {
+    matches!(
+        operator,
+        TokenType::Equal
+            | TokenType::NotEqual
+            | TokenType::LowerThan
+            | TokenType::LowerThanEqual
+            | TokenType::GreaterThan
+            | TokenType::GreaterThanEqual
+    )
+}
+
+fn is_commutative(operator: TokenType) -> bool {
+    matches!(
+        operator,
+        TokenType::Plus | TokenType::Asterisk | TokenType::Equal | TokenType::NotEqual
+    )
+}
@@ -0,0 +1,233 @@
+#[cfg(test)]
+use crate::ast::{Argument, Case, EnumMember, Expression, Node, Parameter, Spanned, Statement};
+#[cfg(test)]
+use crate::error::{Diagnostic, ParseError};
+#[cfg(test)]
+use crate::fold::{self, Fold};
+#[cfg(test)]
+use crate::tags::check;
+#[cfg(test)]
+use crate::test_utils::parse;
+#[cfg(test)]
+use crate::token::Span;
+
+// mismatches lists the tags expected, the tag found and the position of each
+// mismatch.
+#[cfg(test)]
+fn mismatches(diagnostics: &[Diagnostic]) -> Vec<(Vec<&str>, &str, i32, i32)> {
+    diagnostics
+        .iter()
+        .map(|d| match &d.error {
+            ParseError::TagMismatch { expected, found } => (
+                expected.iter().map(String::as_str).collect(),
+                found.as_str(),
+                d.span.line_start,
+                d.span.column_start,
+            ),
+            e => panic!("unexpected error {:?}", e),
+        })
+        .collect()
+}
+
+#[test]
+fn test_tags_strong_and_weak() {
+    let root = parse(
+        "new Float:f = 1.0;\n\
+         new Float:g = 1;\n\
+         new a = f;\n\
+         new bool:b = true;\n\
+         new c = b;\n\
+         new bool:d = 1;\n\
+         new Float:h = Float:1;\n\
+         new i = _:f;\n\
+         new Float:arr[2] = {1.0, 2};\n\
+         enum E { A, Float:B }\n\
+         new j = A;\n\
+         new E:k = A;\n\
+         new Float:l = B;",
+    );
+
+    assert_eq!(
+        mismatches(&check(&root)),
+        vec![
+            (vec!["Float"], "_", 2, 15),
+            // an untagged variable can not hold a strong tag
+            (vec!["_"], "Float", 3, 9),
+            // but it can hold a weak one, while a weak variable still needs
+            // its own tag
+            (vec!["bool"], "_", 6, 14),
+            (vec!["Float"], "_", 9, 26),
+            // enum members take the tag of their enum
+            (vec!["_"], "E", 11, 9),
+        ]
+    );
+}
+
+#[test]
+fn test_tags_operators() {
+    let root = parse(
+        "native Float:operator*(Float:a, Float:b) = floatmul;\n\
+         native Float:operator+(Float:a, b) = floatadd;\n\
+         native bool:operator>(Float:a, Float:b) = floatcmp;\n\
+         f() {\n\
+         \x20   new Float:x = 1.0, Float:y = 2.0;\n\
+         \x20   x = x * y;\n\
+         \x20   x = x + 1;\n\
+         \x20   x = 1 + x;\n\
+         \x20   x = x - 1;\n\
+         \x20   x += 1;\n\
+         \x20   new a = x > y;\n\
+         \x20   new b = x == 1;\n\
+         \x20   if (x && 1) {}\n\
+         \x20   new Float:c = a ? x : 2;\n\
+         \x20   new d = !x;\n\
+         }",
+    );
+
+    assert_eq!(
+        mismatches(&check(&root)),
+        vec![
+            // there is no `-` for floats and integers
+            (vec!["Float"], "_", 9, 13),
+            (vec!["Float"], "_", 12, 18),
+            (vec!["Float"], "_", 14, 27),
+        ]
+    );
+}
+
+#[test]
+fn test_tags_calls_and_returns() {
+    let root = parse(
+        "native printf(const format[], {Float,_}:...);\n\
+         native Float:float(value);\n\
+         g(Float:a, b = 0, Float:c = 0) { return a; }\n\
+         Float:h() { return 1; }\n\
+         main() {\n\
+         \x20   new Float:x = float(1);\n\
+         \x20   new Fixed:q;\n\
+         \x20   printf(\"%f %d\", x, 1, q);\n\
+         \x20   g(1);\n\
+         \x20   g(.b = x, .a = x);\n\
+         \x20   new t = tagof(Float:), u = tagof x;\n\
+         }",
+    );
+    let diagnostics = check(&root);
+
+    assert_eq!(
+        mismatches(&diagnostics),
+        vec![
+            // the default of a parameter is checked against its tag
+            (vec!["Float"], "_", 3, 29),
+            // only functions with a tag have their returns checked
+            (vec!["Float"], "_", 4, 20),
+            (vec!["Float", "_"], "Fixed", 8, 27),
+            (vec!["Float"], "_", 9, 7),
+            (vec!["_"], "Float", 10, 12),
+        ]
+    );
+    assert_eq!(
+        diagnostics[2].to_string(),
+        "8:27: warning 213: tag mismatch: expected tags \"Float\" or none (\"_\"), but found \"Fixed\""
+    );
+    assert!(diagnostics[2].error.is_warning());
+}
+
+#[test]
+fn test_tags_brackets_and_overrides() {
+    let root = parse(
+        "main() {\n\
+         \x20   new Float:a;\n\
+         \x20   new b = _:a, Float:c = (a), Float:d = ((a)), e = (a);\n\
+         \x20   new Float:f = _:(a), g = tagof(a);\n\
+         }",
+    );
+
+    // a symbol in brackets or under a tag override is still found, and the
+    // override is not mistaken for the symbol inside it
+    assert_eq!(
+        mismatches(&check(&root)),
+        vec![(vec!["_"], "Float", 3, 54), (vec!["Float"], "_", 4, 19)]
+    );
+}
+
+#[test]
+fn test_tags_enum_indices() {
+    let root = parse(
+        "enum E_PLAYER { E_NAME[24], Float:E_HEALTH, E_ID };\n\
+         new gPlayerData[10][E_PLAYER];\n\
+         main() { gPlayerData[0][E_HEALTH] = 100.0; new Float:h = gPlayerData[0][E_HEALTH]; }\n\
+         f(data[E_PLAYER]) { new id = data[E_ID], Float:x = data[E_ID]; data[5] = 0; }",
+    );
+
+    // a member with a tag of its own gives the element its tag, any other
+    // index of an enum's dimension must have the enum's tag
+    assert_eq!(
+        mismatches(&check(&root)),
+        vec![(vec!["Float"], "_", 4, 52), (vec!["E_PLAYER"], "_", 4, 69)]
+    );
+}
+
+// Unspan gives every node of a tree the same empty span.
+#[cfg(test)]
+struct Unspan;
+
+#[cfg(test)]
+impl Fold for Unspan {
+    fn fold_node(&mut self, node: Node) -> Node {
+        Node {
+            span: Span::default(),
+            ..fold::walk_node(self, node)
+        }
+    }
+
+    fn fold_expression(&mut self, expr: Spanned<Expression>) -> Spanned<Expression> {
+        Spanned::from(fold::walk_expression(self, expr.node))
+    }
+
+    fn fold_statement(&mut self, statement: Spanned<Statement>) -> Spanned<Statement> {
+        Spanned::from(fold::walk_statement(self, statement.node))
+    }
+
+    fn fold_parameter(&mut self, parameter: Spanned<Parameter>) -> Spanned<Parameter> {
+        Spanned::from(fold::walk_parameter(self, parameter.node))
+    }
+
+    fn fold_enum_member(&mut self, member: Spanned<EnumMember>) -> Spanned<EnumMember> {
+        Spanned::from(fold::walk_enum_member(self, member.node))
+    }
+
+    fn fold_argument(&mut self, argument: Spanned<Argument>) -> Spanned<Argument> {
+        Spanned::from(fold::walk_argument(self, argument.node))
+    }
+
+    fn fold_case(&mut self, case: Spanned<Case>) -> Spanned<Case> {
+        Spanned::from(fold::walk_case(self, case.node))
+    }
+}
+
+#[test]
+fn test_tags_without_spans() {
+    let root = parse(
+        "enum E { A, Float:B }\n\
+         new Float:f = 1.0, g, data[E];\n\
+         h(Float:x) { return _:x; }\n\
+         main() { g = f; f = g; new Float:i = data[B], j = data[g]; h(.x = g); }",
+    );
+
+    // names are found by the node they are used at, so a tree whose nodes
+    // all share one span is checked the same as the one it came from
+    let found = |diagnostics: &[Diagnostic]| -> Vec<(Vec<String>, String)> {
+        mismatches(diagnostics)
+            .into_iter()
+            .map(|(expected, found, _, _)| {
+                (
+                    expected.into_iter().map(String::from).collect(),
+                    found.into(),
+                )
+            })
+            .collect()
+    };
+    let expected = found(&check(&root));
+    assert_eq!(expected.len(), 4);
+    assert_eq!(found(&check(&Unspan.fold_node(root))), expected);
+}
@@ -0,0 +1,25 @@
+#[cfg(test)]
+use crate::arena::Ast;
+#[cfg(test)]
+use crate::ast::{Node, Parser};
+#[cfg(test)]
+use crate::intern::Interner;
+#[cfg(test)]
+use crate::lexer::Lexer;
+
+// parse parses a whole file which must have no errors, returning its root.
+#[cfg(test)]
+pub(crate) fn parse(source: &str) -> Node {
+    let mut p = Parser::new(Lexer::new(source).lex());
+    if let Err(diagnostics) = p.parse() {
+        panic!("failed to parse {:?}: {:?}", source, diagnostics);
+    }
+    p.root
+}
+
+// arena parses a whole file like parse, returning it as an arena whose names
+// are interned with `interner`.
+#[cfg(test)]
+pub(crate) fn arena(source: &str, interner: &mut Interner) -> Ast {
+    Ast::new(&parse(source), interner)
+}
@@ -1,4 +1,8 @@
-#[derive(Debug, PartialEq)]
+use std::fmt;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub token_type: TokenType,
     pub value: Option<TokenValue>,
@@ -8,7 +12,175 @@ pub struct Token {
     pub column_end: i32,
 }
 
-#[derive(Debug, PartialEq)]
+impl Token {
+    pub fn span(&self) -> Span {
+        Span {
+            line_start: self.line_start,
+            line_end: self.line_end,
+            column_start: self.column_start,
+            column_end: self.column_end,
+        }
+    }
+}
+
+/// Span is a range of source code, lines and columns start at 1 and the end
+/// column is exclusive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub line_start: i32,
+    pub line_end: i32,
+    pub column_start: i32,
+    pub column_end: i32,
+}
+
+impl Span {
+    /// to returns a span from the start of this span to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            line_start: self.line_start,
+            line_end: other.line_end,
+            column_start: self.column_start,
+            column_end: other.column_end,
+        }
+    }
+
+    /// end returns an empty span at the end of this span.
+    pub fn end(self) -> Span {
+        Span {
+            line_start: self.line_end,
+            line_end: self.line_end,
+            column_start: self.column_end,
+            column_end: self.column_end,
+        }
+    }
+
+    /// start_position returns the position of the first character of the span.
+    pub fn start_position(self) -> Position {
+        Position::new(self.line_start, self.column_start)
+    }
+
+    /// end_position returns the position just past the end of the span.
+    pub fn end_position(self) -> Position {
+        Position::new(self.line_end, self.column_end)
+    }
+
+    /// contains reports whether a position falls within the span, the end is
+    /// exclusive so a position between two adjacent spans belongs to the
+    /// second.
+    pub fn contains(self, position: Position) -> bool {
+        self.start_position() <= position && position < self.end_position()
+    }
+
+    /// covers reports whether `other` lies entirely within the span.
+    pub fn covers(self, other: Span) -> bool {
+        self.start_position() < self.end_position()
+            && self.start_position() <= other.start_position()
+            && other.end_position() <= self.end_position()
+    }
+}
+
+/// Position is a single point in source code, such as the cursor of an
+/// editor. Lines and columns start at 1 like in `Span`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub line: i32,
+    pub column: i32,
+}
+
+impl Position {
+    pub fn new(line: i32, column: i32) -> Position {
+        Position { line, column }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line_start, self.column_start)
+    }
+}
+
+/// LineIndex converts the line and column positions held by tokens into byte
+/// offsets of the source they were lexed from.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    // the byte offset each line starts at and whether the line is plain ASCII,
+    // in which case columns and bytes line up.
+    lines: Vec<(usize, bool)>,
+    len: usize,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> LineIndex {
+        let mut lines = vec![];
+        let mut start = 0;
+        for line in source.split_inclusive('\n') {
+            lines.push((start, line.is_ascii()));
+            start += line.len();
+        }
+        if lines.is_empty() || source.ends_with('\n') {
+            lines.push((start, true));
+        }
+        LineIndex {
+            lines,
+            len: source.len(),
+        }
+    }
+
+    /// offset returns the byte offset of a line and column in `source`, which
+    /// must be the same source the index was built from.
+    pub fn offset(&self, source: &str, line: i32, column: i32) -> usize {
+        let (start, ascii) = match self.lines.get((line - 1).max(0) as usize) {
+            Some(&line) => line,
+            None => return self.len,
+        };
+        let column = (column - 1).max(0) as usize;
+        let offset = if ascii {
+            start + column
+        } else {
+            start
+                + source[start..]
+                    .chars()
+                    .take(column)
+                    .map(char::len_utf8)
+                    .sum::<usize>()
+        };
+        offset.min(self.len)
+    }
+
+    /// position returns the line and column of a byte offset in `source`, the
+    /// inverse of `offset`.
+    pub fn position(&self, source: &str, offset: usize) -> Position {
+        let offset = offset.min(self.len);
+        let line = self.lines.partition_point(|&(start, _)| start <= offset) - 1;
+        let (start, ascii) = self.lines[line];
+        let column = if ascii {
+            offset - start
+        } else {
+            source[start..]
+                .char_indices()
+                .take_while(|&(i, _)| start + i < offset)
+                .count()
+        };
+        Position::new(line as i32 + 1, column as i32 + 1)
+    }
+
+    /// range returns the byte range covered by a span.
+    pub fn range(&self, source: &str, span: Span) -> Range<usize> {
+        self.offset(source, span.line_start, span.column_start)
+            ..self.offset(source, span.line_end, span.column_end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenValue {
     String(String),
     Integer(i32),
@@ -23,60 +195,71 @@ impl TokenValue {
             TokenValue::Float(v) => v.to_string().len(),
         }
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TokenType {
+    #[default]
     Illegal,
     End,
 
     // -
     // Symbols
     // -
-    Equal,            // ==
-    Assign,           // =
-    Plus,             // +
-    PlusPlus,         // ++
-    PlusAssign,       // +=
-    Minus,            // -
-    MinusMinus,       // --
-    MinusAssign,      // -=
-    Asterisk,         // *
-    AsteriskAssign,   // *=
-    Slash,            // /
-    SlashAssign,      // /=
-    Percent,          // %
-    PercentAssign,    // %=
-    And,              // &&
-    BitAnd,           // &
-    BitAndAssign,     // &=
-    Or,               // ||
-    BitOr,            // |
-    BitOrAssign,      // |=
-    BitXor,           // ^
-    BitXorAssign,     // ^=
-    LowerThan,        // <
-    LowerThanEqual,   // <=
-    BitLeft,          // <<
-    BitLeftAssign,    // <<=
-    GreaterThan,      // >
-    GreaterThanEqual, // >=
-    BitRight,         // >>
-    BitRightAssign,   // >>=
-    NotEqual,         // !=
-    Bang,             // !
-    Colon,            // :
-    Semicolon,        // ;
-    Comma,            // ,
-    LeftBrace,        // {
-    RightBrace,       // }
-    LeftBracket,      // (
-    RightBracket,     // )
-    LeftSquare,       // [
-    RightSquare,      // ]
-    Elipsis,          // ...
-    Range,            // ..
-    Directive,        // #
+    Equal,                 // ==
+    Assign,                // =
+    Plus,                  // +
+    PlusPlus,              // ++
+    PlusAssign,            // +=
+    Minus,                 // -
+    MinusMinus,            // --
+    MinusAssign,           // -=
+    Asterisk,              // *
+    AsteriskAssign,        // *=
+    Slash,                 // /
+    SlashAssign,           // /=
+    Percent,               // %
+    PercentAssign,         // %=
+    And,                   // &&
+    BitAnd,                // &
+    BitAndAssign,          // &=
+    Or,                    // ||
+    BitOr,                 // |
+    BitOrAssign,           // |=
+    BitXor,                // ^
+    BitXorAssign,          // ^=
+    LowerThan,             // <
+    LowerThanEqual,        // <=
+    BitLeft,               // <<
+    BitLeftAssign,         // <<=
+    GreaterThan,           // >
+    GreaterThanEqual,      // >=
+    BitRight,              // >>
+    BitRightAssign,        // >>=
+    BitRightLogical,       // >>>
+    BitRightLogicalAssign, // >>>=
+    NotEqual,              // !=
+    Bang,                  // !
+    Tilde,                 // ~
+    Question,              // ?
+    Colon,                 // :
+    Semicolon,             // ;
+    Comma,                 // ,
+    LeftBrace,             // {
+    RightBrace,            // }
+    LeftBracket,           // (
+    RightBracket,          // )
+    LeftSquare,            // [
+    RightSquare,           // ]
+    Dot,                   // .
+    Elipsis,               // ...
+    Range,                 // ..
+    Directive,             // #
 
     // -
     // Keywords - declaration/definition
@@ -110,107 +293,111 @@ pub enum TokenType {
     Return,   // return
     Sizeof,   // sizeof
     Tagof,    // tagof
+    Defined,  // defined
     Emit,     // __emit
 
     // -
     // Patterns
     // -
-    Integer, // integer number
-    Float,   // floating point number
-    Symbol,  // a-zA-Z0-9_@
-    Label,   // a-zA-Z0-9_
-    Literal, // ".*"
+    Integer,   // integer number
+    Float,     // floating point number
+    Symbol,    // a-zA-Z0-9_@
+    Label,     // a-zA-Z0-9_
+    Literal,   // ".*"
+    Character, // '.'
     Comment,
 }
 
-impl Default for TokenType {
-    fn default() -> TokenType {
-        TokenType::Illegal
-    }
-}
-
-impl ToString for TokenType {
-    fn to_string(&self) -> String {
-        match self {
-            TokenType::Illegal => String::from("Illegal"),
-            TokenType::End => String::from("End"),
-            TokenType::Equal => String::from("=="),
-            TokenType::Assign => String::from("="),
-            TokenType::Plus => String::from("+"),
-            TokenType::PlusPlus => String::from("++"),
-            TokenType::PlusAssign => String::from("+="),
-            TokenType::Minus => String::from("-"),
-            TokenType::MinusMinus => String::from("--"),
-            TokenType::MinusAssign => String::from("-="),
-            TokenType::Asterisk => String::from("*"),
-            TokenType::AsteriskAssign => String::from("*="),
-            TokenType::Slash => String::from("/"),
-            TokenType::SlashAssign => String::from("/="),
-            TokenType::Percent => String::from("%"),
-            TokenType::PercentAssign => String::from("%="),
-            TokenType::And => String::from("&&"),
-            TokenType::BitAnd => String::from("&"),
-            TokenType::BitAndAssign => String::from("&="),
-            TokenType::Or => String::from("||"),
-            TokenType::BitOr => String::from("|"),
-            TokenType::BitOrAssign => String::from("|="),
-            TokenType::BitXor => String::from("^"),
-            TokenType::BitXorAssign => String::from("^="),
-            TokenType::LowerThan => String::from("<"),
-            TokenType::LowerThanEqual => String::from("<="),
-            TokenType::BitLeft => String::from("<<"),
-            TokenType::BitLeftAssign => String::from("<<="),
-            TokenType::GreaterThan => String::from(">"),
-            TokenType::GreaterThanEqual => String::from(">="),
-            TokenType::BitRight => String::from(">>"),
-            TokenType::BitRightAssign => String::from(">>="),
-            TokenType::NotEqual => String::from("!="),
-            TokenType::Bang => String::from("!"),
-            TokenType::Colon => String::from(":"),
-            TokenType::Semicolon => String::from(";"),
-            TokenType::Comma => String::from(","),
-            TokenType::LeftBrace => String::from("{"),
-            TokenType::RightBrace => String::from("}"),
-            TokenType::LeftBracket => String::from("("),
-            TokenType::RightBracket => String::from(")"),
-            TokenType::LeftSquare => String::from("["),
-            TokenType::RightSquare => String::from("]"),
-            TokenType::Elipsis => String::from("..."),
-            TokenType::Range => String::from(".."),
-            TokenType::Directive => String::from("#"),
-            TokenType::Const => String::from("const"),
-            TokenType::New => String::from("new"),
-            TokenType::Static => String::from("static"),
-            TokenType::Stock => String::from("stock"),
-            TokenType::Forward => String::from("forward"),
-            TokenType::Public => String::from("public"),
-            TokenType::Native => String::from("native"),
-            TokenType::Operator => String::from("operator"),
-            TokenType::Char => String::from("char"),
-            TokenType::Enum => String::from("enum"),
-            TokenType::State => String::from("state"),
-            TokenType::If => String::from("if"),
-            TokenType::Else => String::from("else"),
-            TokenType::Switch => String::from("switch"),
-            TokenType::Case => String::from("case"),
-            TokenType::Default => String::from("default"),
-            TokenType::For => String::from("for"),
-            TokenType::While => String::from("while"),
-            TokenType::Do => String::from("do"),
-            TokenType::Break => String::from("break"),
-            TokenType::Continue => String::from("continue"),
-            TokenType::Goto => String::from("goto"),
-            TokenType::Return => String::from("return"),
-            TokenType::Sizeof => String::from("sizeof"),
-            TokenType::Tagof => String::from("tagof"),
-            TokenType::Emit => String::from("__emit"),
-            TokenType::Integer => String::from("Integer"),
-            TokenType::Float => String::from("Float"),
-            TokenType::Symbol => String::from("Symbol"),
-            TokenType::Label => String::from("Label"),
-            TokenType::Literal => String::from("Literal"),
-            TokenType::Comment => String::from("Comment"),
-        }
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            TokenType::Illegal => "Illegal",
+            TokenType::End => "End",
+            TokenType::Equal => "==",
+            TokenType::Assign => "=",
+            TokenType::Plus => "+",
+            TokenType::PlusPlus => "++",
+            TokenType::PlusAssign => "+=",
+            TokenType::Minus => "-",
+            TokenType::MinusMinus => "--",
+            TokenType::MinusAssign => "-=",
+            TokenType::Asterisk => "*",
+            TokenType::AsteriskAssign => "*=",
+            TokenType::Slash => "/",
+            TokenType::SlashAssign => "/=",
+            TokenType::Percent => "%",
+            TokenType::PercentAssign => "%=",
+            TokenType::And => "&&",
+            TokenType::BitAnd => "&",
+            TokenType::BitAndAssign => "&=",
+            TokenType::Or => "||",
+            TokenType::BitOr => "|",
+            TokenType::BitOrAssign => "|=",
+            TokenType::BitXor => "^",
+            TokenType::BitXorAssign => "^=",
+            TokenType::LowerThan => "<",
+            TokenType::LowerThanEqual => "<=",
+            TokenType::BitLeft => "<<",
+            TokenType::BitLeftAssign => "<<=",
+            TokenType::GreaterThan => ">",
+            TokenType::GreaterThanEqual => ">=",
+            TokenType::BitRight => ">>",
+            TokenType::BitRightAssign => ">>=",
+            TokenType::BitRightLogical => ">>>",
+            TokenType::BitRightLogicalAssign => ">>>=",
+            TokenType::NotEqual => "!=",
+            TokenType::Bang => "!",
+            TokenType::Tilde => "~",
+            TokenType::Question => "?",
+            TokenType::Colon => ":",
+            TokenType::Semicolon => ";",
+            TokenType::Comma => ",",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::LeftBracket => "(",
+            TokenType::RightBracket => ")",
+            TokenType::LeftSquare => "[",
+            TokenType::RightSquare => "]",
+            TokenType::Dot => ".",
+            TokenType::Elipsis => "...",
+            TokenType::Range => "..",
+            TokenType::Directive => "#",
+            TokenType::Const => "const",
+            TokenType::New => "new",
+            TokenType::Static => "static",
+            TokenType::Stock => "stock",
+            TokenType::Forward => "forward",
+            TokenType::Public => "public",
+            TokenType::Native => "native",
+            TokenType::Operator => "operator",
+            TokenType::Char => "char",
+            TokenType::Enum => "enum",
+            TokenType::State => "state",
+            TokenType::If => "if",
+            TokenType::Else => "else",
+            TokenType::Switch => "switch",
+            TokenType::Case => "case",
+            TokenType::Default => "default",
+            TokenType::For => "for",
+            TokenType::While => "while",
+            TokenType::Do => "do",
+            TokenType::Break => "break",
+            TokenType::Continue => "continue",
+            TokenType::Goto => "goto",
+            TokenType::Return => "return",
+            TokenType::Sizeof => "sizeof",
+            TokenType::Tagof => "tagof",
+            TokenType::Defined => "defined",
+            TokenType::Emit => "__emit",
+            TokenType::Integer => "Integer",
+            TokenType::Float => "Float",
+            TokenType::Symbol => "Symbol",
+            TokenType::Label => "Label",
+            TokenType::Literal => "Literal",
+            TokenType::Character => "Character",
+            TokenType::Comment => "Comment",
+        };
+        f.write_str(s)
     }
 }
 
@@ -242,6 +429,7 @@ pub fn lookup_keyword(kw: &str) -> Option<TokenType> {
         "return" => Some(TokenType::Return),
         "sizeof" => Some(TokenType::Sizeof),
         "tagof" => Some(TokenType::Tagof),
+        "defined" => Some(TokenType::Defined),
         "__emit" => Some(TokenType::Emit),
 
         _ => None,
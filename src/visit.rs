@@ -0,0 +1,362 @@
+use crate::ast::Argument;
+use crate::ast::Array;
+use crate::ast::BinaryOperation;
+use crate::ast::Call;
+use crate::ast::Case;
+use crate::ast::CaseLabel;
+use crate::ast::DoWhile;
+use crate::ast::Enum;
+use crate::ast::EnumMember;
+use crate::ast::Expression;
+use crate::ast::For;
+use crate::ast::Function;
+use crate::ast::If;
+use crate::ast::Index;
+use crate::ast::Literal;
+use crate::ast::Node;
+use crate::ast::Parameter;
+use crate::ast::Spanned;
+use crate::ast::StateTransition;
+use crate::ast::Statement;
+use crate::ast::Switch;
+use crate::ast::TagOverride;
+use crate::ast::TernaryOperation;
+use crate::ast::UnaryOperation;
+use crate::ast::Variable;
+use crate::ast::While;
+
+/// Visitor walks a syntax tree by reference. Every method defaults to the
+/// matching `walk_` function which visits the children of the node, so an
+/// implementation only overrides the nodes it cares about and calls the
+/// `walk_` function itself to keep descending.
+pub trait Visitor {
+    fn visit_node(&mut self, node: &Node) {
+        walk_node(self, node)
+    }
+
+    fn visit_expression(&mut self, expr: &Spanned<Expression>) {
+        walk_expression(self, &expr.node)
+    }
+
+    fn visit_statement(&mut self, statement: &Spanned<Statement>) {
+        walk_statement(self, &statement.node)
+    }
+
+    fn visit_variable(&mut self, variable: &Variable) {
+        walk_variable(self, variable)
+    }
+
+    fn visit_function(&mut self, function: &Function) {
+        walk_function(self, function)
+    }
+
+    fn visit_parameter(&mut self, parameter: &Spanned<Parameter>) {
+        walk_parameter(self, parameter)
+    }
+
+    fn visit_enum(&mut self, e: &Enum) {
+        walk_enum(self, e)
+    }
+
+    fn visit_enum_member(&mut self, member: &Spanned<EnumMember>) {
+        walk_enum_member(self, member)
+    }
+
+    fn visit_binary_operation(&mut self, op: &BinaryOperation) {
+        walk_binary_operation(self, op)
+    }
+
+    fn visit_unary_operation(&mut self, op: &UnaryOperation) {
+        walk_unary_operation(self, op)
+    }
+
+    fn visit_ternary_operation(&mut self, op: &TernaryOperation) {
+        walk_ternary_operation(self, op)
+    }
+
+    fn visit_tag_override(&mut self, tag: &TagOverride) {
+        walk_tag_override(self, tag)
+    }
+
+    fn visit_call(&mut self, call: &Call) {
+        walk_call(self, call)
+    }
+
+    fn visit_argument(&mut self, argument: &Spanned<Argument>) {
+        walk_argument(self, argument)
+    }
+
+    fn visit_index(&mut self, index: &Index) {
+        walk_index(self, index)
+    }
+
+    fn visit_literal(&mut self, literal: &Literal) {
+        walk_literal(self, literal)
+    }
+
+    fn visit_array(&mut self, array: &Array) {
+        walk_array(self, array)
+    }
+
+    /// visit_symbol is called for every symbol used in an expression, names
+    /// being declared are part of the declaring node instead.
+    fn visit_symbol(&mut self, _name: &str) {}
+
+    fn visit_if(&mut self, s: &If) {
+        walk_if(self, s)
+    }
+
+    fn visit_while(&mut self, s: &While) {
+        walk_while(self, s)
+    }
+
+    fn visit_do_while(&mut self, s: &DoWhile) {
+        walk_do_while(self, s)
+    }
+
+    fn visit_for(&mut self, s: &For) {
+        walk_for(self, s)
+    }
+
+    fn visit_switch(&mut self, s: &Switch) {
+        walk_switch(self, s)
+    }
+
+    fn visit_case(&mut self, case: &Spanned<Case>) {
+        walk_case(self, case)
+    }
+
+    fn visit_case_label(&mut self, label: &CaseLabel) {
+        walk_case_label(self, label)
+    }
+
+    fn visit_state_transition(&mut self, s: &StateTransition) {
+        walk_state_transition(self, s)
+    }
+}
+
+pub fn walk_node<V: Visitor + ?Sized>(v: &mut V, node: &Node) {
+    walk_expression(v, &node.expr);
+    for child in &node.children {
+        v.visit_node(child);
+    }
+}
+
+/// walk_expression dispatches an expression to the visitor method for its
+/// kind. It takes the bare expression since the expressions held by a `Node`
+/// have no span of their own.
+pub fn walk_expression<V: Visitor + ?Sized>(v: &mut V, expr: &Expression) {
+    match expr {
+        Expression::Variable(variable) => v.visit_variable(variable),
+        Expression::Function(function) => v.visit_function(function),
+        Expression::Enum(e) => v.visit_enum(e),
+        Expression::BinaryOperation(op) => v.visit_binary_operation(op),
+        Expression::UnaryOperation(op) => v.visit_unary_operation(op),
+        Expression::TernaryOperation(op) => v.visit_ternary_operation(op),
+        Expression::TagOverride(tag) => v.visit_tag_override(tag),
+        Expression::Call(call) => v.visit_call(call),
+        Expression::Index(index) => v.visit_index(index),
+        Expression::Selector(array) => v.visit_expression(array),
+        Expression::Literal(literal) => v.visit_literal(literal),
+        Expression::Symbol(name) => v.visit_symbol(name),
+        Expression::Tag(_) | Expression::GlobalScope | Expression::Error => {}
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(v: &mut V, statement: &Statement) {
+    match statement {
+        Statement::Block(statements) => {
+            for statement in statements {
+                v.visit_statement(statement);
+            }
+        }
+        Statement::Declaration(nodes) => {
+            for node in nodes {
+                v.visit_node(node);
+            }
+        }
+        Statement::Expression(expr) => v.visit_expression(expr),
+        Statement::If(s) => v.visit_if(s),
+        Statement::While(s) => v.visit_while(s),
+        Statement::DoWhile(s) => v.visit_do_while(s),
+        Statement::For(s) => v.visit_for(s),
+        Statement::Switch(s) => v.visit_switch(s),
+        Statement::State(s) => v.visit_state_transition(s),
+        Statement::Return(expr) => {
+            if let Some(expr) = expr {
+                v.visit_expression(expr);
+            }
+        }
+        Statement::Label(_)
+        | Statement::Goto(_)
+        | Statement::Break
+        | Statement::Continue
+        | Statement::Empty
+        | Statement::Error => {}
+    }
+}
+
+pub fn walk_variable<V: Visitor + ?Sized>(v: &mut V, variable: &Variable) {
+    for dimension in variable.dimensions.iter().flatten() {
+        v.visit_expression(dimension);
+    }
+    if let Some(initialiser) = &variable.initialiser {
+        v.visit_expression(initialiser);
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(v: &mut V, function: &Function) {
+    for parameter in &function.parameters {
+        v.visit_parameter(parameter);
+    }
+    for statement in function.body.iter().flatten() {
+        v.visit_statement(statement);
+    }
+}
+
+pub fn walk_parameter<V: Visitor + ?Sized>(v: &mut V, parameter: &Parameter) {
+    for dimension in parameter.dimensions.iter().flatten() {
+        v.visit_expression(dimension);
+    }
+    if let Some(default) = &parameter.default {
+        v.visit_expression(default);
+    }
+}
+
+pub fn walk_enum<V: Visitor + ?Sized>(v: &mut V, e: &Enum) {
+    if let Some(increment) = &e.increment {
+        v.visit_expression(&increment.amount);
+    }
+    for member in &e.members {
+        v.visit_enum_member(member);
+    }
+}
+
+pub fn walk_enum_member<V: Visitor + ?Sized>(v: &mut V, member: &EnumMember) {
+    if let Some(size) = &member.size {
+        v.visit_expression(size);
+    }
+    if let Some(value) = &member.value {
+        v.visit_expression(value);
+    }
+}
+
+pub fn walk_binary_operation<V: Visitor + ?Sized>(v: &mut V, op: &BinaryOperation) {
+    v.visit_expression(&op.left);
+    v.visit_expression(&op.right);
+}
+
+pub fn walk_unary_operation<V: Visitor + ?Sized>(v: &mut V, op: &UnaryOperation) {
+    v.visit_expression(&op.operand);
+}
+
+pub fn walk_ternary_operation<V: Visitor + ?Sized>(v: &mut V, op: &TernaryOperation) {
+    v.visit_expression(&op.condition);
+    v.visit_expression(&op.left);
+    v.visit_expression(&op.right);
+}
+
+pub fn walk_tag_override<V: Visitor + ?Sized>(v: &mut V, tag: &TagOverride) {
+    v.visit_expression(&tag.operand);
+}
+
+pub fn walk_call<V: Visitor + ?Sized>(v: &mut V, call: &Call) {
+    v.visit_expression(&call.function);
+    for argument in &call.arguments {
+        v.visit_argument(argument);
+    }
+}
+
+pub fn walk_argument<V: Visitor + ?Sized>(v: &mut V, argument: &Argument) {
+    match argument {
+        Argument::Positional(expr) => v.visit_expression(expr),
+        Argument::Named { value, .. } => v.visit_expression(value),
+        Argument::Skipped => {}
+    }
+}
+
+pub fn walk_index<V: Visitor + ?Sized>(v: &mut V, index: &Index) {
+    v.visit_expression(&index.array);
+    v.visit_expression(&index.index);
+}
+
+pub fn walk_literal<V: Visitor + ?Sized>(v: &mut V, literal: &Literal) {
+    match literal {
+        Literal::Array(array) => v.visit_array(array),
+        Literal::Integer(_)
+        | Literal::Float(_)
+        | Literal::Bool(_)
+        | Literal::Char(_)
+        | Literal::String(_) => {}
+    }
+}
+
+pub fn walk_array<V: Visitor + ?Sized>(v: &mut V, array: &Array) {
+    for element in &array.elements {
+        v.visit_expression(element);
+    }
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(v: &mut V, s: &If) {
+    v.visit_expression(&s.condition);
+    v.visit_statement(&s.body);
+    if let Some(otherwise) = &s.otherwise {
+        v.visit_statement(otherwise);
+    }
+}
+
+pub fn walk_while<V: Visitor + ?Sized>(v: &mut V, s: &While) {
+    v.visit_expression(&s.condition);
+    v.visit_statement(&s.body);
+}
+
+pub fn walk_do_while<V: Visitor + ?Sized>(v: &mut V, s: &DoWhile) {
+    v.visit_statement(&s.body);
+    v.visit_expression(&s.condition);
+}
+
+pub fn walk_for<V: Visitor + ?Sized>(v: &mut V, s: &For) {
+    if let Some(initialiser) = &s.initialiser {
+        v.visit_statement(initialiser);
+    }
+    if let Some(condition) = &s.condition {
+        v.visit_expression(condition);
+    }
+    if let Some(increment) = &s.increment {
+        v.visit_expression(increment);
+    }
+    v.visit_statement(&s.body);
+}
+
+pub fn walk_switch<V: Visitor + ?Sized>(v: &mut V, s: &Switch) {
+    v.visit_expression(&s.subject);
+    for case in &s.cases {
+        v.visit_case(case);
+    }
+    if let Some(default) = &s.default {
+        v.visit_statement(default);
+    }
+}
+
+pub fn walk_case<V: Visitor + ?Sized>(v: &mut V, case: &Case) {
+    for label in &case.labels {
+        v.visit_case_label(label);
+    }
+    v.visit_statement(&case.body);
+}
+
+pub fn walk_case_label<V: Visitor + ?Sized>(v: &mut V, label: &CaseLabel) {
+    match label {
+        CaseLabel::Value(value) => v.visit_expression(value),
+        CaseLabel::Range(from, to) => {
+            v.visit_expression(from);
+            v.visit_expression(to);
+        }
+    }
+}
+
+pub fn walk_state_transition<V: Visitor + ?Sized>(v: &mut V, s: &StateTransition) {
+    if let Some(condition) = &s.condition {
+        v.visit_expression(condition);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::ast::Argument;
+use crate::ast::Array;
+use crate::ast::BinaryOperation;
+use crate::ast::Call;
+use crate::ast::Case;
+use crate::ast::CaseLabel;
+use crate::ast::DoWhile;
+use crate::ast::Enum;
+use crate::ast::EnumMember;
+use crate::ast::Expression;
+use crate::ast::For;
+use crate::ast::Function;
+use crate::ast::If;
+use crate::ast::Index;
+use crate::ast::Literal;
+use crate::ast::Node;
+use crate::ast::Parameter;
+use crate::ast::Spanned;
+use crate::ast::StateTransition;
+use crate::ast::Statement;
+use crate::ast::Switch;
+use crate::ast::TagOverride;
+use crate::ast::TernaryOperation;
+use crate::ast::UnaryOperation;
+use crate::ast::Variable;
+use crate::ast::While;
+
+/// VisitorMut walks a syntax tree by mutable reference to change it in place,
+/// see `Visitor` for how the methods fit together.
+pub trait VisitorMut {
+    fn visit_node(&mut self, node: &mut Node) {
+        walk_node(self, node)
+    }
+
+    fn visit_expression(&mut self, expr: &mut Spanned<Expression>) {
+        walk_expression(self, &mut expr.node)
+    }
+
+    fn visit_statement(&mut self, statement: &mut Spanned<Statement>) {
+        walk_statement(self, &mut statement.node)
+    }
+
+    fn visit_variable(&mut self, variable: &mut Variable) {
+        walk_variable(self, variable)
+    }
+
+    fn visit_function(&mut self, function: &mut Function) {
+        walk_function(self, function)
+    }
+
+    fn visit_parameter(&mut self, parameter: &mut Spanned<Parameter>) {
+        walk_parameter(self, parameter)
+    }
+
+    fn visit_enum(&mut self, e: &mut Enum) {
+        walk_enum(self, e)
+    }
+
+    fn visit_enum_member(&mut self, member: &mut Spanned<EnumMember>) {
+        walk_enum_member(self, member)
+    }
+
+    fn visit_binary_operation(&mut self, op: &mut BinaryOperation) {
+        walk_binary_operation(self, op)
+    }
+
+    fn visit_unary_operation(&mut self, op: &mut UnaryOperation) {
+        walk_unary_operation(self, op)
+    }
+
+    fn visit_ternary_operation(&mut self, op: &mut TernaryOperation) {
+        walk_ternary_operation(self, op)
+    }
+
+    fn visit_tag_override(&mut self, tag: &mut TagOverride) {
+        walk_tag_override(self, tag)
+    }
+
+    fn visit_call(&mut self, call: &mut Call) {
+        walk_call(self, call)
+    }
+
+    fn visit_argument(&mut self, argument: &mut Spanned<Argument>) {
+        walk_argument(self, argument)
+    }
+
+    fn visit_index(&mut self, index: &mut Index) {
+        walk_index(self, index)
+    }
+
+    fn visit_literal(&mut self, literal: &mut Literal) {
+        walk_literal(self, literal)
+    }
+
+    fn visit_array(&mut self, array: &mut Array) {
+        walk_array(self, array)
+    }
+
+    /// visit_symbol is called for every symbol used in an expression, names
+    /// being declared are part of the declaring node instead.
+    fn visit_symbol(&mut self, _name: &mut String) {}
+
+    fn visit_if(&mut self, s: &mut If) {
+        walk_if(self, s)
+    }
+
+    fn visit_while(&mut self, s: &mut While) {
+        walk_while(self, s)
+    }
+
+    fn visit_do_while(&mut self, s: &mut DoWhile) {
+        walk_do_while(self, s)
+    }
+
+    fn visit_for(&mut self, s: &mut For) {
+        walk_for(self, s)
+    }
+
+    fn visit_switch(&mut self, s: &mut Switch) {
+        walk_switch(self, s)
+    }
+
+    fn visit_case(&mut self, case: &mut Spanned<Case>) {
+        walk_case(self, case)
+    }
+
+    fn visit_case_label(&mut self, label: &mut CaseLabel) {
+        walk_case_label(self, label)
+    }
+
+    fn visit_state_transition(&mut self, s: &mut StateTransition) {
+        walk_state_transition(self, s)
+    }
+}
+
+pub fn walk_node<V: VisitorMut + ?Sized>(v: &mut V, node: &mut Node) {
+    walk_expression(v, &mut node.expr);
+    for child in &mut node.children {
+        v.visit_node(child);
+    }
+}
+
+/// walk_expression dispatches an expression to the visitor method for its
+/// kind. It takes the bare expression since the expressions held by a `Node`
+/// have no span of their own.
+pub fn walk_expression<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expression) {
+    match expr {
+        Expression::Variable(variable) => v.visit_variable(variable),
+        Expression::Function(function) => v.visit_function(function),
+        Expression::Enum(e) => v.visit_enum(e),
+        Expression::BinaryOperation(op) => v.visit_binary_operation(op),
+        Expression::UnaryOperation(op) => v.visit_unary_operation(op),
+        Expression::TernaryOperation(op) => v.visit_ternary_operation(op),
+        Expression::TagOverride(tag) => v.visit_tag_override(tag),
+        Expression::Call(call) => v.visit_call(call),
+        Expression::Index(index) => v.visit_index(index),
+        Expression::Selector(array) => v.visit_expression(array),
+        Expression::Literal(literal) => v.visit_literal(literal),
+        Expression::Symbol(name) => v.visit_symbol(name),
+        Expression::Tag(_) | Expression::GlobalScope | Expression::Error => {}
+    }
+}
+
+pub fn walk_statement<V: VisitorMut + ?Sized>(v: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::Block(statements) => {
+            for statement in statements {
+                v.visit_statement(statement);
+            }
+        }
+        Statement::Declaration(nodes) => {
+            for node in nodes {
+                v.visit_node(node);
+            }
+        }
+        Statement::Expression(expr) => v.visit_expression(expr),
+        Statement::If(s) => v.visit_if(s),
+        Statement::While(s) => v.visit_while(s),
+        Statement::DoWhile(s) => v.visit_do_while(s),
+        Statement::For(s) => v.visit_for(s),
+        Statement::Switch(s) => v.visit_switch(s),
+        Statement::State(s) => v.visit_state_transition(s),
+        Statement::Return(expr) => {
+            if let Some(expr) = expr {
+                v.visit_expression(expr);
+            }
+        }
+        Statement::Label(_)
+        | Statement::Goto(_)
+        | Statement::Break
+        | Statement::Continue
+        | Statement::Empty
+        | Statement::Error => {}
+    }
+}
+
+pub fn walk_variable<V: VisitorMut + ?Sized>(v: &mut V, variable: &mut Variable) {
+    for dimension in variable.dimensions.iter_mut().flatten() {
+        v.visit_expression(dimension);
+    }
+    if let Some(initialiser) = &mut variable.initialiser {
+        v.visit_expression(initialiser);
+    }
+}
+
+pub fn walk_function<V: VisitorMut + ?Sized>(v: &mut V, function: &mut Function) {
+    for parameter in &mut function.parameters {
+        v.visit_parameter(parameter);
+    }
+    for statement in function.body.iter_mut().flatten() {
+        v.visit_statement(statement);
+    }
+}
+
+pub fn walk_parameter<V: VisitorMut + ?Sized>(v: &mut V, parameter: &mut Parameter) {
+    for dimension in parameter.dimensions.iter_mut().flatten() {
+        v.visit_expression(dimension);
+    }
+    if let Some(default) = &mut parameter.default {
+        v.visit_expression(default);
+    }
+}
+
+pub fn walk_enum<V: VisitorMut + ?Sized>(v: &mut V, e: &mut Enum) {
+    if let Some(increment) = &mut e.increment {
+        v.visit_expression(&mut increment.amount);
+    }
+    for member in &mut e.members {
+        v.visit_enum_member(member);
+    }
+}
+
+pub fn walk_enum_member<V: VisitorMut + ?Sized>(v: &mut V, member: &mut EnumMember) {
+    if let Some(size) = &mut member.size {
+        v.visit_expression(size);
+    }
+    if let Some(value) = &mut member.value {
+        v.visit_expression(value);
+    }
+}
+
+pub fn walk_binary_operation<V: VisitorMut + ?Sized>(v: &mut V, op: &mut BinaryOperation) {
+    v.visit_expression(&mut op.left);
+    v.visit_expression(&mut op.right);
+}
+
+pub fn walk_unary_operation<V: VisitorMut + ?Sized>(v: &mut V, op: &mut UnaryOperation) {
+    v.visit_expression(&mut op.operand);
+}
+
+pub fn walk_ternary_operation<V: VisitorMut + ?Sized>(v: &mut V, op: &mut TernaryOperation) {
+    v.visit_expression(&mut op.condition);
+    v.visit_expression(&mut op.left);
+    v.visit_expression(&mut op.right);
+}
+
+pub fn walk_tag_override<V: VisitorMut + ?Sized>(v: &mut V, tag: &mut TagOverride) {
+    v.visit_expression(&mut tag.operand);
+}
+
+pub fn walk_call<V: VisitorMut + ?Sized>(v: &mut V, call: &mut Call) {
+    v.visit_expression(&mut call.function);
+    for argument in &mut call.arguments {
+        v.visit_argument(argument);
+    }
+}
+
+pub fn walk_argument<V: VisitorMut + ?Sized>(v: &mut V, argument: &mut Argument) {
+    match argument {
+        Argument::Positional(expr) => v.visit_expression(expr),
+        Argument::Named { value, .. } => v.visit_expression(value),
+        Argument::Skipped => {}
+    }
+}
+
+pub fn walk_index<V: VisitorMut + ?Sized>(v: &mut V, index: &mut Index) {
+    v.visit_expression(&mut index.array);
+    v.visit_expression(&mut index.index);
+}
+
+pub fn walk_literal<V: VisitorMut + ?Sized>(v: &mut V, literal: &mut Literal) {
+    match literal {
+        Literal::Array(array) => v.visit_array(array),
+        Literal::Integer(_)
+        | Literal::Float(_)
+        | Literal::Bool(_)
+        | Literal::Char(_)
+        | Literal::String(_) => {}
+    }
+}
+
+pub fn walk_array<V: VisitorMut + ?Sized>(v: &mut V, array: &mut Array) {
+    for element in &mut array.elements {
+        v.visit_expression(element);
+    }
+}
+
+pub fn walk_if<V: VisitorMut + ?Sized>(v: &mut V, s: &mut If) {
+    v.visit_expression(&mut s.condition);
+    v.visit_statement(&mut s.body);
+    if let Some(otherwise) = &mut s.otherwise {
+        v.visit_statement(otherwise);
+    }
+}
+
+pub fn walk_while<V: VisitorMut + ?Sized>(v: &mut V, s: &mut While) {
+    v.visit_expression(&mut s.condition);
+    v.visit_statement(&mut s.body);
+}
+
+pub fn walk_do_while<V: VisitorMut + ?Sized>(v: &mut V, s: &mut DoWhile) {
+    v.visit_statement(&mut s.body);
+    v.visit_expression(&mut s.condition);
+}
+
+pub fn walk_for<V: VisitorMut + ?Sized>(v: &mut V, s: &mut For) {
+    if let Some(initialiser) = &mut s.initialiser {
+        v.visit_statement(initialiser);
+    }
+    if let Some(condition) = &mut s.condition {
+        v.visit_expression(condition);
+    }
+    if let Some(increment) = &mut s.increment {
+        v.visit_expression(increment);
+    }
+    v.visit_statement(&mut s.body);
+}
+
+pub fn walk_switch<V: VisitorMut + ?Sized>(v: &mut V, s: &mut Switch) {
+    v.visit_expression(&mut s.subject);
+    for case in &mut s.cases {
+        v.visit_case(case);
+    }
+    if let Some(default) = &mut s.default {
+        v.visit_statement(default);
+    }
+}
+
+pub fn walk_case<V: VisitorMut + ?Sized>(v: &mut V, case: &mut Case) {
+    for label in &mut case.labels {
+        v.visit_case_label(label);
+    }
+    v.visit_statement(&mut case.body);
+}
+
+pub fn walk_case_label<V: VisitorMut + ?Sized>(v: &mut V, label: &mut CaseLabel) {
+    match label {
+        CaseLabel::Value(value) => v.visit_expression(value),
+        CaseLabel::Range(from, to) => {
+            v.visit_expression(from);
+            v.visit_expression(to);
+        }
+    }
+}
+
+pub fn walk_state_transition<V: VisitorMut + ?Sized>(v: &mut V, s: &mut StateTransition) {
+    if let Some(condition) = &mut s.condition {
+        v.visit_expression(condition);
+    }
+}
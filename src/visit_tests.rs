@@ -0,0 +1,131 @@
+#[cfg(test)]
+use crate::ast::{Call, Expression, Spanned};
+#[cfg(test)]
+use crate::fold::Fold;
+#[cfg(test)]
+use crate::test_utils::parse;
+#[cfg(test)]
+use crate::visit::{self, Visitor};
+#[cfg(test)]
+use crate::visit_mut::VisitorMut;
+
+#[cfg(test)]
+struct CallCounter {
+    calls: Vec<String>,
+}
+
+#[cfg(test)]
+impl Visitor for CallCounter {
+    fn visit_call(&mut self, call: &Call) {
+        if let Expression::Symbol(name) = &call.function.node {
+            self.calls.push(name.clone());
+        }
+        visit::walk_call(self, call);
+    }
+}
+
+#[test]
+fn test_visitor_counts_calls() {
+    let root = parse(
+        "
+new gValue = Init();
+main() {
+    new a[Size()] = {1, 2};
+    if (Check(a[0])) {
+        Print(Format(a, .value = Get()));
+    }
+    switch (Mode()) {
+        case 1: Reset();
+        default: for (new i = Start(); i < 10; i++) Step(i);
+    }
+    return Done() ? 1 : 0;
+}
+",
+    );
+
+    let mut counter = CallCounter { calls: vec![] };
+    counter.visit_node(&root);
+    assert_eq!(
+        counter.calls,
+        vec![
+            "Init", "Size", "Check", "Print", "Format", "Get", "Mode", "Reset", "Start", "Step",
+            "Done"
+        ]
+    );
+}
+
+// Starts records where each expression visited starts, without going into
+// it.
+#[cfg(test)]
+struct Starts(Vec<(i32, i32)>);
+
+#[cfg(test)]
+impl Visitor for Starts {
+    fn visit_expression(&mut self, expr: &Spanned<Expression>) {
+        self.0.push((expr.span.line_start, expr.span.column_start));
+    }
+}
+
+#[test]
+fn test_visitor_visits_initialisers() {
+    let root = parse("new a = b + 1;\nmain() {\n    new c[2] = {1, 2};\n}");
+
+    // an initialiser is an expression like any other, with a span of its own
+    let mut starts = Starts(vec![]);
+    starts.visit_node(&root);
+    assert_eq!(starts.0, vec![(1, 9), (3, 11), (3, 16)]);
+}
+
+#[cfg(test)]
+struct Renamer;
+
+#[cfg(test)]
+impl Fold for Renamer {
+    fn fold_symbol(&mut self, name: String) -> String {
+        match name.as_str() {
+            "old" => "new_name".into(),
+            _ => name,
+        }
+    }
+}
+
+#[cfg(test)]
+struct Symbols(Vec<String>);
+
+#[cfg(test)]
+impl Visitor for Symbols {
+    fn visit_symbol(&mut self, name: &str) {
+        self.0.push(name.into());
+    }
+}
+
+#[test]
+fn test_fold_renames_symbols() {
+    let root = parse("main() {\n    old = old + other(old, .x = old);\n    return old;\n}");
+
+    let folded = Renamer.fold_node(root);
+    let mut symbols = Symbols(vec![]);
+    symbols.visit_node(&folded);
+    assert_eq!(
+        symbols.0,
+        vec!["new_name", "new_name", "other", "new_name", "new_name", "new_name"]
+    );
+    assert_eq!(folded.children[0].span.line_end, 4);
+}
+
+#[cfg(test)]
+struct Upper;
+
+#[cfg(test)]
+impl VisitorMut for Upper {
+    fn visit_symbol(&mut self, name: &mut String) {
+        *name = name.to_uppercase();
+    }
+}
+
+#[test]
+fn test_visitor_mut_renames_in_place() {
+    let mut root = parse("new a = b * c[d];");
+    Upper.visit_node(&mut root);
+    assert_eq!(root, parse("new a = B * C[D];"));
+}
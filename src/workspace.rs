@@ -0,0 +1,144 @@
+use crate::arena::Ast;
+use crate::ast::Node;
+use crate::ast::Parser;
+use crate::error::Diagnostic;
+use crate::intern::Interner;
+use crate::lexer::Lexer;
+use crate::resolve::Resolved;
+use crate::resolve::Resolver;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// SourceFile is a file added to a workspace.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+}
+
+/// ParsedFile is the result of parsing one file of a workspace. `ast` is the
+/// arena built from `root`, which the passes such as `Session::resolve` walk,
+/// its names are symbols of the interner of the session.
+#[derive(Debug)]
+pub struct ParsedFile {
+    pub name: String,
+    pub root: Node,
+    pub ast: Ast,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Workspace is a set of files to be parsed together. Files are parsed
+/// independently, the preprocessor is not run so includes are not followed.
+#[derive(Debug, Default)]
+pub struct Workspace {
+    files: Vec<SourceFile>,
+}
+
+impl Workspace {
+    pub fn new() -> Workspace {
+        Workspace::default()
+    }
+
+    pub fn add(&mut self, name: &str, source: &str) {
+        self.files.push(SourceFile {
+            name: String::from(name),
+            source: String::from(source),
+        });
+    }
+
+    /// load reads a file from disk and adds it, named by its path. Legacy
+    /// scripts are often not UTF-8 so invalid sequences are replaced rather
+    /// than treated as an error.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        self.files.push(SourceFile {
+            name: path.display().to_string(),
+            source: String::from_utf8_lossy(&bytes).into_owned(),
+        });
+        Ok(())
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// parse parses every file on the current thread.
+    pub fn parse(&self) -> Session {
+        Session::new(self.files.iter().map(parse_file).collect())
+    }
+
+    /// parse_parallel parses the files on the rayon thread pool. The result
+    /// is the same as `parse`, whatever order the files finish in.
+    #[cfg(feature = "parallel")]
+    pub fn parse_parallel(&self) -> Session {
+        use rayon::prelude::*;
+
+        Session::new(self.files.par_iter().map(parse_file).collect())
+    }
+}
+
+/// Session holds the parsed files of a workspace, in the order they were
+/// added, along with the interner their symbols belong to.
+#[derive(Debug)]
+pub struct Session {
+    pub interner: Interner,
+    pub files: Vec<ParsedFile>,
+}
+
+impl Session {
+    // new merges the interner each file was parsed with into one, in the
+    // order the files were added, so the numbering does not depend on which
+    // thread parsed what. Each file interned its names once however often
+    // they are used, so this is one lookup per distinct name in a file.
+    fn new(parsed: Vec<(ParsedFile, Interner)>) -> Session {
+        let mut interner = Interner::new();
+        let files = parsed
+            .into_iter()
+            .map(|(mut file, local)| {
+                file.ast.remap(&interner.merge(&local));
+                file
+            })
+            .collect();
+        Session { interner, files }
+    }
+
+    pub fn file(&self, name: &str) -> Option<&ParsedFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    /// diagnostics returns every diagnostic along with the name of the file
+    /// it is in.
+    pub fn diagnostics(&self) -> impl Iterator<Item = (&str, &Diagnostic)> {
+        self.files
+            .iter()
+            .flat_map(|f| f.diagnostics.iter().map(move |d| (f.name.as_str(), d)))
+    }
+
+    /// resolve resolves the names used in every file together, each file
+    /// having its own scope for `static` declarations, see
+    /// `SymbolTable::file`. The nodes of the declarations and references are
+    /// those of the `ast` of each file, and their names are symbols of the
+    /// session's interner, which the predefined names are added to.
+    pub fn resolve(&mut self, resolver: &Resolver) -> Resolved {
+        let asts: Vec<&Ast> = self.files.iter().map(|f| &f.ast).collect();
+        resolver.resolve(&asts, &mut self.interner)
+    }
+}
+
+// parse_file lexes and parses a single file, interning its names with an
+// interner of its own to be merged once every file is done.
+fn parse_file(file: &SourceFile) -> (ParsedFile, Interner) {
+    let mut parser = Parser::new(Lexer::new(&file.source).lex());
+    let diagnostics = parser.parse().err().unwrap_or_default();
+    let mut interner = Interner::new();
+    let parsed = ParsedFile {
+        name: file.name.clone(),
+        ast: Ast::new(&parser.root, &mut interner),
+        root: parser.root,
+        diagnostics,
+    };
+    (parsed, interner)
+}
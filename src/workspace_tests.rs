@@ -0,0 +1,91 @@
+#[cfg(test)]
+use crate::arena::NodeKind;
+#[cfg(test)]
+use crate::intern::Symbol;
+#[cfg(test)]
+use crate::workspace::{Session, Workspace};
+
+#[cfg(test)]
+fn workspace() -> Workspace {
+    let mut workspace = Workspace::new();
+    for i in 0..32 {
+        workspace.add(
+            &format!("file{}.pwn", i),
+            &format!(
+                "new gValue{};\nstock Get{}(playerid) {{\n    return gValue{} + playerid;\n}}\n",
+                i, i, i
+            ),
+        );
+    }
+    workspace.add("broken.pwn", "new a = ;\nmain() {}\n");
+    workspace
+}
+
+// symbols lists the names declared or used in a file once each, in the order
+// they first appear.
+#[cfg(test)]
+fn symbols(session: &Session, file: usize) -> Vec<Symbol> {
+    let ast = &session.files[file].ast;
+    let mut symbols = vec![];
+    for id in ast.ids() {
+        let symbol = match ast.kind(id) {
+            NodeKind::Variable { name, .. }
+            | NodeKind::Function { name, .. }
+            | NodeKind::Symbol(name) => *name,
+            NodeKind::Parameter {
+                name: Some(name), ..
+            } => *name,
+            _ => continue,
+        };
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    symbols
+}
+
+#[cfg(test)]
+fn names(session: &Session, file: usize) -> Vec<&str> {
+    symbols(session, file)
+        .into_iter()
+        .map(|s| session.interner.resolve(s))
+        .collect()
+}
+
+#[test]
+fn test_workspace_parse() {
+    let session = workspace().parse();
+
+    assert_eq!(session.files.len(), 33);
+    assert_eq!(session.files[3].name, "file3.pwn");
+    assert_eq!(session.files[3].root.children.len(), 2);
+    assert_eq!(names(&session, 3), vec!["gValue3", "Get3", "playerid"]);
+
+    // symbols are numbered in file order, and shared between files
+    assert_eq!(symbols(&session, 0)[2].index(), 2);
+    assert_eq!(symbols(&session, 1)[2], symbols(&session, 0)[2]);
+
+    let diagnostics: Vec<_> = session.diagnostics().collect();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].0, "broken.pwn");
+    assert!(session.file("broken.pwn").is_some());
+}
+
+#[cfg(all(test, feature = "parallel"))]
+#[test]
+fn test_workspace_parse_parallel() {
+    let workspace = workspace();
+    let single = workspace.parse();
+
+    for _ in 0..10 {
+        let parallel = workspace.parse_parallel();
+        assert_eq!(parallel.files.len(), single.files.len());
+        for (a, b) in single.files.iter().zip(&parallel.files) {
+            assert_eq!(a.name, b.name);
+            assert!(a.root == b.root);
+            assert_eq!(a.diagnostics, b.diagnostics);
+            assert!(a.ast == b.ast);
+        }
+        assert_eq!(parallel.interner.len(), single.interner.len());
+    }
+}